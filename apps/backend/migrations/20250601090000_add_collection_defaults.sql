@@ -0,0 +1,16 @@
+-- Add migration script here
+-- Collection level defaults that are inherited by every request in the collection
+ALTER TABLE collections
+    ADD COLUMN base_url TEXT,                                   -- Prepended to relative request URLs
+    ADD COLUMN default_headers JSONB NOT NULL DEFAULT '{}',     -- Headers sent with every request
+    ADD COLUMN default_params JSONB NOT NULL DEFAULT '{}',      -- Query parameters sent with every request
+    ADD COLUMN auth JSONB,                                      -- Default auth configuration
+    ADD COLUMN settings JSONB NOT NULL DEFAULT '{}';            -- Default execution settings
+
+-- Requests can override the inherited auth and execution settings
+ALTER TABLE requests
+    ADD COLUMN auth JSONB,
+    ADD COLUMN settings JSONB NOT NULL DEFAULT '{}';
+
+-- Relative URLs and long absolute URLs should both fit
+ALTER TABLE requests ALTER COLUMN url TYPE TEXT;
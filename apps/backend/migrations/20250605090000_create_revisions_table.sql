@@ -0,0 +1,23 @@
+-- Add migration script here
+-- Full snapshots of requests and collections, one row per change
+CREATE TABLE IF NOT EXISTS revisions (
+    id UUID PRIMARY KEY,
+    entity_type VARCHAR(20) NOT NULL,                       -- 'request' or 'collection'
+    entity_id UUID NOT NULL,                                -- ID of the request or collection
+    version INTEGER NOT NULL,                               -- Increments per entity, starting at 1
+    snapshot JSONB NOT NULL,                                -- Full state of the entity after the change
+    user_id UUID REFERENCES users(id) ON DELETE SET NULL,   -- User who made the change
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (entity_type, entity_id, version)
+);
+
+CREATE INDEX idx_revisions_entity ON revisions(entity_type, entity_id);
+
+-- Existing rows start their history with their current state
+INSERT INTO revisions (id, entity_type, entity_id, version, snapshot, user_id, created_at)
+SELECT gen_random_uuid(), 'request', r.id, 1, to_jsonb(r), r.user_id, r.updated_at
+FROM requests r;
+
+INSERT INTO revisions (id, entity_type, entity_id, version, snapshot, user_id, created_at)
+SELECT gen_random_uuid(), 'collection', c.id, 1, to_jsonb(c), c.user_id, c.updated_at
+FROM collections c;
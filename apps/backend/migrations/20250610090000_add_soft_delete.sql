@@ -0,0 +1,8 @@
+-- Add migration script here
+-- Deleted requests and collections stay in the trash until restored or purged
+ALTER TABLE collections ADD COLUMN deleted_at TIMESTAMP;
+ALTER TABLE requests ADD COLUMN deleted_at TIMESTAMP;
+
+-- The trash listing and the purge job only look at deleted rows
+CREATE INDEX idx_collections_deleted_at ON collections(deleted_at) WHERE deleted_at IS NOT NULL;
+CREATE INDEX idx_requests_deleted_at ON requests(deleted_at) WHERE deleted_at IS NOT NULL;
@@ -0,0 +1,14 @@
+-- Add migration script here
+-- Every execution of a saved request together with the response it received
+CREATE TABLE IF NOT EXISTS executions (
+    id UUID PRIMARY KEY,
+    request_id UUID NOT NULL REFERENCES requests(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,  -- User who ran the request
+    status INTEGER NOT NULL,                                        -- HTTP status of the response
+    headers JSONB NOT NULL,                                         -- Response headers
+    body JSONB NOT NULL,                                            -- Response body
+    duration_ms BIGINT NOT NULL,                                    -- Time until the response was read
+    executed_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_executions_request_id ON executions(request_id, executed_at DESC);
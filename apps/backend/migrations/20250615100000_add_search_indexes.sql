@@ -0,0 +1,40 @@
+-- Add migration script here
+-- Full-text and trigram search over requests, collections and stored responses
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+-- Space separated keys of a JSON object, used to search header names
+CREATE OR REPLACE FUNCTION jsonb_keys_text(data JSONB) RETURNS TEXT
+LANGUAGE SQL IMMUTABLE AS $$
+    SELECT COALESCE(string_agg(key, ' '), '')
+    FROM jsonb_object_keys(CASE WHEN jsonb_typeof(data) = 'object' THEN data ELSE '{}' END) AS key
+$$;
+
+-- Searchable document of a request, name matches rank above everything else
+CREATE OR REPLACE FUNCTION request_search_vector(
+    name TEXT, description TEXT, url TEXT, headers JSONB, body JSONB
+) RETURNS tsvector
+LANGUAGE SQL IMMUTABLE AS $$
+    SELECT setweight(to_tsvector('simple', COALESCE(name, '')), 'A')
+        || setweight(to_tsvector('simple', COALESCE(description, '')), 'B')
+        || setweight(to_tsvector('simple', COALESCE(url, '')), 'B')
+        || setweight(to_tsvector('simple', jsonb_keys_text(headers)), 'C')
+        || setweight(to_tsvector('simple', COALESCE(body::TEXT, '')), 'D')
+$$;
+
+CREATE OR REPLACE FUNCTION collection_search_vector(name TEXT, description TEXT) RETURNS tsvector
+LANGUAGE SQL IMMUTABLE AS $$
+    SELECT setweight(to_tsvector('simple', COALESCE(name, '')), 'A')
+        || setweight(to_tsvector('simple', COALESCE(description, '')), 'B')
+$$;
+
+CREATE INDEX idx_requests_search ON requests
+    USING GIN (request_search_vector(name, description, url, headers, body));
+CREATE INDEX idx_requests_name_trgm ON requests USING GIN (name gin_trgm_ops);
+CREATE INDEX idx_requests_url_trgm ON requests USING GIN (url gin_trgm_ops);
+
+CREATE INDEX idx_collections_search ON collections
+    USING GIN (collection_search_vector(name, description));
+CREATE INDEX idx_collections_name_trgm ON collections USING GIN (name gin_trgm_ops);
+
+CREATE INDEX idx_executions_search ON executions
+    USING GIN (to_tsvector('simple', body::TEXT));
@@ -0,0 +1,39 @@
+-- Add migration script here
+-- Labels such as `smoke` or `billing` that can be attached to requests and collections
+CREATE TABLE IF NOT EXISTS tags (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name VARCHAR(50) NOT NULL,
+    color VARCHAR(20),                                     -- Optional display color
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (user_id, name)
+);
+
+CREATE TABLE IF NOT EXISTS request_tags (
+    request_id UUID NOT NULL REFERENCES requests(id) ON DELETE CASCADE,
+    tag_id UUID NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+    PRIMARY KEY (request_id, tag_id)
+);
+
+CREATE TABLE IF NOT EXISTS collection_tags (
+    collection_id UUID NOT NULL REFERENCES collections(id) ON DELETE CASCADE,
+    tag_id UUID NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+    PRIMARY KEY (collection_id, tag_id)
+);
+
+CREATE INDEX idx_request_tags_tag_id ON request_tags(tag_id);
+CREATE INDEX idx_collection_tags_tag_id ON collection_tags(tag_id);
+
+-- Requests and collections pinned by a user
+CREATE TABLE IF NOT EXISTS favorites (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    request_id UUID REFERENCES requests(id) ON DELETE CASCADE,
+    collection_id UUID REFERENCES collections(id) ON DELETE CASCADE,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    CHECK ((request_id IS NULL) <> (collection_id IS NULL))  -- Exactly one target
+);
+
+CREATE UNIQUE INDEX idx_favorites_request ON favorites(user_id, request_id) WHERE request_id IS NOT NULL;
+CREATE UNIQUE INDEX idx_favorites_collection ON favorites(user_id, collection_id) WHERE collection_id IS NOT NULL;
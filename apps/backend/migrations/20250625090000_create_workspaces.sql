@@ -0,0 +1,79 @@
+-- Add migration script here
+-- Workspaces group the collections, requests, environments and tags shared by their members
+CREATE TABLE IF NOT EXISTS workspaces (
+    id UUID PRIMARY KEY,
+    name VARCHAR(255) NOT NULL,
+    description TEXT,
+    is_personal BOOLEAN NOT NULL DEFAULT FALSE,            -- Created with the account, cannot be deleted
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE TABLE IF NOT EXISTS workspace_members (
+    workspace_id UUID NOT NULL REFERENCES workspaces(id) ON DELETE CASCADE,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    role VARCHAR(20) NOT NULL DEFAULT 'member',            -- owner or member
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (workspace_id, user_id)
+);
+
+CREATE INDEX idx_workspace_members_user_id ON workspace_members(user_id);
+
+-- Give every existing user a personal workspace
+WITH personal AS (
+    SELECT id AS user_id, gen_random_uuid() AS workspace_id FROM users
+), created AS (
+    INSERT INTO workspaces (id, name, is_personal)
+    SELECT workspace_id, 'Personal', TRUE FROM personal
+)
+INSERT INTO workspace_members (workspace_id, user_id, role)
+SELECT workspace_id, user_id, 'owner' FROM personal;
+
+-- Move the existing data into the personal workspace of its owner
+ALTER TABLE collections ADD COLUMN workspace_id UUID REFERENCES workspaces(id) ON DELETE CASCADE;
+ALTER TABLE requests ADD COLUMN workspace_id UUID REFERENCES workspaces(id) ON DELETE CASCADE;
+ALTER TABLE tags ADD COLUMN workspace_id UUID REFERENCES workspaces(id) ON DELETE CASCADE;
+
+UPDATE collections c SET workspace_id = m.workspace_id
+FROM workspace_members m JOIN workspaces w ON w.id = m.workspace_id AND w.is_personal
+WHERE m.user_id = c.user_id;
+
+UPDATE requests r SET workspace_id = m.workspace_id
+FROM workspace_members m JOIN workspaces w ON w.id = m.workspace_id AND w.is_personal
+WHERE m.user_id = r.user_id;
+
+UPDATE tags t SET workspace_id = m.workspace_id
+FROM workspace_members m JOIN workspaces w ON w.id = m.workspace_id AND w.is_personal
+WHERE m.user_id = t.user_id;
+
+ALTER TABLE collections ALTER COLUMN workspace_id SET NOT NULL;
+ALTER TABLE requests ALTER COLUMN workspace_id SET NOT NULL;
+ALTER TABLE tags ALTER COLUMN workspace_id SET NOT NULL;
+
+CREATE INDEX idx_collections_workspace_id ON collections(workspace_id);
+CREATE INDEX idx_requests_workspace_id ON requests(workspace_id);
+
+-- Tag names are unique within a workspace, user_id now records who created the tag
+ALTER TABLE tags DROP CONSTRAINT tags_user_id_name_key;
+ALTER TABLE tags ADD CONSTRAINT tags_workspace_id_name_key UNIQUE (workspace_id, name);
+
+-- Sets of variables such as staging or production
+CREATE TABLE IF NOT EXISTS environments (
+    id UUID PRIMARY KEY,
+    workspace_id UUID NOT NULL REFERENCES workspaces(id) ON DELETE CASCADE,
+    name VARCHAR(255) NOT NULL,
+    variables JSONB NOT NULL DEFAULT '{}',                 -- JSON object of variable names to values
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (workspace_id, name)
+);
+
+-- Older snapshots predate workspaces, record the workspace so they can still be restored
+UPDATE revisions rv SET snapshot = rv.snapshot || jsonb_build_object('workspace_id', r.workspace_id)
+FROM requests r
+WHERE rv.entity_type = 'request' AND rv.entity_id = r.id;
+
+UPDATE revisions rv SET snapshot = rv.snapshot || jsonb_build_object('workspace_id', c.workspace_id)
+FROM collections c
+WHERE rv.entity_type = 'collection' AND rv.entity_id = c.id;
@@ -0,0 +1,7 @@
+-- Add migration script here
+-- Members added before roles existed could edit everything
+UPDATE workspace_members SET role = 'editor' WHERE role = 'member';
+
+ALTER TABLE workspace_members ALTER COLUMN role SET DEFAULT 'editor';
+ALTER TABLE workspace_members ADD CONSTRAINT workspace_members_role_check
+    CHECK (role IN ('owner', 'admin', 'editor', 'runner', 'viewer'));
@@ -0,0 +1,33 @@
+-- Add migration script here
+CREATE TABLE workspace_invitations (
+    id UUID PRIMARY KEY,
+    workspace_id UUID NOT NULL REFERENCES workspaces(id) ON DELETE CASCADE,
+    email VARCHAR(255) NOT NULL,
+    role VARCHAR(20) NOT NULL CHECK (role IN ('admin', 'editor', 'runner', 'viewer')),
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+    status VARCHAR(20) NOT NULL DEFAULT 'pending'
+        CHECK (status IN ('pending', 'accepted', 'declined', 'revoked')),
+    invited_by UUID REFERENCES users(id) ON DELETE SET NULL,
+    expires_at TIMESTAMP NOT NULL,
+    responded_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- At most one open invitation per email and workspace
+CREATE UNIQUE INDEX idx_workspace_invitations_pending
+    ON workspace_invitations(workspace_id, LOWER(email)) WHERE status = 'pending';
+
+CREATE INDEX idx_workspace_invitations_email ON workspace_invitations(LOWER(email));
+
+-- Record of membership changes, kept when the workspace or the actor is deleted
+CREATE TABLE audit_log (
+    id UUID PRIMARY KEY,
+    workspace_id UUID,
+    actor_id UUID,
+    action VARCHAR(100) NOT NULL,
+    target_id UUID,
+    details JSONB NOT NULL DEFAULT '{}',
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_audit_log_workspace ON audit_log(workspace_id, created_at DESC);
@@ -0,0 +1,24 @@
+-- Add migration script here
+ALTER TABLE audit_log
+    ADD COLUMN ip_address VARCHAR(64),
+    ADD COLUMN user_agent TEXT,
+    ADD COLUMN before JSONB,
+    ADD COLUMN after JSONB;
+
+CREATE INDEX idx_audit_log_actor ON audit_log(actor_id, created_at DESC);
+CREATE INDEX idx_audit_log_action ON audit_log(action);
+
+-- The audit log is append-only, entries can never be changed or removed
+CREATE FUNCTION prevent_audit_log_changes() RETURNS TRIGGER AS $$
+BEGIN
+    RAISE EXCEPTION 'audit_log is append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER audit_log_append_only
+    BEFORE UPDATE OR DELETE ON audit_log
+    FOR EACH ROW EXECUTE FUNCTION prevent_audit_log_changes();
+
+CREATE TRIGGER audit_log_no_truncate
+    BEFORE TRUNCATE ON audit_log
+    FOR EACH STATEMENT EXECUTE FUNCTION prevent_audit_log_changes();
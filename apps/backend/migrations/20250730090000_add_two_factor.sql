@@ -0,0 +1,36 @@
+-- Add migration script here
+-- The secret is set on setup, two-factor authentication is on once a code confirms it
+ALTER TABLE users ADD COLUMN totp_secret VARCHAR(64);
+ALTER TABLE users ADD COLUMN totp_enabled_at TIMESTAMP;
+ALTER TABLE users ADD COLUMN totp_last_step BIGINT; -- Time step of the last accepted code, codes work once
+
+-- Single-use codes for signing in without the authenticator app
+CREATE TABLE recovery_codes (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    code_hash VARCHAR(64) NOT NULL,
+    used_at TIMESTAMP,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX idx_recovery_codes_user ON recovery_codes(user_id);
+
+-- Handed out after the password when the second factor is still missing
+CREATE TABLE login_challenges (
+    id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+    attempts INTEGER NOT NULL DEFAULT 0,
+    expires_at TIMESTAMP NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+ALTER TABLE workspaces ADD COLUMN require_2fa BOOLEAN NOT NULL DEFAULT FALSE;
+
+-- Memberships that grant access right now, workspaces can require members to use 2FA
+CREATE VIEW workspace_access AS
+SELECT m.workspace_id, m.user_id, m.role
+FROM workspace_members m
+JOIN workspaces w ON w.id = m.workspace_id
+JOIN users u ON u.id = m.user_id
+WHERE NOT w.require_2fa OR u.totp_enabled_at IS NOT NULL;
@@ -0,0 +1,4 @@
+-- Add migration script here
+-- Failed logins in a row, the account locks once they pile up
+ALTER TABLE users ADD COLUMN failed_login_count INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE users ADD COLUMN locked_until TIMESTAMP;
@@ -0,0 +1,24 @@
+-- Add migration script here
+-- One data key per workspace, stored wrapped with a master key from the config
+CREATE TABLE workspace_keys (
+    workspace_id UUID PRIMARY KEY REFERENCES workspaces(id) ON DELETE CASCADE,
+    version INTEGER NOT NULL DEFAULT 1,     -- Goes up with every rotation
+    master_key_id VARCHAR(16) NOT NULL,     -- Fingerprint of the master key that wrapped it
+    wrapped_key BYTEA NOT NULL,             -- Nonce followed by the encrypted key
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    rotated_at TIMESTAMP
+);
+
+-- Secret variables, referenced as {{secrets.NAME}} and only decrypted to send a request
+CREATE TABLE secrets (
+    id UUID PRIMARY KEY,
+    workspace_id UUID NOT NULL REFERENCES workspaces(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    description TEXT,
+    ciphertext BYTEA NOT NULL,              -- Nonce followed by the encrypted value
+    key_version INTEGER NOT NULL,           -- Version of the data key it is encrypted with
+    user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (workspace_id, name)
+);
@@ -0,0 +1,5 @@
+-- Proxy of the workspace, used when the request and its collection pick none
+ALTER TABLE workspaces ADD COLUMN proxy JSONB;
+
+-- Proxy an execution went through, without its credentials
+ALTER TABLE executions ADD COLUMN proxy JSONB;
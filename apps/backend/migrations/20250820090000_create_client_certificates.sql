@@ -0,0 +1,20 @@
+-- Client certificates for mutual TLS, presented to the hosts matching their patterns
+CREATE TABLE client_certificates (
+    id UUID PRIMARY KEY,
+    workspace_id UUID NOT NULL REFERENCES workspaces(id) ON DELETE CASCADE,
+    name VARCHAR(100) NOT NULL,
+    host_patterns TEXT[] NOT NULL,          -- Like api.partner.com or *.partner.com
+    format VARCHAR(10) NOT NULL,            -- pem or pkcs12, as uploaded
+    subject TEXT NOT NULL,
+    issuer TEXT NOT NULL,
+    expires_at TIMESTAMP NOT NULL,
+    ciphertext BYTEA NOT NULL,              -- Key and chain as PEM, encrypted like the secrets
+    key_version INTEGER NOT NULL,           -- Version of the data key it is encrypted with
+    user_id UUID REFERENCES users(id) ON DELETE SET NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE (workspace_id, name)
+);
+
+-- The certificate an execution presented, kept when the certificate is deleted later
+ALTER TABLE executions ADD COLUMN client_certificate_id UUID;
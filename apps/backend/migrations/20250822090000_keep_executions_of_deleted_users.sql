@@ -0,0 +1,5 @@
+-- Executions stay in the history of shared workspaces when the user who ran them deletes the account
+ALTER TABLE executions ALTER COLUMN user_id DROP NOT NULL;
+ALTER TABLE executions DROP CONSTRAINT executions_user_id_fkey;
+ALTER TABLE executions
+    ADD CONSTRAINT executions_user_id_fkey FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE SET NULL;
@@ -0,0 +1,23 @@
+-- Only the start of a JSON body is searched, a tsvector cannot hold more than 1MB
+CREATE OR REPLACE FUNCTION body_search_text(body JSONB) RETURNS TEXT
+LANGUAGE SQL IMMUTABLE AS $$
+    SELECT left(COALESCE(body::TEXT, ''), 100000)
+$$;
+
+CREATE OR REPLACE FUNCTION request_search_vector(
+    name TEXT, description TEXT, url TEXT, headers JSONB, body JSONB
+) RETURNS tsvector
+LANGUAGE SQL IMMUTABLE AS $$
+    SELECT setweight(to_tsvector('simple', COALESCE(name, '')), 'A')
+        || setweight(to_tsvector('simple', COALESCE(description, '')), 'B')
+        || setweight(to_tsvector('simple', COALESCE(url, '')), 'B')
+        || setweight(to_tsvector('simple', jsonb_keys_text(headers)), 'C')
+        || setweight(to_tsvector('simple', body_search_text(body)), 'D')
+$$;
+
+-- The function changed, so the index is built again
+REINDEX INDEX idx_requests_search;
+
+DROP INDEX idx_executions_search;
+CREATE INDEX idx_executions_search ON executions
+    USING GIN (to_tsvector('simple', body_search_text(body)));
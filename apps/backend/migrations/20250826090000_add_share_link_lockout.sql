@@ -0,0 +1,3 @@
+-- Wrong passwords in a row, a protected link locks once they pile up
+ALTER TABLE share_links ADD COLUMN failed_password_count INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE share_links ADD COLUMN locked_until TIMESTAMP;
@@ -1,24 +1,45 @@
 use actix_web::{
     dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
-    Error, HttpMessage,
+    web, Error, HttpMessage,
 };
 use futures::future::{ready, LocalBoxFuture, Ready};
-use jsonwebtoken::{decode, DecodingKey, Validation};
 use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
 use std::rc::Rc;
 use uuid::Uuid;
 
-use crate::config::Config;
 use crate::error::AppError;
+use crate::utils::api_key::{authenticate_api_key, required_scope};
+use crate::utils::jwt::JwtKeys;
+use crate::utils::session::is_session_active;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String, // User ID
+    pub sid: String, // Session ID
+    pub iss: String, // Issuer
+    pub aud: String, // Audience
+    pub iat: usize,  // Issued at
     pub exp: usize,  // Expiration time
 }
 
+// Session the access token of the request belongs to
+#[derive(Debug, Clone, Copy)]
+pub struct SessionId(pub Uuid);
+
+// API key the request was made with, requests with an access token have none
+#[derive(Debug, Clone, Copy)]
+pub struct ApiKeyId(pub Uuid);
+
+// Header scripts can send the API key in instead of `Authorization: ApiKey ...`
+const API_KEY_HEADER: &str = "X-API-Key";
+
+// Accepts access tokens and API keys
 pub struct Auth;
 
+// Only accepts access tokens, for managing the account itself
+pub struct SessionAuth;
+
 impl<S, B> Transform<S, ServiceRequest> for Auth
 where
     S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
@@ -34,12 +55,34 @@ where
     fn new_transform(&self, service: S) -> Self::Future {
         ready(Ok(AuthMiddleware {
             service: Rc::new(service),
+            allow_api_keys: true,
+        }))
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for SessionAuth
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = AuthMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(AuthMiddleware {
+            service: Rc::new(service),
+            allow_api_keys: false,
         }))
     }
 }
 
 pub struct AuthMiddleware<S> {
     service: Rc<S>,
+    allow_api_keys: bool,
 }
 
 impl<S, B> Service<ServiceRequest> for AuthMiddleware<S>
@@ -56,11 +99,54 @@ where
 
     fn call(&self, req: ServiceRequest) -> Self::Future {
         let service = Rc::clone(&self.service);
+        let allow_api_keys = self.allow_api_keys;
 
         Box::pin(async move {
+            let pool = req.app_data::<web::Data<PgPool>>().ok_or_else(|| {
+                AppError::InternalServerError("Missing database pool".to_string())
+            })?;
+
             // Get the authorization header
-            let auth_header = match req.headers().get("Authorization") {
-                Some(header) => header.to_str().unwrap_or(""),
+            let auth_header = req
+                .headers()
+                .get("Authorization")
+                .map(|header| header.to_str().unwrap_or(""));
+            let api_key = match auth_header {
+                Some(header) => header.strip_prefix("ApiKey "),
+                None => req
+                    .headers()
+                    .get(API_KEY_HEADER)
+                    .map(|header| header.to_str().unwrap_or("")),
+            };
+
+            // API keys stand in for the user, limited to their scopes
+            if let Some(api_key) = api_key {
+                if !allow_api_keys {
+                    return Err(AppError::ForbiddenError(
+                        "API keys cannot be used here, sign in instead".to_string(),
+                    )
+                    .into());
+                }
+
+                let api_key = authenticate_api_key(pool.get_ref(), api_key.trim()).await?;
+
+                let scope = required_scope(req.method(), req.path());
+                if !api_key.scopes.iter().any(|s| s == scope.as_str()) {
+                    return Err(AppError::ForbiddenError(format!(
+                        "This API key does not have the {} scope",
+                        scope.as_str()
+                    ))
+                    .into());
+                }
+
+                req.extensions_mut().insert(api_key.user_id);
+                req.extensions_mut().insert(ApiKeyId(api_key.id));
+
+                return service.call(req).await;
+            }
+
+            let auth_header = match auth_header {
+                Some(header) => header,
                 None => {
                     return Err(
                         AppError::AuthError("Missing authorization header".to_string()).into(),
@@ -78,20 +164,11 @@ where
             // Extract the token
             let token = &auth_header[7..];
 
-            // Load config to get JWT secret
-            let config = Config::from_env().expect("Failed to load configuration");
-
-            // Validate and decode the token
-            let claims = match decode::<Claims>(
-                token,
-                &DecodingKey::from_secret(config.jwt_secret.as_bytes()),
-                &Validation::default(),
-            ) {
-                Ok(token_data) => token_data.claims,
-                Err(e) => {
-                    return Err(AppError::AuthError(format!("Invalid token: {}", e)).into());
-                }
-            };
+            // Validate and decode the token with the configured keys
+            let keys = req
+                .app_data::<web::Data<JwtKeys>>()
+                .ok_or_else(|| AppError::InternalServerError("Missing JWT keys".to_string()))?;
+            let claims = keys.decode_access_token(token)?;
 
             // Parse the user ID from the token
             let user_id = match Uuid::parse_str(&claims.sub) {
@@ -101,8 +178,25 @@ where
                 }
             };
 
-            // Store the user ID in the request extensions
+            let session_id = match Uuid::parse_str(&claims.sid) {
+                Ok(id) => id,
+                Err(_) => {
+                    return Err(
+                        AppError::AuthError("Invalid session ID in token".to_string()).into(),
+                    );
+                }
+            };
+
+            // Tokens of sessions that were logged out are no longer accepted
+            if !is_session_active(pool.get_ref(), session_id, user_id).await? {
+                return Err(
+                    AppError::AuthError("Session has expired or was revoked".to_string()).into(),
+                );
+            }
+
+            // Store the user and session IDs in the request extensions
             req.extensions_mut().insert(user_id);
+            req.extensions_mut().insert(SessionId(session_id));
 
             // Forward the request to the next middleware or handler
             service.call(req).await
@@ -111,6 +205,7 @@ where
 }
 
 // Helper function to extract user ID from request extensions
+#[allow(dead_code)]
 pub fn get_user_id(req: &ServiceRequest) -> Result<Uuid, AppError> {
     req.extensions()
         .get::<Uuid>()
@@ -1,3 +1,5 @@
 pub mod auth;
+pub mod rate_limit;
 
-pub use auth::{get_user_id, Auth, Claims};
+pub use auth::{ApiKeyId, Auth, Claims, SessionAuth, SessionId};
+pub use rate_limit::RateLimit;
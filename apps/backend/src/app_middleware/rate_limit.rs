@@ -0,0 +1,90 @@
+use actix_web::{
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    web, Error, HttpMessage,
+};
+use futures::future::{ready, LocalBoxFuture, Ready};
+use std::rc::Rc;
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::utils::rate_limit::{client_ip, user_key, RateLimitScope, RateLimiter};
+
+// Limits the wrapped routes per user, or per IP address before the user is known
+pub struct RateLimit {
+    scope: RateLimitScope,
+}
+
+impl RateLimit {
+    pub fn new(scope: RateLimitScope) -> Self {
+        RateLimit { scope }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimit
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Transform = RateLimitMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RateLimitMiddleware {
+            service: Rc::new(service),
+            scope: self.scope,
+        }))
+    }
+}
+
+pub struct RateLimitMiddleware<S> {
+    service: Rc<S>,
+    scope: RateLimitScope,
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimitMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let scope = self.scope;
+
+        Box::pin(async move {
+            // Without a limiter rate limiting is turned off
+            let Some(limiter) = req.app_data::<web::Data<RateLimiter>>() else {
+                return service.call(req).await;
+            };
+
+            let key = match req.extensions().get::<Uuid>() {
+                Some(user_id) => user_key(user_id),
+                None => format!(
+                    "ip:{}",
+                    client_ip(req.request(), limiter.trust_forwarded_for())
+                        .unwrap_or_else(|| "unknown".to_string())
+                ),
+            };
+
+            if let Err(retry_after) = limiter.check(scope, &key) {
+                return Err(AppError::RateLimitError(
+                    format!("Too many {} requests, try again later", scope.as_str()),
+                    retry_after,
+                )
+                .into());
+            }
+
+            service.call(req).await
+        })
+    }
+}
@@ -0,0 +1,278 @@
+// A minimal OpenID Connect provider for trying single sign-on locally.
+// Every authorization request is approved right away for MOCK_OIDC_EMAIL,
+// or for the `login_hint` when one is passed to /authorize.
+//
+//   cargo run --bin mock_oidc
+//   ENDPOINT__OIDC__ENABLED=true ENDPOINT__OIDC__ISSUER_URL=http://127.0.0.1:9090 \
+//   ENDPOINT__OIDC__CLIENT_ID=endpoint cargo run
+
+use actix_web::{http::header, web, App, HttpRequest, HttpResponse, HttpServer};
+use base64::{engine::general_purpose::STANDARD, engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use log::info;
+use rand::RngCore;
+use rsa::pkcs1::EncodeRsaPrivateKey;
+use rsa::traits::PublicKeyParts;
+use rsa::RsaPrivateKey;
+use serde::Deserialize;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::env;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use url::Url;
+
+const KEY_ID: &str = "mock-1";
+const CODE_TTL: Duration = Duration::from_secs(60);
+
+struct Provider {
+    issuer: String,
+    client_id: String,
+    client_secret: Option<String>,
+    email: String,
+    name: String,
+    email_verified: bool,
+    encoding_key: EncodingKey,
+    jwk: serde_json::Value,
+    codes: Mutex<HashMap<String, PendingCode>>,
+}
+
+// An approved authorization waiting to be traded for tokens
+struct PendingCode {
+    client_id: String,
+    redirect_uri: String,
+    nonce: Option<String>,
+    code_challenge: String,
+    email: String,
+    issued_at: Instant,
+}
+
+#[derive(Deserialize)]
+struct AuthorizeQuery {
+    response_type: String,
+    client_id: String,
+    redirect_uri: String,
+    state: Option<String>,
+    nonce: Option<String>,
+    code_challenge: Option<String>,
+    code_challenge_method: Option<String>,
+    login_hint: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TokenForm {
+    grant_type: String,
+    code: String,
+    redirect_uri: String,
+    client_id: Option<String>,
+    client_secret: Option<String>,
+    code_verifier: Option<String>,
+}
+
+async fn discovery(provider: web::Data<Provider>) -> HttpResponse {
+    let issuer = &provider.issuer;
+    HttpResponse::Ok().json(json!({
+        "issuer": issuer,
+        "authorization_endpoint": format!("{}/authorize", issuer),
+        "token_endpoint": format!("{}/token", issuer),
+        "jwks_uri": format!("{}/jwks", issuer),
+        "response_types_supported": ["code"],
+        "subject_types_supported": ["public"],
+        "id_token_signing_alg_values_supported": ["RS256"],
+        "code_challenge_methods_supported": ["S256"],
+        "token_endpoint_auth_methods_supported": ["client_secret_basic", "client_secret_post", "none"],
+        "scopes_supported": ["openid", "email", "profile"],
+    }))
+}
+
+async fn jwks(provider: web::Data<Provider>) -> HttpResponse {
+    HttpResponse::Ok().json(json!({ "keys": [provider.jwk] }))
+}
+
+async fn authorize(
+    provider: web::Data<Provider>,
+    query: web::Query<AuthorizeQuery>,
+) -> HttpResponse {
+    if query.response_type != "code" || query.client_id != provider.client_id {
+        return HttpResponse::BadRequest().body("unsupported response_type or unknown client_id");
+    }
+    let Some(code_challenge) = query
+        .code_challenge
+        .clone()
+        .filter(|_| query.code_challenge_method.as_deref() == Some("S256"))
+    else {
+        return HttpResponse::BadRequest().body("PKCE with S256 is required");
+    };
+    let Ok(mut location) = Url::parse(&query.redirect_uri) else {
+        return HttpResponse::BadRequest().body("invalid redirect_uri");
+    };
+
+    let code = random_token();
+    let email = query
+        .login_hint
+        .clone()
+        .unwrap_or_else(|| provider.email.clone());
+    info!("Approving the login of {}", email);
+
+    provider.codes.lock().unwrap().insert(
+        code.clone(),
+        PendingCode {
+            client_id: query.client_id.clone(),
+            redirect_uri: query.redirect_uri.clone(),
+            nonce: query.nonce.clone(),
+            code_challenge,
+            email,
+            issued_at: Instant::now(),
+        },
+    );
+
+    location.query_pairs_mut().append_pair("code", &code);
+    if let Some(state) = &query.state {
+        location.query_pairs_mut().append_pair("state", state);
+    }
+
+    HttpResponse::Found()
+        .insert_header((header::LOCATION, location.to_string()))
+        .finish()
+}
+
+async fn token(
+    req: HttpRequest,
+    provider: web::Data<Provider>,
+    form: web::Form<TokenForm>,
+) -> HttpResponse {
+    let error = |error: &str| HttpResponse::BadRequest().json(json!({ "error": error }));
+
+    if form.grant_type != "authorization_code" {
+        return error("unsupported_grant_type");
+    }
+    let Some(pending) = provider.codes.lock().unwrap().remove(&form.code) else {
+        return error("invalid_grant");
+    };
+
+    // Client credentials from the basic auth header or the form
+    let (client_id, client_secret) = match basic_credentials(&req) {
+        Some((id, secret)) => (Some(id), Some(secret)),
+        None => (form.client_id.clone(), form.client_secret.clone()),
+    };
+    if client_id.as_deref() != Some(pending.client_id.as_str()) {
+        return error("invalid_client");
+    }
+    if provider.client_secret.is_some() && client_secret != provider.client_secret {
+        return error("invalid_client");
+    }
+
+    let verifier_matches = form.code_verifier.as_deref().is_some_and(|verifier| {
+        URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes())) == pending.code_challenge
+    });
+    if pending.issued_at.elapsed() > CODE_TTL
+        || form.redirect_uri != pending.redirect_uri
+        || !verifier_matches
+    {
+        return error("invalid_grant");
+    }
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let subject = format!(
+        "{:x}",
+        Sha256::digest(pending.email.to_lowercase().as_bytes())
+    );
+    let claims = json!({
+        "iss": provider.issuer,
+        "sub": &subject[..24],
+        "aud": pending.client_id,
+        "iat": now,
+        "exp": now + 300,
+        "nonce": pending.nonce,
+        "email": pending.email,
+        "email_verified": provider.email_verified,
+        "name": provider.name,
+    });
+
+    let mut header = Header::new(Algorithm::RS256);
+    header.kid = Some(KEY_ID.to_string());
+    let id_token = encode(&header, &claims, &provider.encoding_key).unwrap();
+
+    HttpResponse::Ok().json(json!({
+        "access_token": random_token(),
+        "token_type": "Bearer",
+        "expires_in": 300,
+        "id_token": id_token,
+    }))
+}
+
+fn basic_credentials(req: &HttpRequest) -> Option<(String, String)> {
+    let value = req.headers().get(header::AUTHORIZATION)?.to_str().ok()?;
+    let decoded = STANDARD.decode(value.strip_prefix("Basic ")?).ok()?;
+    let (id, secret) = std::str::from_utf8(&decoded).ok()?.split_once(':')?;
+    let decode = |value: &str| {
+        url::form_urlencoded::parse(format!("v={}", value).as_bytes())
+            .next()
+            .map(|(_, value)| value.into_owned())
+    };
+    Some((decode(id)?, decode(secret)?))
+}
+
+fn random_token() -> String {
+    let mut bytes = [0u8; 24];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+#[actix_web::main]
+async fn main() -> std::io::Result<()> {
+    env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
+
+    let var = |name: &str, default: &str| env::var(name).unwrap_or_else(|_| default.to_string());
+    let host = var("MOCK_OIDC_HOST", "127.0.0.1");
+    let port = var("MOCK_OIDC_PORT", "9090");
+
+    // A fresh signing key on every start, the backend picks it up from the JWKS
+    info!("Generating the signing key");
+    let private_key = RsaPrivateKey::new(&mut rand::thread_rng(), 2048)
+        .expect("Failed to generate the signing key");
+    let pem = private_key
+        .to_pkcs1_pem(Default::default())
+        .expect("Failed to encode the signing key");
+
+    let provider = web::Data::new(Provider {
+        issuer: var("MOCK_OIDC_ISSUER", &format!("http://{}:{}", host, port)),
+        client_id: var("MOCK_OIDC_CLIENT_ID", "endpoint"),
+        client_secret: env::var("MOCK_OIDC_CLIENT_SECRET").ok(),
+        email: var("MOCK_OIDC_EMAIL", "dev@example.com"),
+        name: var("MOCK_OIDC_NAME", "Dev User"),
+        email_verified: var("MOCK_OIDC_EMAIL_VERIFIED", "true") == "true",
+        encoding_key: EncodingKey::from_rsa_pem(pem.as_bytes()).unwrap(),
+        jwk: json!({
+            "kty": "RSA",
+            "use": "sig",
+            "alg": "RS256",
+            "kid": KEY_ID,
+            "n": URL_SAFE_NO_PAD.encode(private_key.n().to_bytes_be()),
+            "e": URL_SAFE_NO_PAD.encode(private_key.e().to_bytes_be()),
+        }),
+        codes: Mutex::new(HashMap::new()),
+    });
+
+    info!("Mock OIDC provider at {}", provider.issuer);
+
+    HttpServer::new(move || {
+        App::new()
+            .app_data(provider.clone())
+            .route(
+                "/.well-known/openid-configuration",
+                web::get().to(discovery),
+            )
+            .route("/jwks", web::get().to(jwks))
+            .route("/authorize", web::get().to(authorize))
+            .route("/token", web::post().to(token))
+    })
+    .workers(1)
+    .bind(format!("{}:{}", host, port))?
+    .run()
+    .await
+}
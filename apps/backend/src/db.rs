@@ -2,17 +2,22 @@ use anyhow::Result;
 use deadpool_postgres::{Config, Pool, Runtime};
 use log::{error, info};
 use sqlx::postgres::PgPoolOptions;
-use tokio_postgres::{Config as PgConfig, NoTls};
+use std::time::Duration;
+use tokio_postgres::NoTls;
 use url::Url;
 
+use crate::config::DatabaseConfig;
+
 pub type DbPool = sqlx::PgPool;
 
-pub async fn create_pool(database_url: &str) -> Result<DbPool> {
+pub async fn create_pool(database: &DatabaseConfig) -> Result<DbPool> {
     info!("Creating database connection pool...");
 
     let pool = PgPoolOptions::new()
-        .max_connections(10)
-        .connect(database_url)
+        .max_connections(database.max_connections)
+        .min_connections(database.min_connections)
+        .acquire_timeout(Duration::from_secs(database.acquire_timeout_secs))
+        .connect(&database.url)
         .await?;
 
     info!("Database connection pool created successfully");
@@ -39,6 +44,7 @@ pub async fn run_migrations(database_url: &str) -> Result<()> {
 }
 
 // Function to create a deadpool connection pool (alternative to sqlx)
+#[allow(dead_code)]
 pub fn create_deadpool(database_url: &str) -> Result<Pool> {
     let url = Url::parse(database_url)?;
 
@@ -53,3 +59,43 @@ pub fn create_deadpool(database_url: &str) -> Result<Pool> {
     let pool = cfg.create_pool(Some(Runtime::Tokio1), NoTls)?;
     Ok(pool)
 }
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    // A migrated database of its own for a test, created next to the one in DATABASE_URL
+    pub(crate) struct TestDatabase {
+        pub pool: DbPool,
+        admin: DbPool,
+        name: String,
+    }
+
+    impl TestDatabase {
+        pub async fn create() -> Self {
+            let url = std::env::var("DATABASE_URL").expect("Database tests need DATABASE_URL");
+            let admin = DbPool::connect(&url).await.unwrap();
+            let name = format!("test_{}", Uuid::new_v4().simple());
+            sqlx::query(&format!("CREATE DATABASE {}", name))
+                .execute(&admin)
+                .await
+                .unwrap();
+
+            let mut test_url = Url::parse(&url).unwrap();
+            test_url.set_path(&name);
+            let pool = DbPool::connect(test_url.as_str()).await.unwrap();
+            sqlx::migrate!("./migrations").run(&pool).await.unwrap();
+
+            TestDatabase { pool, admin, name }
+        }
+
+        pub async fn drop(self) {
+            self.pool.close().await;
+            sqlx::query(&format!("DROP DATABASE {} WITH (FORCE)", self.name))
+                .execute(&self.admin)
+                .await
+                .unwrap();
+        }
+    }
+}
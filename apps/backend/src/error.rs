@@ -1,9 +1,9 @@
-use actix_web::{HttpResponse, ResponseError};
+use actix_web::{http::header, HttpResponse, ResponseError};
 use serde::{Deserialize, Serialize};
-use std::fmt;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum AppError {
     #[error("Database error: {0}")]
     DbError(#[from] sqlx::Error),
@@ -14,6 +14,9 @@ pub enum AppError {
     #[error("Validation error: {0}")]
     ValidationError(String),
 
+    #[error("Forbidden: {0}")]
+    ForbiddenError(String),
+
     #[error("Not found: {0}")]
     NotFoundError(String),
 
@@ -25,6 +28,12 @@ pub enum AppError {
 
     #[error("Bad request: {0}")]
     BadRequestError(String),
+
+    #[error("Blocked by outbound policy: {0}")]
+    OutboundPolicyError(String), // A request sent on behalf of a user broke the outbound rules
+
+    #[error("Too many requests: {0}")]
+    RateLimitError(String, u64), // Seconds until the next attempt is allowed
 }
 
 #[derive(Serialize, Deserialize)]
@@ -51,6 +60,10 @@ impl ResponseError for AppError {
                 status: "error".to_string(),
                 message: e.to_string(),
             }),
+            AppError::ForbiddenError(e) => HttpResponse::Forbidden().json(ErrorResponse {
+                status: "error".to_string(),
+                message: e.to_string(),
+            }),
             AppError::NotFoundError(e) => HttpResponse::NotFound().json(ErrorResponse {
                 status: "error".to_string(),
                 message: e.to_string(),
@@ -70,6 +83,16 @@ impl ResponseError for AppError {
                 status: "error".to_string(),
                 message: e.to_string(),
             }),
+            AppError::OutboundPolicyError(e) => HttpResponse::Forbidden().json(ErrorResponse {
+                status: "error".to_string(),
+                message: format!("Blocked by outbound policy: {}", e),
+            }),
+            AppError::RateLimitError(e, retry_after) => HttpResponse::TooManyRequests()
+                .insert_header((header::RETRY_AFTER, retry_after.to_string()))
+                .json(ErrorResponse {
+                    status: "error".to_string(),
+                    message: e.to_string(),
+                }),
         }
     }
 }
@@ -0,0 +1,499 @@
+use actix_web::{web, HttpResponse};
+use bcrypt::{hash, verify, DEFAULT_COST};
+use chrono::{Duration, Utc};
+use log::error;
+use serde_json::json;
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::app_middleware::SessionId;
+use crate::config::Config;
+use crate::error::AppError;
+use crate::mailer::templates::{
+    email_changed_email, password_changed_email, password_reset_email, verification_email,
+};
+use crate::mailer::Mailer;
+use crate::models::{
+    ChangePasswordDto, DeleteAccountDto, EmailTokenPurpose, PasswordResetDto,
+    PasswordResetRequestDto, UpdateProfileDto, User, VerifyEmailDto,
+};
+use crate::utils::account::{build_account_export, release_workspaces};
+use crate::utils::audit::{record_audit, AuditEntry, RequestOrigin};
+use crate::utils::email_token::{consume_email_token, create_email_token, email_link};
+use crate::utils::session::{revoke_other_sessions, revoke_sessions};
+use crate::utils::two_factor::verify_second_factor;
+
+pub async fn verify_email(
+    pool: web::Data<PgPool>,
+    verify_dto: web::Json<VerifyEmailDto>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let mut tx = pool.begin().await?;
+
+    let email_token =
+        consume_email_token(&mut tx, &verify_dto.token, EmailTokenPurpose::VerifyEmail).await?;
+
+    // The address may have changed since the link was sent
+    let user = sqlx::query_as!(
+        User,
+        r#"
+        UPDATE users
+        SET email_verified_at = COALESCE(email_verified_at, $3)
+        WHERE id = $1 AND LOWER(email) = LOWER($2)
+        RETURNING *
+        "#,
+        email_token.user_id,
+        email_token.email,
+        Utc::now().naive_utc()
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| {
+        AppError::BadRequestError("This link was sent to a different email address".to_string())
+    })?;
+
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            actor_id: Some(user.id),
+            action: "user.email_verified",
+            target_id: Some(user.id),
+            details: Some(json!({ "email": user.email })),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    // Return the verified user
+    Ok(HttpResponse::Ok().json(user.to_response()))
+}
+
+pub async fn resend_verification(
+    pool: web::Data<PgPool>,
+    config: web::Data<Config>,
+    mailer: web::Data<dyn Mailer>,
+    user_id: web::ReqData<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let user = sqlx::query_as!(
+        User,
+        "SELECT * FROM users WHERE id = $1",
+        user_id.into_inner()
+    )
+    .fetch_optional(pool.get_ref())
+    .await?
+    .ok_or_else(|| AppError::NotFoundError("User not found".to_string()))?;
+
+    if user.email_verified_at.is_some() {
+        return Err(AppError::ConflictError(
+            "Your email address is already verified".to_string(),
+        ));
+    }
+
+    // A new link replaces the one sent before
+    let mut tx = pool.begin().await?;
+    let token = create_email_token(
+        &mut tx,
+        user.id,
+        &user.email,
+        EmailTokenPurpose::VerifyEmail,
+        Duration::hours(config.email_verification_expiry_hours),
+    )
+    .await?;
+    tx.commit().await?;
+
+    let link = email_link(&config.mail.app_url, "verify-email", &token);
+    mailer.send(&verification_email(&user.email, &link)).await?;
+
+    // Return success, the link is in the mail
+    Ok(HttpResponse::Accepted().finish())
+}
+
+pub async fn request_password_reset(
+    pool: web::Data<PgPool>,
+    config: web::Data<Config>,
+    mailer: web::Data<dyn Mailer>,
+    reset_dto: web::Json<PasswordResetRequestDto>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    // Validate the email
+    reset_dto
+        .validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    let user = sqlx::query_as!(
+        User,
+        "SELECT * FROM users WHERE LOWER(email) = LOWER($1)",
+        reset_dto.email
+    )
+    .fetch_optional(pool.get_ref())
+    .await?;
+
+    // The answer is the same whether the account exists or not
+    let Some(user) = user else {
+        return Ok(HttpResponse::Accepted().finish());
+    };
+
+    let mut tx = pool.begin().await?;
+
+    let token = create_email_token(
+        &mut tx,
+        user.id,
+        &user.email,
+        EmailTokenPurpose::ResetPassword,
+        Duration::minutes(config.password_reset_expiry_minutes),
+    )
+    .await?;
+
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            actor_id: Some(user.id),
+            action: "user.password_reset_requested",
+            target_id: Some(user.id),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    let link = email_link(&config.mail.app_url, "reset-password", &token);
+    if let Err(e) = mailer.send(&password_reset_email(&user.email, &link)).await {
+        error!("Failed to send the password reset email: {:?}", e);
+    }
+
+    // Return success without telling whether the email is known
+    Ok(HttpResponse::Accepted().finish())
+}
+
+pub async fn reset_password(
+    pool: web::Data<PgPool>,
+    reset_dto: web::Json<PasswordResetDto>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    // Validate the new password
+    reset_dto
+        .validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    let password_hash = hash(&reset_dto.password, DEFAULT_COST)
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+    let mut tx = pool.begin().await?;
+
+    let email_token =
+        consume_email_token(&mut tx, &reset_dto.token, EmailTokenPurpose::ResetPassword).await?;
+
+    // Getting the link proves the address belongs to the user as well, and lifts a lockout
+    let now = Utc::now().naive_utc();
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET password_hash = $2, updated_at = $3, failed_login_count = 0, locked_until = NULL,
+            email_verified_at = CASE
+                WHEN LOWER(email) = LOWER($4) THEN COALESCE(email_verified_at, $3)
+                ELSE email_verified_at
+            END
+        WHERE id = $1
+        "#,
+        email_token.user_id,
+        password_hash,
+        now,
+        email_token.email
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    // Whoever knew the old password is signed out everywhere
+    revoke_sessions(&mut *tx, email_token.user_id, None).await?;
+
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            actor_id: Some(email_token.user_id),
+            action: "user.password_reset",
+            target_id: Some(email_token.user_id),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    // Return success with no content
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn update_profile(
+    pool: web::Data<PgPool>,
+    config: web::Data<Config>,
+    mailer: web::Data<dyn Mailer>,
+    user_id: web::ReqData<Uuid>,
+    profile_dto: web::Json<UpdateProfileDto>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    // Validate the profile data
+    profile_dto
+        .validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    let user = get_user(pool.get_ref(), user_id.into_inner()).await?;
+
+    let new_email = profile_dto
+        .email
+        .as_deref()
+        .map(str::trim)
+        .filter(|email| !email.eq_ignore_ascii_case(&user.email));
+
+    if let Some(new_email) = new_email {
+        // Whoever holds the email holds the account, so the password has to be confirmed
+        let current_password = profile_dto.current_password.as_deref().ok_or_else(|| {
+            AppError::ValidationError(
+                "The current password is required to change the email".to_string(),
+            )
+        })?;
+        check_password(current_password, &user)?;
+
+        let taken = sqlx::query!(
+            "SELECT id FROM users WHERE LOWER(email) = LOWER($1) AND id <> $2",
+            new_email,
+            user.id
+        )
+        .fetch_optional(pool.get_ref())
+        .await?;
+        if taken.is_some() {
+            return Err(AppError::ConflictError(
+                "User with this email already exists".to_string(),
+            ));
+        }
+    }
+
+    let mut tx = pool.begin().await?;
+
+    // A new address is unverified until the link sent to it is opened
+    let updated = sqlx::query_as!(
+        User,
+        r#"
+        UPDATE users
+        SET name = COALESCE($2, name), email = COALESCE($3, email), updated_at = $4,
+            email_verified_at = CASE WHEN $3::TEXT IS NULL THEN email_verified_at END
+        WHERE id = $1
+        RETURNING *
+        "#,
+        user.id,
+        profile_dto.name,
+        new_email,
+        Utc::now().naive_utc()
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let verification_token = match new_email {
+        Some(new_email) => Some(
+            create_email_token(
+                &mut tx,
+                user.id,
+                new_email,
+                EmailTokenPurpose::VerifyEmail,
+                Duration::hours(config.email_verification_expiry_hours),
+            )
+            .await?,
+        ),
+        None => None,
+    };
+
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            actor_id: Some(user.id),
+            action: "user.profile_updated",
+            target_id: Some(user.id),
+            before: Some(json!({ "email": user.email, "name": user.name })),
+            after: Some(json!({ "email": updated.email, "name": updated.name })),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    // The change is saved either way, so failed emails are only logged
+    if let Some(token) = verification_token {
+        let link = email_link(&config.mail.app_url, "verify-email", &token);
+        if let Err(e) = mailer
+            .send(&verification_email(&updated.email, &link))
+            .await
+        {
+            error!("Failed to send the verification email: {:?}", e);
+        }
+        if let Err(e) = mailer
+            .send(&email_changed_email(&user.email, &updated.email))
+            .await
+        {
+            error!("Failed to send the email change notice: {:?}", e);
+        }
+    }
+
+    // Return the updated user
+    Ok(HttpResponse::Ok().json(updated.to_response()))
+}
+
+pub async fn change_password(
+    pool: web::Data<PgPool>,
+    mailer: web::Data<dyn Mailer>,
+    user_id: web::ReqData<Uuid>,
+    session_id: web::ReqData<SessionId>,
+    password_dto: web::Json<ChangePasswordDto>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    // Validate the new password
+    password_dto
+        .validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    let user = get_user(pool.get_ref(), user_id.into_inner()).await?;
+    check_password(&password_dto.current_password, &user)?;
+
+    let password_hash = hash(&password_dto.new_password, DEFAULT_COST)
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!(
+        "UPDATE users SET password_hash = $2, updated_at = $3 WHERE id = $1",
+        user.id,
+        password_hash,
+        Utc::now().naive_utc()
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    // Other devices have to sign in with the new password, this one stays signed in
+    let SessionId(session_id) = session_id.into_inner();
+    let revoked = revoke_other_sessions(&mut *tx, user.id, session_id).await?;
+
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            actor_id: Some(user.id),
+            action: "user.password_changed",
+            target_id: Some(user.id),
+            details: Some(json!({ "sessions_revoked": revoked })),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    if let Err(e) = mailer.send(&password_changed_email(&user.email)).await {
+        error!("Failed to send the password change notice: {:?}", e);
+    }
+
+    // Return success with no content
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn export_account(
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<Uuid>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let user = get_user(pool.get_ref(), user_id.into_inner()).await?;
+
+    let mut conn = pool.acquire().await?;
+    let export = build_account_export(&mut conn, &user).await?;
+
+    record_audit(
+        &mut *conn,
+        &origin,
+        AuditEntry {
+            actor_id: Some(user.id),
+            action: "user.exported",
+            target_id: Some(user.id),
+            details: Some(json!({ "workspaces": export.workspaces.len() })),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    // Return everything the account holds as one document
+    Ok(HttpResponse::Ok().json(export))
+}
+
+pub async fn delete_account(
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<Uuid>,
+    delete_dto: web::Json<DeleteAccountDto>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let user = get_user(pool.get_ref(), user_id.into_inner()).await?;
+    check_password(&delete_dto.password, &user)?;
+
+    let mut tx = pool.begin().await?;
+
+    if user.totp_enabled_at.is_some() {
+        let code = delete_dto.code.as_deref().ok_or_else(|| {
+            AppError::ValidationError("A two-factor code is required".to_string())
+        })?;
+        verify_second_factor(&mut tx, &user, code)
+            .await?
+            .ok_or_else(|| AppError::BadRequestError("Invalid code".to_string()))?;
+    }
+
+    // The export is taken before anything is removed and handed back as the answer
+    let export = build_account_export(&mut tx, &user).await?;
+
+    release_workspaces(&mut tx, user.id, delete_dto.owned_workspaces, &origin).await?;
+
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            actor_id: Some(user.id),
+            action: "user.deleted",
+            target_id: Some(user.id),
+            before: Some(json!({ "email": user.email, "name": user.name })),
+            details: Some(json!({ "owned_workspaces": delete_dto.owned_workspaces })),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    // Sessions, API keys, memberships and favorites go with the user, executions stay without it
+    sqlx::query!("DELETE FROM users WHERE id = $1", user.id)
+        .execute(&mut *tx)
+        .await?;
+
+    tx.commit().await?;
+
+    // Return the export of the deleted account
+    Ok(HttpResponse::Ok().json(export))
+}
+
+async fn get_user<'c>(executor: impl PgExecutor<'c>, user_id: Uuid) -> Result<User, AppError> {
+    let user = sqlx::query_as!(User, "SELECT * FROM users WHERE id = $1", user_id)
+        .fetch_optional(executor)
+        .await?
+        .ok_or_else(|| AppError::NotFoundError("User not found".to_string()))?;
+
+    Ok(user)
+}
+
+fn check_password(password: &str, user: &User) -> Result<(), AppError> {
+    let valid_password = verify(password, &user.password_hash)
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+    if !valid_password {
+        return Err(AppError::BadRequestError("Invalid password".to_string()));
+    }
+
+    Ok(())
+}
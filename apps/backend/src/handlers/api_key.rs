@@ -0,0 +1,151 @@
+use actix_web::{web, HttpResponse};
+use chrono::{Duration, Utc};
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::error::AppError;
+use crate::models::{ApiKey, ApiKeyScope, CreateApiKeyDto};
+use crate::utils::api_key::generate_api_key;
+use crate::utils::audit::{record_audit, AuditEntry, RequestOrigin};
+use crate::utils::token::hash_token;
+
+pub async fn create_api_key(
+    pool: web::Data<PgPool>,
+    api_key_dto: web::Json<CreateApiKeyDto>,
+    user_id: web::ReqData<Uuid>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let user_id = user_id.into_inner();
+
+    // Validate the API key data
+    api_key_dto
+        .validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    let scopes: Vec<String> = [ApiKeyScope::Read, ApiKeyScope::Write, ApiKeyScope::Execute]
+        .iter()
+        .filter(|scope| api_key_dto.scopes.contains(scope))
+        .map(|scope| scope.as_str().to_string())
+        .collect();
+
+    let now = Utc::now().naive_utc();
+    let expires_at = api_key_dto
+        .expires_in_days
+        .map(|days| now + Duration::days(days));
+
+    let mut tx = pool.begin().await?;
+
+    // Insert the API key, only the hash of the key is kept
+    let (key, prefix) = generate_api_key();
+    let api_key = sqlx::query_as!(
+        ApiKey,
+        r#"
+        INSERT INTO api_keys (id, user_id, name, prefix, key_hash, scopes, expires_at, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        RETURNING *
+        "#,
+        Uuid::new_v4(),
+        user_id,
+        api_key_dto.name,
+        prefix,
+        hash_token(&key),
+        &scopes,
+        expires_at,
+        now
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            actor_id: Some(user_id),
+            action: "api_key.created",
+            target_id: Some(api_key.id),
+            details: Some(json!({
+                "name": api_key.name,
+                "scopes": api_key.scopes,
+                "expires_at": api_key.expires_at,
+            })),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    // Return the API key together with the key itself
+    let mut response = api_key.to_response();
+    response.key = Some(key);
+    Ok(HttpResponse::Created().json(response))
+}
+
+pub async fn get_api_keys(
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    // Get all API keys of the user, newest first
+    let api_keys = sqlx::query_as!(
+        ApiKey,
+        r#"
+        SELECT * FROM api_keys
+        WHERE user_id = $1
+        ORDER BY created_at DESC
+        "#,
+        user_id.into_inner()
+    )
+    .fetch_all(pool.get_ref())
+    .await?;
+
+    // Return the API keys
+    Ok(HttpResponse::Ok().json(api_keys.iter().map(|k| k.to_response()).collect::<Vec<_>>()))
+}
+
+pub async fn revoke_api_key(
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    user_id: web::ReqData<Uuid>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let api_key_id = path.into_inner();
+    let user_id = user_id.into_inner();
+
+    let mut tx = pool.begin().await?;
+
+    // Revoke the API key, revoking it twice is a no-op
+    let api_key = sqlx::query!(
+        r#"
+        UPDATE api_keys
+        SET revoked_at = COALESCE(revoked_at, $3)
+        WHERE id = $1 AND user_id = $2
+        RETURNING id, name
+        "#,
+        api_key_id,
+        user_id,
+        Utc::now().naive_utc()
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| AppError::NotFoundError("API key not found".to_string()))?;
+
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            actor_id: Some(user_id),
+            action: "api_key.revoked",
+            target_id: Some(api_key.id),
+            details: Some(json!({ "name": api_key.name })),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    // Return success with no content
+    Ok(HttpResponse::NoContent().finish())
+}
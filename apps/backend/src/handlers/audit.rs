@@ -0,0 +1,154 @@
+use actix_web::{web, HttpResponse};
+use sqlx::{PgPool, Postgres, QueryBuilder};
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::models::{AuditLogEntry, AuditLogQuery, Permission, WorkspaceRole};
+use crate::utils::pagination::{
+    page_size, push_cursor_condition, push_order_by, sort_value, Cursor, Page, SortField, SortOrder,
+};
+use crate::utils::workspace::authorize;
+
+pub async fn get_audit_log(
+    pool: web::Data<PgPool>,
+    query: web::Query<AuditLogQuery>,
+    user_id: web::ReqData<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = user_id.into_inner();
+
+    // Asking for a single workspace fails loudly for members without access
+    if let Some(workspace_id) = query.workspace_id {
+        authorize(pool.get_ref(), workspace_id, user_id, Permission::ViewAudit).await?;
+    }
+
+    audit_page(pool.get_ref(), &query, |builder| {
+        push_workspace_scope(builder, user_id, query.workspace_id);
+        push_audit_filters(builder, &query);
+    })
+    .await
+}
+
+// Entries about the account of the user rather than a workspace, like logins and password changes
+pub async fn get_my_audit_log(
+    pool: web::Data<PgPool>,
+    query: web::Query<AuditLogQuery>,
+    user_id: web::ReqData<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = user_id.into_inner();
+
+    audit_page(pool.get_ref(), &query, |builder| {
+        builder
+            .push(" WHERE a.workspace_id IS NULL AND (a.actor_id = ")
+            .push_bind(user_id)
+            .push(" OR a.target_id = ")
+            .push_bind(user_id)
+            .push(")");
+        push_audit_filters(builder, &query);
+    })
+    .await
+}
+
+// One page of the entries `push_filters` selects, newest first
+async fn audit_page(
+    pool: &PgPool,
+    query: &AuditLogQuery,
+    push_filters: impl Fn(&mut QueryBuilder<'_, Postgres>),
+) -> Result<HttpResponse, AppError> {
+    let limit = page_size(query.limit)?;
+    let cursor = query.cursor.as_deref().map(Cursor::decode).transpose()?;
+
+    // Count all matching entries
+    let mut count_query = QueryBuilder::new("SELECT COUNT(*) FROM audit_log a");
+    push_filters(&mut count_query);
+    let total: i64 = count_query.build_query_scalar().fetch_one(pool).await?;
+
+    // Get one page of matching entries, newest first, plus one row to know if there is a next page
+    let mut page_query = QueryBuilder::new("SELECT a.* FROM audit_log a");
+    push_filters(&mut page_query);
+    if let Some(cursor) = &cursor {
+        push_cursor_condition(
+            &mut page_query,
+            "a",
+            SortField::CreatedAt,
+            SortOrder::Desc,
+            cursor,
+        )?;
+    }
+    push_order_by(&mut page_query, "a", SortField::CreatedAt, SortOrder::Desc);
+    page_query.push(" LIMIT ").push_bind(limit + 1);
+
+    let mut entries = page_query
+        .build_query_as::<AuditLogEntry>()
+        .fetch_all(pool)
+        .await?;
+
+    let next_cursor = if entries.len() as i64 > limit {
+        entries.truncate(limit as usize);
+        entries.last().map(|last| {
+            Cursor {
+                value: sort_value(SortField::CreatedAt, "", last.created_at, last.created_at),
+                id: last.id,
+            }
+            .encode()
+        })
+    } else {
+        None
+    };
+
+    // Return the page of entries
+    Ok(HttpResponse::Ok().json(Page {
+        items: entries,
+        total,
+        next_cursor,
+    }))
+}
+
+// Only entries of the workspaces where the user may read the audit log
+fn push_workspace_scope(
+    builder: &mut QueryBuilder<'_, Postgres>,
+    user_id: Uuid,
+    workspace_id: Option<Uuid>,
+) {
+    builder
+        .push(
+            " WHERE a.workspace_id IN \
+             (SELECT workspace_id FROM workspace_access WHERE user_id = ",
+        )
+        .push_bind(user_id)
+        .push(" AND role = ANY(")
+        .push_bind(WorkspaceRole::names_with(Permission::ViewAudit))
+        .push("))");
+
+    if let Some(workspace_id) = workspace_id {
+        builder
+            .push(" AND a.workspace_id = ")
+            .push_bind(workspace_id);
+    }
+}
+
+// Filters of the query, shared by the count and page queries of both views
+fn push_audit_filters(builder: &mut QueryBuilder<'_, Postgres>, query: &AuditLogQuery) {
+    if let Some(actor_id) = query.actor_id {
+        builder.push(" AND a.actor_id = ").push_bind(actor_id);
+    }
+    if let Some(action) = &query.action {
+        builder
+            .push(" AND (a.action = ")
+            .push_bind(action.clone())
+            .push(" OR a.action LIKE ")
+            .push_bind(format!(
+                "{}.%",
+                action.replace('%', "\\%").replace('_', "\\_")
+            ))
+            .push(")");
+    }
+    if let Some(target_id) = query.target_id {
+        builder.push(" AND a.target_id = ").push_bind(target_id);
+    }
+    if let Some(from) = query.from {
+        builder.push(" AND a.created_at >= ").push_bind(from);
+    }
+    if let Some(to) = query.to {
+        builder.push(" AND a.created_at < ").push_bind(to);
+    }
+}
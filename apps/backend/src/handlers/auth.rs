@@ -1,19 +1,41 @@
 use actix_web::{web, HttpResponse};
 use bcrypt::{hash, verify, DEFAULT_COST};
 use chrono::{Duration, Utc};
-use jsonwebtoken::{encode, EncodingKey, Header};
+use log::error;
+use serde_json::json;
 use sqlx::PgPool;
 use uuid::Uuid;
 use validator::Validate;
 
-use crate::app_middleware::Claims;
+use crate::app_middleware::SessionId;
 use crate::config::Config;
 use crate::error::AppError;
-use crate::models::{AuthResponse, CreateUserDto, LoginDto, User, UserResponse};
+use crate::mailer::templates::verification_email;
+use crate::mailer::Mailer;
+use crate::models::{
+    AuthResponse, CreateUserDto, EmailTokenPurpose, LoginChallengeResponse, LoginDto,
+    RefreshTokenDto, TwoFactorLoginDto, User,
+};
+use crate::utils::audit::{record_audit, AuditEntry, RequestOrigin};
+use crate::utils::email_token::{create_email_token, email_link};
+use crate::utils::jwt::JwtKeys;
+use crate::utils::login_lockout::{
+    clear_failed_logins, ensure_not_locked, lock_out_on_failure, lockout_retry_after,
+};
+use crate::utils::session::{issue_tokens, revoke_sessions, start_session};
+use crate::utils::token::hash_token;
+use crate::utils::two_factor::{
+    create_login_challenge, verify_second_factor, MAX_CHALLENGE_ATTEMPTS,
+};
+use crate::utils::workspace::{insert_workspace, PERSONAL_WORKSPACE_NAME};
 
 pub async fn register(
     pool: web::Data<PgPool>,
+    config: web::Data<Config>,
+    keys: web::Data<JwtKeys>,
+    mailer: web::Data<dyn Mailer>,
     user_dto: web::Json<CreateUserDto>,
+    origin: RequestOrigin,
 ) -> Result<HttpResponse, AppError> {
     // Validate the user data
     user_dto
@@ -35,13 +57,16 @@ pub async fn register(
     let password_hash = hash(&user_dto.password, DEFAULT_COST)
         .map_err(|e| AppError::InternalServerError(e.to_string()))?;
 
+    let mut tx = pool.begin().await?;
+
     // Insert the user into the database
     let user = sqlx::query_as!(
         User,
         r#"
         INSERT INTO users (id, email, password_hash, name, created_at, updated_at)
         VALUES ($1, $2, $3, $4, $5, $6)
-        RETURNING id, email, password_hash, name, created_at, updated_at
+        RETURNING id, email, password_hash, name, created_at, updated_at, email_verified_at,
+            totp_secret, totp_enabled_at, totp_last_step, failed_login_count, locked_until
         "#,
         Uuid::new_v4(),
         user_dto.email,
@@ -50,34 +75,58 @@ pub async fn register(
         Utc::now().naive_utc(),
         Utc::now().naive_utc()
     )
-    .fetch_one(pool.get_ref())
+    .fetch_one(&mut *tx)
     .await?;
 
-    // Generate JWT token
-    let config = Config::from_env().expect("Failed to load configuration");
-    let expiration = Utc::now() + Duration::hours(24);
-    let claims = Claims {
-        sub: user.id.to_string(),
-        exp: expiration.timestamp() as usize,
-    };
+    // Every account starts with a personal workspace
+    insert_workspace(&mut tx, PERSONAL_WORKSPACE_NAME, None, true, user.id).await?;
 
-    let token = encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(config.jwt_secret.as_bytes()),
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            actor_id: Some(user.id),
+            action: "user.registered",
+            target_id: Some(user.id),
+            after: Some(json!({ "email": user.email, "name": user.name })),
+            ..Default::default()
+        },
     )
-    .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+    .await?;
+
+    // Sign the new user in right away
+    let tokens = start_session(&mut tx, &config, &keys, user.id, &origin).await?;
+
+    let verification_token = create_email_token(
+        &mut tx,
+        user.id,
+        &user.email,
+        EmailTokenPurpose::VerifyEmail,
+        Duration::hours(config.email_verification_expiry_hours),
+    )
+    .await?;
 
-    // Return the user with token
+    tx.commit().await?;
+
+    // The account works without it, so a failed email does not fail the registration
+    let link = email_link(&config.mail.app_url, "verify-email", &verification_token);
+    if let Err(e) = mailer.send(&verification_email(&user.email, &link)).await {
+        error!("Failed to send the verification email: {:?}", e);
+    }
+
+    // Return the user with tokens
     Ok(HttpResponse::Created().json(AuthResponse {
         user: user.to_response(),
-        token,
+        tokens,
     }))
 }
 
 pub async fn login(
     pool: web::Data<PgPool>,
+    config: web::Data<Config>,
+    keys: web::Data<JwtKeys>,
     login_dto: web::Json<LoginDto>,
+    origin: RequestOrigin,
 ) -> Result<HttpResponse, AppError> {
     // Validate the login data
     login_dto
@@ -91,39 +140,335 @@ pub async fn login(
         login_dto.email
     )
     .fetch_optional(pool.get_ref())
-    .await?
-    .ok_or_else(|| AppError::AuthError("Invalid email or password".to_string()))?;
+    .await?;
+
+    // Locked accounts do not get to try the password. They fail like a wrong password,
+    // a lockout answered differently would tell which emails have accounts
+    let locked = user
+        .as_ref()
+        .is_some_and(|user| lockout_retry_after(user.locked_until).is_some());
 
     // Verify the password
-    let valid_password = verify(&login_dto.password, &user.password_hash)
-        .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+    let valid_password = match &user {
+        Some(user) if !locked => verify(&login_dto.password, &user.password_hash)
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?,
+        _ => false,
+    };
+
+    let actor_id = user.as_ref().map(|user| user.id);
+    let Some(user) = user.filter(|_| valid_password) else {
+        let mut tx = pool.begin().await?;
+
+        // Failed attempts are kept as well, tied to the account when the email is known
+        let mut details = json!({ "email": login_dto.email });
+        if locked {
+            details["reason"] = json!("account_locked");
+        }
+        record_audit(
+            &mut *tx,
+            &origin,
+            AuditEntry {
+                actor_id,
+                action: "user.login_failed",
+                target_id: actor_id,
+                details: Some(details),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        // Attempts while locked do not make the lockout longer
+        if let Some(user_id) = actor_id.filter(|_| !locked) {
+            lock_out_on_failure(&mut tx, &config.login_lockout, user_id, &origin).await?;
+        }
+
+        tx.commit().await?;
 
-    if !valid_password {
         return Err(AppError::AuthError("Invalid email or password".to_string()));
+    };
+
+    let mut tx = pool.begin().await?;
+
+    // With 2FA the password only earns a challenge, the session comes with the code
+    if user.totp_enabled_at.is_some() {
+        let expires_in = Duration::minutes(config.login_challenge_expiry_minutes);
+        let challenge_token = create_login_challenge(&mut tx, user.id, expires_in).await?;
+        tx.commit().await?;
+
+        return Ok(HttpResponse::Ok().json(LoginChallengeResponse {
+            two_factor_required: true,
+            challenge_token,
+            expires_in: expires_in.num_seconds(),
+        }));
     }
 
-    // Generate JWT token
-    let config = Config::from_env().expect("Failed to load configuration");
-    let expiration = Utc::now() + Duration::hours(24);
-    let claims = Claims {
-        sub: user.id.to_string(),
-        exp: expiration.timestamp() as usize,
+    // Every login starts a session of its own
+    clear_failed_logins(&mut *tx, user.id).await?;
+    let tokens = start_session(&mut tx, &config, &keys, user.id, &origin).await?;
+
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            actor_id: Some(user.id),
+            action: "user.logged_in",
+            target_id: Some(user.id),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    // Return the user with tokens
+    Ok(HttpResponse::Ok().json(AuthResponse {
+        user: user.to_response(),
+        tokens,
+    }))
+}
+
+pub async fn login_two_factor(
+    pool: web::Data<PgPool>,
+    config: web::Data<Config>,
+    keys: web::Data<JwtKeys>,
+    login_dto: web::Json<TwoFactorLoginDto>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let mut tx = pool.begin().await?;
+
+    // Find the challenge handed out for the password
+    let challenge = sqlx::query!(
+        r#"
+        SELECT id, user_id, attempts, expires_at FROM login_challenges
+        WHERE token_hash = $1
+        FOR UPDATE
+        "#,
+        hash_token(&login_dto.challenge_token)
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .filter(|challenge| challenge.expires_at > Utc::now().naive_utc())
+    .ok_or_else(|| {
+        AppError::AuthError("The login has expired, sign in with your password again".to_string())
+    })?;
+
+    let user = sqlx::query_as!(User, "SELECT * FROM users WHERE id = $1", challenge.user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+    ensure_not_locked(&user)?;
+
+    let Some(factor) = verify_second_factor(&mut tx, &user, &login_dto.code).await? else {
+        // Too many wrong codes and the password has to be entered again
+        if challenge.attempts + 1 >= MAX_CHALLENGE_ATTEMPTS {
+            sqlx::query!("DELETE FROM login_challenges WHERE id = $1", challenge.id)
+                .execute(&mut *tx)
+                .await?;
+        } else {
+            sqlx::query!(
+                "UPDATE login_challenges SET attempts = attempts + 1 WHERE id = $1",
+                challenge.id
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        record_audit(
+            &mut *tx,
+            &origin,
+            AuditEntry {
+                actor_id: Some(user.id),
+                action: "user.login_failed",
+                target_id: Some(user.id),
+                details: Some(json!({ "email": user.email, "reason": "invalid_2fa_code" })),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        // Wrong codes count towards the lockout like wrong passwords
+        lock_out_on_failure(&mut tx, &config.login_lockout, user.id, &origin).await?;
+
+        tx.commit().await?;
+
+        return Err(AppError::AuthError("Invalid two-factor code".to_string()));
     };
 
-    let token = encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(config.jwt_secret.as_bytes()),
+    // The challenge is used up
+    sqlx::query!("DELETE FROM login_challenges WHERE id = $1", challenge.id)
+        .execute(&mut *tx)
+        .await?;
+    clear_failed_logins(&mut *tx, user.id).await?;
+
+    let tokens = start_session(&mut tx, &config, &keys, user.id, &origin).await?;
+
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            actor_id: Some(user.id),
+            action: "user.logged_in",
+            target_id: Some(user.id),
+            details: Some(json!({ "second_factor": factor.as_str() })),
+            ..Default::default()
+        },
     )
-    .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+    .await?;
+
+    tx.commit().await?;
 
-    // Return the user with token
+    // Return the user with tokens
     Ok(HttpResponse::Ok().json(AuthResponse {
         user: user.to_response(),
-        token,
+        tokens,
     }))
 }
 
+pub async fn refresh(
+    pool: web::Data<PgPool>,
+    config: web::Data<Config>,
+    keys: web::Data<JwtKeys>,
+    refresh_dto: web::Json<RefreshTokenDto>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let mut tx = pool.begin().await?;
+
+    // Find the refresh token together with its session
+    let refresh_token = sqlx::query!(
+        r#"
+        SELECT t.id, t.used_at, t.session_id, s.user_id, s.expires_at, s.revoked_at
+        FROM refresh_tokens t
+        JOIN sessions s ON s.id = t.session_id
+        WHERE t.token_hash = $1
+        FOR UPDATE OF t, s
+        "#,
+        hash_token(&refresh_dto.refresh_token)
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| AppError::AuthError("Invalid refresh token".to_string()))?;
+
+    let now = Utc::now().naive_utc();
+    if refresh_token.revoked_at.is_some() || refresh_token.expires_at <= now {
+        return Err(AppError::AuthError(
+            "Session has expired or was revoked".to_string(),
+        ));
+    }
+
+    // Refresh tokens are single use, seeing one again means it leaked
+    if refresh_token.used_at.is_some() {
+        revoke_sessions(
+            &mut *tx,
+            refresh_token.user_id,
+            Some(refresh_token.session_id),
+        )
+        .await?;
+
+        record_audit(
+            &mut *tx,
+            &origin,
+            AuditEntry {
+                actor_id: Some(refresh_token.user_id),
+                action: "session.refresh_token_reused",
+                target_id: Some(refresh_token.session_id),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        tx.commit().await?;
+
+        return Err(AppError::AuthError(
+            "Refresh token was already used, the session has been revoked".to_string(),
+        ));
+    }
+
+    // Rotate the refresh token
+    sqlx::query!(
+        "UPDATE refresh_tokens SET used_at = $2 WHERE id = $1",
+        refresh_token.id,
+        now
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let tokens = issue_tokens(
+        &mut tx,
+        &config,
+        &keys,
+        refresh_token.user_id,
+        refresh_token.session_id,
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    // Return the new tokens
+    Ok(HttpResponse::Ok().json(tokens))
+}
+
+pub async fn logout(
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<Uuid>,
+    session_id: web::ReqData<SessionId>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let user_id = user_id.into_inner();
+    let SessionId(session_id) = session_id.into_inner();
+
+    let mut tx = pool.begin().await?;
+
+    // Revoke the session of the access token
+    revoke_sessions(&mut *tx, user_id, Some(session_id)).await?;
+
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            actor_id: Some(user_id),
+            action: "user.logged_out",
+            target_id: Some(session_id),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    // Return success with no content
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn logout_everywhere(
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<Uuid>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let user_id = user_id.into_inner();
+
+    let mut tx = pool.begin().await?;
+
+    // Revoke every session of the user, including the current one
+    let revoked = revoke_sessions(&mut *tx, user_id, None).await?;
+
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            actor_id: Some(user_id),
+            action: "user.logged_out_everywhere",
+            target_id: Some(user_id),
+            details: Some(json!({ "sessions": revoked })),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    // Return success with no content
+    Ok(HttpResponse::NoContent().finish())
+}
+
 pub async fn get_current_user(
     user_id: web::ReqData<Uuid>,
     pool: web::Data<PgPool>,
@@ -141,3 +486,8 @@ pub async fn get_current_user(
     // Return the user
     Ok(HttpResponse::Ok().json(user.to_response()))
 }
+
+pub async fn get_jwks(keys: web::Data<JwtKeys>) -> Result<HttpResponse, AppError> {
+    // Return the public keys that verify access tokens
+    Ok(HttpResponse::Ok().json(keys.jwks()))
+}
@@ -0,0 +1,314 @@
+use actix_web::{web, HttpResponse};
+use chrono::Utc;
+use serde_json::json;
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::error::AppError;
+use crate::models::{
+    ClientCertificate, ClientCertificateResponse, CreateClientCertificateDto, Permission,
+    UpdateClientCertificateDto,
+};
+use crate::utils::audit::{record_audit, AuditEntry, RequestOrigin};
+use crate::utils::client_certificate::{normalize_host_patterns, parse_certificate};
+use crate::utils::secrets::{secrets_not_configured, SecretVault};
+use crate::utils::workspace::{access_role, authorize};
+
+pub async fn get_client_certificates(
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    user_id: web::ReqData<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let workspace_id = path.into_inner();
+
+    // Every member may see which certificates exist and where they go, never their keys
+    access_role(pool.get_ref(), workspace_id, user_id.into_inner()).await?;
+
+    let certificates = sqlx::query_as!(
+        ClientCertificate,
+        "SELECT * FROM client_certificates WHERE workspace_id = $1 ORDER BY name ASC",
+        workspace_id
+    )
+    .fetch_all(pool.get_ref())
+    .await?;
+
+    let certificate_responses: Vec<ClientCertificateResponse> = certificates
+        .iter()
+        .map(|certificate| certificate.to_response())
+        .collect();
+
+    // Return the certificates without their keys
+    Ok(HttpResponse::Ok().json(certificate_responses))
+}
+
+pub async fn create_client_certificate(
+    pool: web::Data<PgPool>,
+    vault: Option<web::Data<SecretVault>>,
+    path: web::Path<Uuid>,
+    certificate_dto: web::Json<CreateClientCertificateDto>,
+    user_id: web::ReqData<Uuid>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let vault = vault.ok_or_else(secrets_not_configured)?;
+    let workspace_id = path.into_inner();
+    let user_id = user_id.into_inner();
+
+    // Validate the certificate data
+    certificate_dto
+        .validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+    let host_patterns = normalize_host_patterns(&certificate_dto.host_patterns)?;
+    let parsed = parse_certificate(
+        certificate_dto.format,
+        &certificate_dto.certificate,
+        certificate_dto.passphrase.as_deref(),
+    )?;
+
+    authorize(pool.get_ref(), workspace_id, user_id, Permission::Edit).await?;
+    ensure_name_available(pool.get_ref(), workspace_id, &certificate_dto.name, None).await?;
+
+    let mut tx = pool.begin().await?;
+
+    let certificate_id = Uuid::new_v4();
+    let (ciphertext, key_version) = vault
+        .encrypt(&mut tx, workspace_id, certificate_id, &parsed.pem)
+        .await?;
+
+    let now = Utc::now().naive_utc();
+    let certificate = sqlx::query_as!(
+        ClientCertificate,
+        r#"
+        INSERT INTO client_certificates
+            (id, workspace_id, name, host_patterns, format, subject, issuer, expires_at,
+             ciphertext, key_version, user_id, created_at, updated_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $12)
+        RETURNING *
+        "#,
+        certificate_id,
+        workspace_id,
+        certificate_dto.name,
+        &host_patterns,
+        certificate_dto.format.as_str(),
+        parsed.subject,
+        parsed.issuer,
+        parsed.expires_at,
+        ciphertext,
+        key_version,
+        user_id,
+        now
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            workspace_id: Some(workspace_id),
+            actor_id: Some(user_id),
+            action: "client_certificate.created",
+            target_id: Some(certificate.id),
+            after: Some(certificate.audit_summary()),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    // Return the certificate without its key
+    Ok(HttpResponse::Created().json(certificate.to_response()))
+}
+
+pub async fn update_client_certificate(
+    pool: web::Data<PgPool>,
+    vault: Option<web::Data<SecretVault>>,
+    path: web::Path<(Uuid, Uuid)>,
+    certificate_dto: web::Json<UpdateClientCertificateDto>,
+    user_id: web::ReqData<Uuid>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let (workspace_id, certificate_id) = path.into_inner();
+    let user_id = user_id.into_inner();
+
+    // Validate the certificate data
+    certificate_dto
+        .validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+    let host_patterns = certificate_dto
+        .host_patterns
+        .as_deref()
+        .map(normalize_host_patterns)
+        .transpose()?;
+    let parsed = certificate_dto
+        .certificate
+        .as_deref()
+        .map(|certificate| {
+            parse_certificate(
+                certificate_dto.format,
+                certificate,
+                certificate_dto.passphrase.as_deref(),
+            )
+        })
+        .transpose()?;
+
+    authorize(pool.get_ref(), workspace_id, user_id, Permission::Edit).await?;
+    let certificate =
+        get_workspace_certificate(pool.get_ref(), workspace_id, certificate_id).await?;
+
+    if let Some(name) = &certificate_dto.name {
+        ensure_name_available(pool.get_ref(), workspace_id, name, Some(certificate_id)).await?;
+    }
+
+    let mut tx = pool.begin().await?;
+
+    // A new certificate is encrypted with the current key of the workspace
+    let (ciphertext, key_version) = match &parsed {
+        Some(parsed) => {
+            let vault = vault.ok_or_else(secrets_not_configured)?;
+            vault
+                .encrypt(&mut tx, workspace_id, certificate_id, &parsed.pem)
+                .await?
+        }
+        None => (certificate.ciphertext.clone(), certificate.key_version),
+    };
+
+    let updated_certificate = sqlx::query_as!(
+        ClientCertificate,
+        r#"
+        UPDATE client_certificates
+        SET name = $2, host_patterns = $3, format = $4, subject = $5, issuer = $6,
+            expires_at = $7, ciphertext = $8, key_version = $9, updated_at = $10
+        WHERE id = $1
+        RETURNING *
+        "#,
+        certificate_id,
+        certificate_dto.name.as_deref().unwrap_or(&certificate.name),
+        host_patterns.as_ref().unwrap_or(&certificate.host_patterns),
+        match &parsed {
+            Some(_) => certificate_dto.format.as_str(),
+            None => &certificate.format,
+        },
+        parsed
+            .as_ref()
+            .map_or(&certificate.subject, |parsed| &parsed.subject),
+        parsed
+            .as_ref()
+            .map_or(&certificate.issuer, |parsed| &parsed.issuer),
+        parsed
+            .as_ref()
+            .map_or(certificate.expires_at, |parsed| parsed.expires_at),
+        ciphertext,
+        key_version,
+        Utc::now().naive_utc()
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            workspace_id: Some(workspace_id),
+            actor_id: Some(user_id),
+            action: "client_certificate.updated",
+            target_id: Some(certificate_id),
+            details: Some(json!({ "certificate_changed": parsed.is_some() })),
+            before: Some(certificate.audit_summary()),
+            after: Some(updated_certificate.audit_summary()),
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    // Return the updated certificate without its key
+    Ok(HttpResponse::Ok().json(updated_certificate.to_response()))
+}
+
+pub async fn delete_client_certificate(
+    pool: web::Data<PgPool>,
+    path: web::Path<(Uuid, Uuid)>,
+    user_id: web::ReqData<Uuid>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let (workspace_id, certificate_id) = path.into_inner();
+    let user_id = user_id.into_inner();
+
+    authorize(pool.get_ref(), workspace_id, user_id, Permission::Edit).await?;
+    let certificate =
+        get_workspace_certificate(pool.get_ref(), workspace_id, certificate_id).await?;
+
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!(
+        "DELETE FROM client_certificates WHERE id = $1",
+        certificate_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            workspace_id: Some(workspace_id),
+            actor_id: Some(user_id),
+            action: "client_certificate.deleted",
+            target_id: Some(certificate_id),
+            before: Some(certificate.audit_summary()),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    // Return success with no content
+    Ok(HttpResponse::NoContent().finish())
+}
+
+async fn get_workspace_certificate<'c>(
+    executor: impl PgExecutor<'c>,
+    workspace_id: Uuid,
+    certificate_id: Uuid,
+) -> Result<ClientCertificate, AppError> {
+    let certificate = sqlx::query_as!(
+        ClientCertificate,
+        "SELECT * FROM client_certificates WHERE id = $1 AND workspace_id = $2",
+        certificate_id,
+        workspace_id
+    )
+    .fetch_optional(executor)
+    .await?
+    .ok_or_else(|| AppError::NotFoundError("Client certificate not found".to_string()))?;
+
+    Ok(certificate)
+}
+
+async fn ensure_name_available<'c>(
+    executor: impl PgExecutor<'c>,
+    workspace_id: Uuid,
+    name: &str,
+    certificate_id: Option<Uuid>,
+) -> Result<(), AppError> {
+    let certificate_exists = sqlx::query!(
+        r#"
+        SELECT id FROM client_certificates
+        WHERE workspace_id = $1 AND name = $2 AND ($3::UUID IS NULL OR id <> $3)
+        "#,
+        workspace_id,
+        name,
+        certificate_id
+    )
+    .fetch_optional(executor)
+    .await?;
+
+    if certificate_exists.is_some() {
+        return Err(AppError::ConflictError(
+            "Client certificate with this name already exists".to_string(),
+        ));
+    }
+    Ok(())
+}
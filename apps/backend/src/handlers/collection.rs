@@ -1,69 +1,176 @@
 use actix_web::{web, HttpResponse};
-use sqlx::PgPool;
+use serde_json::{json, Value};
+use sqlx::{PgPool, Postgres, QueryBuilder};
+use std::time::Instant;
 use uuid::Uuid;
 use validator::Validate;
 
+use crate::config::Config;
 use crate::error::AppError;
 use crate::models::{
-    Collection, CollectionResponse, CollectionWithRequestsResponse, CreateCollectionDto, Request,
+    Collection, CollectionExport, CollectionListQuery, CollectionResponse, CollectionRunResponse,
+    CreateCollectionDto, DuplicateCollectionDto, EntityType, Permission, Request, RunResult,
     UpdateCollectionDto,
 };
+use crate::utils::audit::{record_audit, AuditEntry, RequestOrigin};
+use crate::utils::duplicate::{copy_collection, copy_name, copy_request};
+use crate::utils::execution::execute_and_record;
+use crate::utils::json::to_json;
+use crate::utils::outbound::OutboundPolicy;
+use crate::utils::pagination::{
+    page_size, push_cursor_condition, push_order_by, sort_value, Cursor, Page,
+};
+use crate::utils::rate_limit::{user_key, RateLimitScope, RateLimiter};
+use crate::utils::resolve::resolve_request;
+use crate::utils::revision::record_revision;
+use crate::utils::secrets::SecretVault;
+use crate::utils::workspace::{authorize, target_workspace};
 
 pub async fn create_collection(
     pool: web::Data<PgPool>,
     collection_dto: web::Json<CreateCollectionDto>,
     user_id: web::ReqData<Uuid>,
+    origin: RequestOrigin,
 ) -> Result<HttpResponse, AppError> {
+    let user_id = user_id.into_inner();
+
     // Validate the collection data
     collection_dto
         .validate()
         .map_err(|e| AppError::ValidationError(e.to_string()))?;
 
+    // Prepare the request defaults
+    let default_headers = collection_dto
+        .default_headers
+        .clone()
+        .unwrap_or_else(|| Value::Object(serde_json::Map::new()));
+    let default_params = collection_dto
+        .default_params
+        .clone()
+        .unwrap_or_else(|| Value::Object(serde_json::Map::new()));
+    let auth = to_json(collection_dto.auth.as_ref())?;
+    let settings = to_json(collection_dto.settings.as_ref())?
+        .unwrap_or_else(|| Value::Object(serde_json::Map::new()));
+
+    let mut tx = pool.begin().await?;
+
+    // Collections go to the personal workspace unless another one is picked
+    let workspace_id = target_workspace(&mut *tx, collection_dto.workspace_id, user_id).await?;
+    authorize(&mut *tx, workspace_id, user_id, Permission::Edit).await?;
+
     // Insert the collection into the database
     let collection = sqlx::query_as!(
         Collection,
         r#"
-        INSERT INTO collections (id, name, description, user_id, created_at, updated_at)
-        VALUES ($1, $2, $3, $4, $5, $6)
-        RETURNING id, name, description, user_id, created_at, updated_at
+        INSERT INTO collections (
+            id, name, description, user_id, created_at, updated_at,
+            base_url, default_headers, default_params, auth, settings, workspace_id
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+        RETURNING id, name, description, user_id, created_at, updated_at,
+                  base_url, default_headers, default_params, auth, settings, deleted_at,
+                  workspace_id
         "#,
         Uuid::new_v4(),
         collection_dto.name,
         collection_dto.description,
-        user_id.into_inner(),
+        user_id,
+        chrono::Utc::now().naive_utc(),
         chrono::Utc::now().naive_utc(),
-        chrono::Utc::now().naive_utc()
+        collection_dto.base_url,
+        default_headers,
+        default_params,
+        auth,
+        settings,
+        workspace_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    // Start the history of the collection
+    record_revision(
+        &mut tx,
+        EntityType::Collection,
+        collection.id,
+        &collection,
+        user_id,
     )
-    .fetch_one(pool.get_ref())
     .await?;
 
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            workspace_id: Some(workspace_id),
+            actor_id: Some(user_id),
+            action: "collection.created",
+            target_id: Some(collection.id),
+            after: Some(collection.audit_summary()),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+
     // Return the collection
     Ok(HttpResponse::Created().json(collection.to_response()))
 }
 
 pub async fn get_collections(
     pool: web::Data<PgPool>,
+    query: web::Query<CollectionListQuery>,
     user_id: web::ReqData<Uuid>,
 ) -> Result<HttpResponse, AppError> {
-    // Get all collections for the user
-    let collections = sqlx::query_as!(
-        Collection,
-        r#"
-        SELECT * FROM collections
-        WHERE user_id = $1
-        ORDER BY created_at DESC
-        "#,
-        user_id.into_inner()
-    )
-    .fetch_all(pool.get_ref())
-    .await?;
+    let user_id = user_id.into_inner();
+    let limit = page_size(query.limit)?;
+    let cursor = query.cursor.as_deref().map(Cursor::decode).transpose()?;
+
+    // Count all matching collections
+    let mut count_query = QueryBuilder::new("SELECT COUNT(*) FROM collections c");
+    push_collection_filters(&mut count_query, user_id, &query);
+    let total: i64 = count_query
+        .build_query_scalar()
+        .fetch_one(pool.get_ref())
+        .await?;
+
+    // Get one page of matching collections, plus one row to know if there is a next page
+    let mut page_query = QueryBuilder::new("SELECT c.* FROM collections c");
+    push_collection_filters(&mut page_query, user_id, &query);
+    if let Some(cursor) = &cursor {
+        push_cursor_condition(&mut page_query, "c", query.sort, query.order, cursor)?;
+    }
+    push_order_by(&mut page_query, "c", query.sort, query.order);
+    page_query.push(" LIMIT ").push_bind(limit + 1);
+
+    let mut collections = page_query
+        .build_query_as::<Collection>()
+        .fetch_all(pool.get_ref())
+        .await?;
+
+    let next_cursor = if collections.len() as i64 > limit {
+        collections.truncate(limit as usize);
+        collections.last().map(|last| {
+            Cursor {
+                value: sort_value(query.sort, &last.name, last.created_at, last.updated_at),
+                id: last.id,
+            }
+            .encode()
+        })
+    } else {
+        None
+    };
 
     // Transform to response objects
     let collection_responses: Vec<CollectionResponse> =
         collections.into_iter().map(|c| c.to_response()).collect();
 
-    // Return the collections
-    Ok(HttpResponse::Ok().json(collection_responses))
+    // Return the page of collections
+    Ok(HttpResponse::Ok().json(Page {
+        items: collection_responses,
+        total,
+        next_cursor,
+    }))
 }
 
 pub async fn get_collection(
@@ -78,7 +185,8 @@ pub async fn get_collection(
         Collection,
         r#"
         SELECT * FROM collections
-        WHERE id = $1 AND user_id = $2
+        WHERE id = $1 AND deleted_at IS NULL
+          AND workspace_id IN (SELECT workspace_id FROM workspace_access WHERE user_id = $2)
         "#,
         collection_id,
         user_id.into_inner()
@@ -92,7 +200,7 @@ pub async fn get_collection(
         Request,
         r#"
         SELECT * FROM requests
-        WHERE collection_id = $1
+        WHERE collection_id = $1 AND deleted_at IS NULL
         ORDER BY created_at DESC
         "#,
         collection_id
@@ -104,14 +212,7 @@ pub async fn get_collection(
     let request_responses = requests.into_iter().map(|r| r.to_response()).collect();
 
     // Create response with collection and its requests
-    let response = CollectionWithRequestsResponse {
-        id: collection.id,
-        name: collection.name,
-        description: collection.description,
-        created_at: collection.created_at,
-        updated_at: collection.updated_at,
-        requests: request_responses,
-    };
+    let response = collection.to_response_with_requests(request_responses);
 
     // Return the collection with requests
     Ok(HttpResponse::Ok().json(response))
@@ -122,47 +223,119 @@ pub async fn update_collection(
     path: web::Path<Uuid>,
     collection_dto: web::Json<UpdateCollectionDto>,
     user_id: web::ReqData<Uuid>,
+    origin: RequestOrigin,
 ) -> Result<HttpResponse, AppError> {
     let collection_id = path.into_inner();
+    let user_id = user_id.into_inner();
 
-    // Validate the collection exists and belongs to the user
+    // Validate the collection exists in a workspace of the user
     let collection = sqlx::query_as!(
         Collection,
         r#"
         SELECT * FROM collections
-        WHERE id = $1 AND user_id = $2
+        WHERE id = $1 AND deleted_at IS NULL
+          AND workspace_id IN (SELECT workspace_id FROM workspace_access WHERE user_id = $2)
         "#,
         collection_id,
-        user_id.into_inner()
+        user_id
     )
     .fetch_optional(pool.get_ref())
     .await?
     .ok_or_else(|| AppError::NotFoundError("Collection not found".to_string()))?;
 
+    authorize(
+        pool.get_ref(),
+        collection.workspace_id,
+        user_id,
+        Permission::Edit,
+    )
+    .await?;
+
+    let before = collection.audit_summary();
+
+    // Validate the collection data
+    collection_dto
+        .validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
     // Update only provided fields
     let name = collection_dto.name.clone().unwrap_or(collection.name);
     let description = collection_dto
         .description
         .clone()
         .or(collection.description);
+    let base_url = collection_dto
+        .base_url
+        .clone()
+        .unwrap_or(collection.base_url);
+    let default_headers = collection_dto
+        .default_headers
+        .clone()
+        .unwrap_or(collection.default_headers);
+    let default_params = collection_dto
+        .default_params
+        .clone()
+        .unwrap_or(collection.default_params);
+    let auth = match &collection_dto.auth {
+        Some(auth) => to_json(auth.as_ref())?,
+        None => collection.auth,
+    };
+    let settings = to_json(collection_dto.settings.as_ref())?.unwrap_or(collection.settings);
+
+    let mut tx = pool.begin().await?;
 
     // Update the collection in the database
     let updated_collection = sqlx::query_as!(
         Collection,
         r#"
         UPDATE collections
-        SET name = $1, description = $2, updated_at = $3
-        WHERE id = $4
-        RETURNING id, name, description, user_id, created_at, updated_at
+        SET name = $1, description = $2, base_url = $3, default_headers = $4,
+            default_params = $5, auth = $6, settings = $7, updated_at = $8
+        WHERE id = $9
+        RETURNING id, name, description, user_id, created_at, updated_at,
+                  base_url, default_headers, default_params, auth, settings, deleted_at,
+                  workspace_id
         "#,
         name,
         description,
+        base_url,
+        default_headers,
+        default_params,
+        auth,
+        settings,
         chrono::Utc::now().naive_utc(),
         collection_id
     )
-    .fetch_one(pool.get_ref())
+    .fetch_one(&mut *tx)
+    .await?;
+
+    // Keep the previous state recoverable
+    record_revision(
+        &mut tx,
+        EntityType::Collection,
+        updated_collection.id,
+        &updated_collection,
+        user_id,
+    )
     .await?;
 
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            workspace_id: Some(updated_collection.workspace_id),
+            actor_id: Some(user_id),
+            action: "collection.updated",
+            target_id: Some(collection_id),
+            before: Some(before),
+            after: Some(updated_collection.audit_summary()),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+
     // Return the updated collection
     Ok(HttpResponse::Ok().json(updated_collection.to_response()))
 }
@@ -171,47 +344,430 @@ pub async fn delete_collection(
     pool: web::Data<PgPool>,
     path: web::Path<Uuid>,
     user_id: web::ReqData<Uuid>,
+    origin: RequestOrigin,
 ) -> Result<HttpResponse, AppError> {
     let collection_id = path.into_inner();
+    let user_id = user_id.into_inner();
 
-    // Verify the collection exists and belongs to the user
-    let collection_exists = sqlx::query!(
+    // Verify the collection exists in a workspace of the user
+    let collection = sqlx::query_as!(
+        Collection,
         r#"
-        SELECT id FROM collections
-        WHERE id = $1 AND user_id = $2
+        SELECT * FROM collections
+        WHERE id = $1 AND deleted_at IS NULL
+          AND workspace_id IN (SELECT workspace_id FROM workspace_access WHERE user_id = $2)
         "#,
         collection_id,
-        user_id.into_inner()
+        user_id
     )
     .fetch_optional(pool.get_ref())
+    .await?
+    .ok_or_else(|| AppError::NotFoundError("Collection not found".to_string()))?;
+
+    authorize(
+        pool.get_ref(),
+        collection.workspace_id,
+        user_id,
+        Permission::Edit,
+    )
     .await?;
 
-    if collection_exists.is_none() {
-        return Err(AppError::NotFoundError("Collection not found".to_string()));
-    }
+    // Requests are trashed with the same timestamp so they can be restored together
+    let deleted_at = chrono::Utc::now().naive_utc();
+    let mut tx = pool.begin().await?;
 
-    // Delete all requests in the collection first
+    // Move all requests in the collection to the trash first
     sqlx::query!(
         r#"
-        DELETE FROM requests
-        WHERE collection_id = $1
+        UPDATE requests
+        SET deleted_at = $1
+        WHERE collection_id = $2 AND deleted_at IS NULL
         "#,
+        deleted_at,
         collection_id
     )
-    .execute(pool.get_ref())
+    .execute(&mut *tx)
     .await?;
 
-    // Delete the collection
+    // Move the collection to the trash
     sqlx::query!(
         r#"
-        DELETE FROM collections
-        WHERE id = $1
+        UPDATE collections
+        SET deleted_at = $1
+        WHERE id = $2
         "#,
+        deleted_at,
         collection_id
     )
-    .execute(pool.get_ref())
+    .execute(&mut *tx)
+    .await?;
+
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            workspace_id: Some(collection.workspace_id),
+            actor_id: Some(user_id),
+            action: "collection.deleted",
+            target_id: Some(collection_id),
+            before: Some(collection.audit_summary()),
+            ..Default::default()
+        },
+    )
     .await?;
 
+    tx.commit().await?;
+
     // Return success with no content
     Ok(HttpResponse::NoContent().finish())
 }
+
+pub async fn duplicate_collection(
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    duplicate_dto: Option<web::Json<DuplicateCollectionDto>>,
+    user_id: web::ReqData<Uuid>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let collection_id = path.into_inner();
+    let user_id = user_id.into_inner();
+    let duplicate_dto = duplicate_dto
+        .map(|dto| dto.into_inner())
+        .unwrap_or_default();
+
+    // Get the collection to copy
+    let source = sqlx::query_as!(
+        Collection,
+        r#"
+        SELECT * FROM collections
+        WHERE id = $1 AND deleted_at IS NULL
+          AND workspace_id IN (SELECT workspace_id FROM workspace_access WHERE user_id = $2)
+        "#,
+        collection_id,
+        user_id
+    )
+    .fetch_optional(pool.get_ref())
+    .await?
+    .ok_or_else(|| AppError::NotFoundError("Collection not found".to_string()))?;
+
+    // If a target collection is provided, verify it exists in a workspace of the user
+    let target = match duplicate_dto.target_collection_id {
+        Some(target_collection_id) => Some(
+            sqlx::query_as!(
+                Collection,
+                r#"
+                SELECT * FROM collections
+                WHERE id = $1 AND deleted_at IS NULL
+                  AND workspace_id IN (SELECT workspace_id FROM workspace_access WHERE user_id = $2)
+                "#,
+                target_collection_id,
+                user_id
+            )
+            .fetch_optional(pool.get_ref())
+            .await?
+            .ok_or_else(|| AppError::NotFoundError("Target collection not found".to_string()))?,
+        ),
+        None => None,
+    };
+
+    // Copying needs edit rights where the copies are made, only read access to the source
+    let workspace_id = target
+        .as_ref()
+        .map_or(source.workspace_id, |target| target.workspace_id);
+    authorize(pool.get_ref(), workspace_id, user_id, Permission::Edit).await?;
+
+    // Get all requests to copy
+    let requests = sqlx::query_as!(
+        Request,
+        r#"
+        SELECT * FROM requests
+        WHERE collection_id = $1 AND deleted_at IS NULL
+        ORDER BY created_at ASC
+        "#,
+        collection_id
+    )
+    .fetch_all(pool.get_ref())
+    .await?;
+
+    let mut tx = pool.begin().await?;
+
+    // Copy into the target collection or into a new copy of the collection
+    let collection = match target {
+        Some(target) => target,
+        None => {
+            let name = duplicate_dto
+                .name
+                .clone()
+                .unwrap_or_else(|| copy_name(&source.name));
+            copy_collection(&mut tx, &source, &name, user_id).await?
+        }
+    };
+
+    let mut request_responses = Vec::with_capacity(requests.len());
+    for request in &requests {
+        let copy = copy_request(
+            &mut tx,
+            request,
+            &request.name,
+            Some(collection.id),
+            collection.workspace_id,
+            user_id,
+        )
+        .await?;
+        request_responses.push(copy.to_response());
+    }
+
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            workspace_id: Some(collection.workspace_id),
+            actor_id: Some(user_id),
+            action: "collection.duplicated",
+            target_id: Some(collection.id),
+            details: Some(json!({ "source_id": source.id, "requests": requests.len() })),
+            after: Some(collection.audit_summary()),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    // Return the collection with the copied requests
+    Ok(HttpResponse::Created().json(collection.to_response_with_requests(request_responses)))
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn run_collection(
+    pool: web::Data<PgPool>,
+    config: web::Data<Config>,
+    policy: web::Data<OutboundPolicy>,
+    vault: Option<web::Data<SecretVault>>,
+    limiter: Option<web::Data<RateLimiter>>,
+    path: web::Path<Uuid>,
+    user_id: web::ReqData<Uuid>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let user_id = user_id.into_inner();
+    let policy = policy.into_inner();
+    let collection = get_member_collection(pool.get_ref(), path.into_inner(), user_id).await?;
+
+    authorize(
+        pool.get_ref(),
+        collection.workspace_id,
+        user_id,
+        Permission::Run,
+    )
+    .await?;
+
+    let requests = get_collection_requests(pool.get_ref(), collection.id).await?;
+    let started_at = chrono::Utc::now().naive_utc();
+
+    // Send the requests one after the other, a failing request does not stop the run
+    let mut results = Vec::with_capacity(requests.len());
+    for (index, request) in requests.iter().enumerate() {
+        // Every request sent takes a token of the execute budget, as a single send does
+        if let Some(Err(retry_after)) = limiter
+            .as_ref()
+            .map(|limiter| limiter.check(RateLimitScope::Execute, &user_key(&user_id)))
+        {
+            if index == 0 {
+                return Err(AppError::RateLimitError(
+                    "Too many execute requests, try again later".to_string(),
+                    retry_after,
+                ));
+            }
+
+            // What was sent so far is still reported, the rest is left out of the run
+            results.extend(requests[index..].iter().map(|request| RunResult {
+                request_id: request.id,
+                name: request.name.clone(),
+                status: None,
+                duration_ms: 0,
+                error: Some(format!(
+                    "Not sent, too many execute requests, try again in {} seconds",
+                    retry_after
+                )),
+            }));
+            break;
+        }
+
+        let request_started_at = Instant::now();
+        let outcome = match resolve_request(request, Some(&collection)) {
+            Ok(resolved) => {
+                execute_and_record(
+                    pool.get_ref(),
+                    &config.execution,
+                    &policy,
+                    vault.as_ref().map(|vault| vault.get_ref()),
+                    &resolved,
+                    user_id,
+                )
+                .await
+            }
+            Err(e) => Err(e),
+        };
+
+        let (status, error) = match outcome {
+            Ok(result) => (Some(result.status), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+
+        results.push(RunResult {
+            request_id: request.id,
+            name: request.name.clone(),
+            status,
+            duration_ms: request_started_at.elapsed().as_millis() as i64,
+            error,
+        });
+    }
+
+    record_audit(
+        pool.get_ref(),
+        &origin,
+        AuditEntry {
+            workspace_id: Some(collection.workspace_id),
+            actor_id: Some(user_id),
+            action: "collection.run",
+            target_id: Some(collection.id),
+            details: Some(json!({
+                "requests": results.len(),
+                "failed": results.iter().filter(|r| r.error.is_some()).count(),
+            })),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    // Return the outcome of every request
+    Ok(HttpResponse::Ok().json(CollectionRunResponse {
+        collection_id: collection.id,
+        started_at,
+        results,
+    }))
+}
+
+pub async fn export_collection(
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    user_id: web::ReqData<Uuid>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let user_id = user_id.into_inner();
+    let collection = get_member_collection(pool.get_ref(), path.into_inner(), user_id).await?;
+
+    authorize(
+        pool.get_ref(),
+        collection.workspace_id,
+        user_id,
+        Permission::Export,
+    )
+    .await?;
+
+    let requests = get_collection_requests(pool.get_ref(), collection.id).await?;
+
+    record_audit(
+        pool.get_ref(),
+        &origin,
+        AuditEntry {
+            workspace_id: Some(collection.workspace_id),
+            actor_id: Some(user_id),
+            action: "collection.exported",
+            target_id: Some(collection.id),
+            details: Some(json!({ "requests": requests.len() })),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    // Return the collection as a portable document
+    Ok(HttpResponse::Ok().json(CollectionExport::new(&collection, &requests)))
+}
+
+// Load a collection from one of the workspaces of the user
+async fn get_member_collection(
+    pool: &PgPool,
+    collection_id: Uuid,
+    user_id: Uuid,
+) -> Result<Collection, AppError> {
+    let collection = sqlx::query_as!(
+        Collection,
+        r#"
+        SELECT * FROM collections
+        WHERE id = $1 AND deleted_at IS NULL
+          AND workspace_id IN (SELECT workspace_id FROM workspace_access WHERE user_id = $2)
+        "#,
+        collection_id,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::NotFoundError("Collection not found".to_string()))?;
+
+    Ok(collection)
+}
+
+// Requests of a collection in the order they were created
+async fn get_collection_requests(
+    pool: &PgPool,
+    collection_id: Uuid,
+) -> Result<Vec<Request>, AppError> {
+    let requests = sqlx::query_as!(
+        Request,
+        r#"
+        SELECT * FROM requests
+        WHERE collection_id = $1 AND deleted_at IS NULL
+        ORDER BY created_at ASC
+        "#,
+        collection_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(requests)
+}
+
+// Conditions shared by the count and page queries of the collection list
+fn push_collection_filters(
+    builder: &mut QueryBuilder<'_, Postgres>,
+    user_id: Uuid,
+    query: &CollectionListQuery,
+) {
+    builder
+        .push(
+            " WHERE c.workspace_id IN \
+             (SELECT workspace_id FROM workspace_access WHERE user_id = ",
+        )
+        .push_bind(user_id)
+        .push(") AND c.deleted_at IS NULL");
+
+    if let Some(workspace_id) = query.workspace_id {
+        builder
+            .push(" AND c.workspace_id = ")
+            .push_bind(workspace_id);
+    }
+
+    if let Some(tag) = &query.tag {
+        builder
+            .push(
+                " AND EXISTS (SELECT 1 FROM collection_tags ct \
+                 JOIN tags t ON t.id = ct.tag_id \
+                 WHERE ct.collection_id = c.id AND t.name = ",
+            )
+            .push_bind(tag.clone())
+            .push(")");
+    }
+
+    if let Some(updated_after) = query.updated_after {
+        builder
+            .push(" AND c.updated_at >= ")
+            .push_bind(updated_after);
+    }
+
+    if let Some(updated_before) = query.updated_before {
+        builder
+            .push(" AND c.updated_at < ")
+            .push_bind(updated_before);
+    }
+}
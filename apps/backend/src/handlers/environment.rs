@@ -0,0 +1,293 @@
+use actix_web::{web, HttpResponse};
+use serde_json::Value;
+use sqlx::PgPool;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::error::AppError;
+use crate::models::{
+    CreateEnvironmentDto, Environment, EnvironmentResponse, Permission, UpdateEnvironmentDto,
+    WorkspaceQuery,
+};
+use crate::utils::audit::{record_audit, AuditEntry, RequestOrigin};
+use crate::utils::workspace::{authorize, target_workspace};
+
+pub async fn create_environment(
+    pool: web::Data<PgPool>,
+    environment_dto: web::Json<CreateEnvironmentDto>,
+    user_id: web::ReqData<Uuid>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let user_id = user_id.into_inner();
+
+    // Validate the environment data
+    environment_dto
+        .validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    // Environments go to the personal workspace unless another one is picked
+    let workspace_id =
+        target_workspace(pool.get_ref(), environment_dto.workspace_id, user_id).await?;
+    authorize(pool.get_ref(), workspace_id, user_id, Permission::Edit).await?;
+
+    // Check if the workspace already has an environment with this name
+    let environment_exists = sqlx::query!(
+        "SELECT id FROM environments WHERE workspace_id = $1 AND name = $2",
+        workspace_id,
+        environment_dto.name
+    )
+    .fetch_optional(pool.get_ref())
+    .await?;
+
+    if environment_exists.is_some() {
+        return Err(AppError::ConflictError(
+            "Environment with this name already exists".to_string(),
+        ));
+    }
+
+    let variables = environment_dto
+        .variables
+        .clone()
+        .unwrap_or_else(|| Value::Object(serde_json::Map::new()));
+
+    let mut tx = pool.begin().await?;
+
+    // Insert the environment into the database
+    let environment = sqlx::query_as!(
+        Environment,
+        r#"
+        INSERT INTO environments (id, workspace_id, name, variables, user_id, created_at, updated_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING id, workspace_id, name, variables, user_id, created_at, updated_at
+        "#,
+        Uuid::new_v4(),
+        workspace_id,
+        environment_dto.name,
+        variables,
+        user_id,
+        chrono::Utc::now().naive_utc(),
+        chrono::Utc::now().naive_utc()
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            workspace_id: Some(workspace_id),
+            actor_id: Some(user_id),
+            action: "environment.created",
+            target_id: Some(environment.id),
+            after: Some(environment.audit_summary()),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    // Return the environment
+    Ok(HttpResponse::Created().json(environment.to_response()))
+}
+
+pub async fn get_environments(
+    pool: web::Data<PgPool>,
+    query: web::Query<WorkspaceQuery>,
+    user_id: web::ReqData<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    // Get all environments in the workspaces of the user
+    let environments = sqlx::query_as!(
+        Environment,
+        r#"
+        SELECT * FROM environments
+        WHERE workspace_id IN (SELECT workspace_id FROM workspace_access WHERE user_id = $1)
+          AND ($2::UUID IS NULL OR workspace_id = $2)
+        ORDER BY name ASC
+        "#,
+        user_id.into_inner(),
+        query.workspace_id
+    )
+    .fetch_all(pool.get_ref())
+    .await?;
+
+    // Transform to response objects
+    let environment_responses: Vec<EnvironmentResponse> =
+        environments.into_iter().map(|e| e.to_response()).collect();
+
+    // Return the environments
+    Ok(HttpResponse::Ok().json(environment_responses))
+}
+
+pub async fn get_environment(
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    user_id: web::ReqData<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let environment =
+        get_member_environment(pool.get_ref(), path.into_inner(), user_id.into_inner()).await?;
+
+    // Return the environment
+    Ok(HttpResponse::Ok().json(environment.to_response()))
+}
+
+pub async fn update_environment(
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    environment_dto: web::Json<UpdateEnvironmentDto>,
+    user_id: web::ReqData<Uuid>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let environment_id = path.into_inner();
+    let user_id = user_id.into_inner();
+
+    // Validate the environment data
+    environment_dto
+        .validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    // Validate the environment exists in a workspace of the user
+    let environment = get_member_environment(pool.get_ref(), environment_id, user_id).await?;
+
+    authorize(
+        pool.get_ref(),
+        environment.workspace_id,
+        user_id,
+        Permission::Edit,
+    )
+    .await?;
+    let before = environment.audit_summary();
+
+    // Renaming must not clash with another environment of the workspace
+    if let Some(name) = &environment_dto.name {
+        let environment_exists = sqlx::query!(
+            "SELECT id FROM environments WHERE workspace_id = $1 AND name = $2 AND id <> $3",
+            environment.workspace_id,
+            name,
+            environment_id
+        )
+        .fetch_optional(pool.get_ref())
+        .await?;
+
+        if environment_exists.is_some() {
+            return Err(AppError::ConflictError(
+                "Environment with this name already exists".to_string(),
+            ));
+        }
+    }
+
+    // Update only provided fields
+    let name = environment_dto.name.clone().unwrap_or(environment.name);
+    let variables = environment_dto
+        .variables
+        .clone()
+        .unwrap_or(environment.variables);
+
+    let mut tx = pool.begin().await?;
+
+    // Update the environment in the database
+    let updated_environment = sqlx::query_as!(
+        Environment,
+        r#"
+        UPDATE environments
+        SET name = $1, variables = $2, updated_at = $3
+        WHERE id = $4
+        RETURNING id, workspace_id, name, variables, user_id, created_at, updated_at
+        "#,
+        name,
+        variables,
+        chrono::Utc::now().naive_utc(),
+        environment_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            workspace_id: Some(updated_environment.workspace_id),
+            actor_id: Some(user_id),
+            action: "environment.updated",
+            target_id: Some(environment_id),
+            before: Some(before),
+            after: Some(updated_environment.audit_summary()),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    // Return the updated environment
+    Ok(HttpResponse::Ok().json(updated_environment.to_response()))
+}
+
+pub async fn delete_environment(
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    user_id: web::ReqData<Uuid>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let user_id = user_id.into_inner();
+
+    // Validate the environment exists in a workspace of the user
+    let environment = get_member_environment(pool.get_ref(), path.into_inner(), user_id).await?;
+
+    authorize(
+        pool.get_ref(),
+        environment.workspace_id,
+        user_id,
+        Permission::Edit,
+    )
+    .await?;
+
+    let mut tx = pool.begin().await?;
+
+    // Delete the environment
+    sqlx::query!("DELETE FROM environments WHERE id = $1", environment.id)
+        .execute(&mut *tx)
+        .await?;
+
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            workspace_id: Some(environment.workspace_id),
+            actor_id: Some(user_id),
+            action: "environment.deleted",
+            target_id: Some(environment.id),
+            before: Some(environment.audit_summary()),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    // Return success with no content
+    Ok(HttpResponse::NoContent().finish())
+}
+
+// Load an environment from one of the workspaces of the user
+async fn get_member_environment(
+    pool: &PgPool,
+    environment_id: Uuid,
+    user_id: Uuid,
+) -> Result<Environment, AppError> {
+    let environment = sqlx::query_as!(
+        Environment,
+        r#"
+        SELECT * FROM environments
+        WHERE id = $1
+          AND workspace_id IN (SELECT workspace_id FROM workspace_access WHERE user_id = $2)
+        "#,
+        environment_id,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::NotFoundError("Environment not found".to_string()))?;
+
+    Ok(environment)
+}
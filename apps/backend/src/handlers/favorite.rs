@@ -0,0 +1,179 @@
+use actix_web::{web, HttpResponse};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::models::{Collection, FavoritesResponse, Request};
+
+pub async fn get_favorites(
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = user_id.into_inner();
+
+    // Get the favorite collections, skipping the ones in the trash or in workspaces the user left
+    let collections = sqlx::query_as!(
+        Collection,
+        r#"
+        SELECT c.* FROM collections c
+        JOIN favorites f ON f.collection_id = c.id
+        WHERE f.user_id = $1 AND c.deleted_at IS NULL
+          AND c.workspace_id IN (SELECT workspace_id FROM workspace_access WHERE user_id = $1)
+        ORDER BY f.created_at DESC
+        "#,
+        user_id
+    )
+    .fetch_all(pool.get_ref())
+    .await?;
+
+    // Get the favorite requests, skipping the ones in the trash or in workspaces the user left
+    let requests = sqlx::query_as!(
+        Request,
+        r#"
+        SELECT r.* FROM requests r
+        JOIN favorites f ON f.request_id = r.id
+        WHERE f.user_id = $1 AND r.deleted_at IS NULL
+          AND r.workspace_id IN (SELECT workspace_id FROM workspace_access WHERE user_id = $1)
+        ORDER BY f.created_at DESC
+        "#,
+        user_id
+    )
+    .fetch_all(pool.get_ref())
+    .await?;
+
+    // Return the favorites
+    Ok(HttpResponse::Ok().json(FavoritesResponse {
+        collections: collections.iter().map(|c| c.to_response()).collect(),
+        requests: requests.iter().map(|r| r.to_response()).collect(),
+    }))
+}
+
+pub async fn favorite_request(
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    user_id: web::ReqData<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let request_id = path.into_inner();
+    let user_id = user_id.into_inner();
+
+    // Verify the request exists in a workspace of the user
+    let request_exists = sqlx::query!(
+        r#"
+        SELECT id FROM requests
+        WHERE id = $1 AND deleted_at IS NULL
+          AND workspace_id IN (SELECT workspace_id FROM workspace_access WHERE user_id = $2)
+        "#,
+        request_id,
+        user_id
+    )
+    .fetch_optional(pool.get_ref())
+    .await?;
+
+    if request_exists.is_none() {
+        return Err(AppError::NotFoundError("Request not found".to_string()));
+    }
+
+    // Pin the request, pinning it twice is a no-op
+    sqlx::query!(
+        r#"
+        INSERT INTO favorites (id, user_id, request_id, created_at)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (user_id, request_id) WHERE request_id IS NOT NULL DO NOTHING
+        "#,
+        Uuid::new_v4(),
+        user_id,
+        request_id,
+        chrono::Utc::now().naive_utc()
+    )
+    .execute(pool.get_ref())
+    .await?;
+
+    // Return success with no content
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn unfavorite_request(
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    user_id: web::ReqData<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    // Unpin the request
+    sqlx::query!(
+        r#"
+        DELETE FROM favorites
+        WHERE request_id = $1 AND user_id = $2
+        "#,
+        path.into_inner(),
+        user_id.into_inner()
+    )
+    .execute(pool.get_ref())
+    .await?;
+
+    // Return success with no content
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn favorite_collection(
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    user_id: web::ReqData<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let collection_id = path.into_inner();
+    let user_id = user_id.into_inner();
+
+    // Verify the collection exists in a workspace of the user
+    let collection_exists = sqlx::query!(
+        r#"
+        SELECT id FROM collections
+        WHERE id = $1 AND deleted_at IS NULL
+          AND workspace_id IN (SELECT workspace_id FROM workspace_access WHERE user_id = $2)
+        "#,
+        collection_id,
+        user_id
+    )
+    .fetch_optional(pool.get_ref())
+    .await?;
+
+    if collection_exists.is_none() {
+        return Err(AppError::NotFoundError("Collection not found".to_string()));
+    }
+
+    // Pin the collection, pinning it twice is a no-op
+    sqlx::query!(
+        r#"
+        INSERT INTO favorites (id, user_id, collection_id, created_at)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (user_id, collection_id) WHERE collection_id IS NOT NULL DO NOTHING
+        "#,
+        Uuid::new_v4(),
+        user_id,
+        collection_id,
+        chrono::Utc::now().naive_utc()
+    )
+    .execute(pool.get_ref())
+    .await?;
+
+    // Return success with no content
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn unfavorite_collection(
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    user_id: web::ReqData<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    // Unpin the collection
+    sqlx::query!(
+        r#"
+        DELETE FROM favorites
+        WHERE collection_id = $1 AND user_id = $2
+        "#,
+        path.into_inner(),
+        user_id.into_inner()
+    )
+    .execute(pool.get_ref())
+    .await?;
+
+    // Return success with no content
+    Ok(HttpResponse::NoContent().finish())
+}
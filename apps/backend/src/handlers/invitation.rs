@@ -0,0 +1,424 @@
+use actix_web::{web, HttpResponse};
+use chrono::Duration;
+use serde_json::json;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::config::Config;
+use crate::error::AppError;
+use crate::models::{
+    CreateInvitationDto, Invitation, InvitationPreview, Permission, Workspace, WorkspaceRole,
+};
+use crate::utils::audit::{record_audit, AuditEntry, RequestOrigin};
+use crate::utils::token::{generate_token, hash_token};
+use crate::utils::workspace::authorize;
+
+pub async fn create_invitation(
+    pool: web::Data<PgPool>,
+    config: web::Data<Config>,
+    path: web::Path<Uuid>,
+    invitation_dto: web::Json<CreateInvitationDto>,
+    user_id: web::ReqData<Uuid>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let workspace_id = path.into_inner();
+    let user_id = user_id.into_inner();
+
+    // Validate the invitation data
+    invitation_dto
+        .validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    // Only owners and admins can invite
+    authorize(
+        pool.get_ref(),
+        workspace_id,
+        user_id,
+        Permission::ManageMembers,
+    )
+    .await?;
+
+    // A workspace has a single owner
+    let role = invitation_dto.role.unwrap_or(WorkspaceRole::Editor);
+    if role == WorkspaceRole::Owner {
+        return Err(AppError::ValidationError(
+            "A workspace can only have one owner".to_string(),
+        ));
+    }
+
+    // Check if the email already belongs to a member
+    let member_exists = sqlx::query!(
+        r#"
+        SELECT m.user_id FROM workspace_members m
+        JOIN users u ON u.id = m.user_id
+        WHERE m.workspace_id = $1 AND LOWER(u.email) = LOWER($2)
+        "#,
+        workspace_id,
+        invitation_dto.email
+    )
+    .fetch_optional(pool.get_ref())
+    .await?;
+
+    if member_exists.is_some() {
+        return Err(AppError::ConflictError(
+            "User is already a member of this workspace".to_string(),
+        ));
+    }
+
+    let now = chrono::Utc::now().naive_utc();
+    let expires_at = now
+        + Duration::days(
+            invitation_dto
+                .expires_in_days
+                .unwrap_or(config.invitation_expiry_days),
+        );
+
+    let mut tx = pool.begin().await?;
+
+    // Expired invitations no longer count as open
+    sqlx::query!(
+        r#"
+        UPDATE workspace_invitations
+        SET status = 'revoked', responded_at = $3
+        WHERE workspace_id = $1 AND LOWER(email) = LOWER($2)
+          AND status = 'pending' AND expires_at <= $3
+        "#,
+        workspace_id,
+        invitation_dto.email,
+        now
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    // Insert the invitation, only the hash of the token is kept
+    let token = generate_token();
+    let invitation = sqlx::query_as!(
+        Invitation,
+        r#"
+        INSERT INTO workspace_invitations
+            (id, workspace_id, email, role, token_hash, invited_by, expires_at, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        ON CONFLICT (workspace_id, LOWER(email)) WHERE status = 'pending' DO NOTHING
+        RETURNING *
+        "#,
+        Uuid::new_v4(),
+        workspace_id,
+        invitation_dto.email,
+        role.as_str(),
+        hash_token(&token),
+        user_id,
+        expires_at,
+        now
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| {
+        AppError::ConflictError("This email already has a pending invitation".to_string())
+    })?;
+
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            workspace_id: Some(workspace_id),
+            actor_id: Some(user_id),
+            action: "invitation.created",
+            target_id: Some(invitation.id),
+            details: Some(json!({ "email": invitation.email, "role": invitation.role })),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    // Return the invitation together with its token
+    let mut response = invitation.to_response();
+    response.token = Some(token);
+    Ok(HttpResponse::Created().json(response))
+}
+
+pub async fn get_invitations(
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    user_id: web::ReqData<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let workspace_id = path.into_inner();
+
+    // Only owners and admins can see the invitations
+    authorize(
+        pool.get_ref(),
+        workspace_id,
+        user_id.into_inner(),
+        Permission::ManageMembers,
+    )
+    .await?;
+
+    // Get all invitations of the workspace, newest first
+    let invitations = sqlx::query_as!(
+        Invitation,
+        r#"
+        SELECT * FROM workspace_invitations
+        WHERE workspace_id = $1
+        ORDER BY created_at DESC
+        "#,
+        workspace_id
+    )
+    .fetch_all(pool.get_ref())
+    .await?;
+
+    // Return the invitations
+    Ok(HttpResponse::Ok().json(
+        invitations
+            .iter()
+            .map(|i| i.to_response())
+            .collect::<Vec<_>>(),
+    ))
+}
+
+pub async fn revoke_invitation(
+    pool: web::Data<PgPool>,
+    path: web::Path<(Uuid, Uuid)>,
+    user_id: web::ReqData<Uuid>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let (workspace_id, invitation_id) = path.into_inner();
+    let user_id = user_id.into_inner();
+
+    // Only owners and admins can revoke invitations
+    authorize(
+        pool.get_ref(),
+        workspace_id,
+        user_id,
+        Permission::ManageMembers,
+    )
+    .await?;
+
+    let mut tx = pool.begin().await?;
+
+    // Revoke the invitation if it is still open
+    let invitation = sqlx::query!(
+        r#"
+        UPDATE workspace_invitations
+        SET status = 'revoked', responded_at = $3
+        WHERE id = $1 AND workspace_id = $2 AND status = 'pending'
+        RETURNING email
+        "#,
+        invitation_id,
+        workspace_id,
+        chrono::Utc::now().naive_utc()
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| AppError::NotFoundError("Invitation not found".to_string()))?;
+
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            workspace_id: Some(workspace_id),
+            actor_id: Some(user_id),
+            action: "invitation.revoked",
+            target_id: Some(invitation_id),
+            details: Some(json!({ "email": invitation.email })),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    // Return success with no content
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn get_invitation(
+    pool: web::Data<PgPool>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    // Look the invitation up by its token
+    let invitation = sqlx::query_as!(
+        Invitation,
+        "SELECT * FROM workspace_invitations WHERE token_hash = $1",
+        hash_token(&path.into_inner())
+    )
+    .fetch_optional(pool.get_ref())
+    .await?
+    .ok_or_else(|| AppError::NotFoundError("Invitation not found".to_string()))?;
+
+    // Get the workspace and the inviter to show
+    let details = sqlx::query!(
+        r#"
+        SELECT w.name AS workspace_name, COALESCE(u.name, u.email) AS invited_by
+        FROM workspaces w
+        LEFT JOIN users u ON u.id = $2
+        WHERE w.id = $1
+        "#,
+        invitation.workspace_id,
+        invitation.invited_by
+    )
+    .fetch_one(pool.get_ref())
+    .await?;
+
+    // Return the preview
+    Ok(HttpResponse::Ok().json(InvitationPreview {
+        workspace_id: invitation.workspace_id,
+        workspace_name: details.workspace_name,
+        email: invitation.email.clone(),
+        role: invitation.role.clone(),
+        status: invitation.display_status(),
+        invited_by: details.invited_by,
+        expires_at: invitation.expires_at,
+    }))
+}
+
+pub async fn accept_invitation(
+    pool: web::Data<PgPool>,
+    path: web::Path<String>,
+    user_id: web::ReqData<Uuid>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let user_id = user_id.into_inner();
+
+    let mut tx = pool.begin().await?;
+
+    // The invitation must be open and addressed to the user
+    let invitation = get_open_invitation(&mut tx, &path.into_inner(), user_id).await?;
+
+    // Join the workspace
+    let member = sqlx::query!(
+        r#"
+        INSERT INTO workspace_members (workspace_id, user_id, role, created_at)
+        VALUES ($1, $2, $3, $4)
+        ON CONFLICT (workspace_id, user_id) DO NOTHING
+        RETURNING role
+        "#,
+        invitation.workspace_id,
+        user_id,
+        invitation.role,
+        chrono::Utc::now().naive_utc()
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| {
+        AppError::ConflictError("You are already a member of this workspace".to_string())
+    })?;
+
+    respond_to_invitation(&mut tx, &invitation, user_id, "accepted", &origin).await?;
+
+    let workspace = sqlx::query_as!(
+        Workspace,
+        "SELECT * FROM workspaces WHERE id = $1",
+        invitation.workspace_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    // Return the workspace that was joined
+    Ok(HttpResponse::Ok().json(workspace.to_response(member.role.parse()?)))
+}
+
+pub async fn decline_invitation(
+    pool: web::Data<PgPool>,
+    path: web::Path<String>,
+    user_id: web::ReqData<Uuid>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let user_id = user_id.into_inner();
+
+    let mut tx = pool.begin().await?;
+
+    // The invitation must be open and addressed to the user
+    let invitation = get_open_invitation(&mut tx, &path.into_inner(), user_id).await?;
+
+    respond_to_invitation(&mut tx, &invitation, user_id, "declined", &origin).await?;
+
+    tx.commit().await?;
+
+    // Return success with no content
+    Ok(HttpResponse::NoContent().finish())
+}
+
+// Lock a pending, unexpired invitation sent to the email of the user
+async fn get_open_invitation(
+    conn: &mut PgConnection,
+    token: &str,
+    user_id: Uuid,
+) -> Result<Invitation, AppError> {
+    let invitation = sqlx::query_as!(
+        Invitation,
+        r#"
+        SELECT * FROM workspace_invitations
+        WHERE token_hash = $1
+        FOR UPDATE
+        "#,
+        hash_token(token)
+    )
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or_else(|| AppError::NotFoundError("Invitation not found".to_string()))?;
+
+    if invitation.status != "pending" {
+        return Err(AppError::ConflictError(format!(
+            "This invitation was already {}",
+            invitation.status
+        )));
+    }
+
+    if invitation.is_expired() {
+        return Err(AppError::ConflictError(
+            "This invitation has expired".to_string(),
+        ));
+    }
+
+    let user = sqlx::query!("SELECT email FROM users WHERE id = $1", user_id)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    if !user.email.eq_ignore_ascii_case(&invitation.email) {
+        return Err(AppError::ForbiddenError(
+            "This invitation was sent to another email address".to_string(),
+        ));
+    }
+
+    Ok(invitation)
+}
+
+// Close the invitation with the answer of the user
+async fn respond_to_invitation(
+    conn: &mut PgConnection,
+    invitation: &Invitation,
+    user_id: Uuid,
+    status: &str,
+    origin: &RequestOrigin,
+) -> Result<(), AppError> {
+    sqlx::query!(
+        r#"
+        UPDATE workspace_invitations
+        SET status = $2, responded_at = $3
+        WHERE id = $1
+        "#,
+        invitation.id,
+        status,
+        chrono::Utc::now().naive_utc()
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    record_audit(
+        &mut *conn,
+        origin,
+        AuditEntry {
+            workspace_id: Some(invitation.workspace_id),
+            actor_id: Some(user_id),
+            action: &format!("invitation.{}", status),
+            target_id: Some(invitation.id),
+            details: Some(json!({ "email": invitation.email, "role": invitation.role })),
+            ..Default::default()
+        },
+    )
+    .await
+}
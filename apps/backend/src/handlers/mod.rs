@@ -1,11 +1,76 @@
+pub mod account;
+pub mod api_key;
+pub mod audit;
 pub mod auth;
+pub mod client_certificate;
 pub mod collection;
+pub mod environment;
+pub mod favorite;
+pub mod invitation;
+pub mod oidc;
 pub mod request;
+pub mod revision;
+pub mod search;
+pub mod secret;
+pub mod share;
+pub mod tag;
+pub mod trash;
+pub mod two_factor;
+pub mod workspace;
 
-pub use auth::{get_current_user, login, register};
+pub use account::{
+    change_password, delete_account, export_account, request_password_reset, resend_verification,
+    reset_password, update_profile, verify_email,
+};
+pub use api_key::{create_api_key, get_api_keys, revoke_api_key};
+pub use audit::{get_audit_log, get_my_audit_log};
+pub use auth::{
+    get_current_user, get_jwks, login, login_two_factor, logout, logout_everywhere, refresh,
+    register,
+};
+pub use client_certificate::{
+    create_client_certificate, delete_client_certificate, get_client_certificates,
+    update_client_certificate,
+};
 pub use collection::{
-    create_collection, delete_collection, get_collection, get_collections, update_collection,
+    create_collection, delete_collection, duplicate_collection, export_collection, get_collection,
+    get_collections, run_collection, update_collection,
+};
+pub use environment::{
+    create_environment, delete_environment, get_environment, get_environments, update_environment,
+};
+pub use favorite::{
+    favorite_collection, favorite_request, get_favorites, unfavorite_collection, unfavorite_request,
+};
+pub use invitation::{
+    accept_invitation, create_invitation, decline_invitation, get_invitation, get_invitations,
+    revoke_invitation,
 };
+pub use oidc::{oidc_callback, oidc_login};
 pub use request::{
-    create_request, delete_request, execute, get_request, get_requests, update_request,
+    create_request, delete_request, duplicate_request, execute, get_effective_request,
+    get_executions, get_request, get_requests, update_request,
+};
+pub use revision::{
+    get_collection_revisions, get_request_revisions, restore_collection_revision,
+    restore_request_revision,
+};
+pub use search::search;
+pub use secret::{create_secret, delete_secret, get_secrets, rotate_secret_key, update_secret};
+pub use share::{create_share_link, get_share_links, get_shared_collection, revoke_share_link};
+pub use tag::{
+    create_tag, delete_tag, get_collection_tags, get_request_tags, get_tags, set_collection_tags,
+    set_request_tags, update_tag,
+};
+pub use trash::{
+    empty_trash, get_trash, purge_trashed_collection, purge_trashed_request, restore_collection,
+    restore_request,
+};
+pub use two_factor::{
+    disable_two_factor, enable_two_factor, regenerate_recovery_codes, setup_two_factor,
+};
+pub use workspace::{
+    create_workspace, delete_workspace, delete_workspace_proxy, get_members, get_workspace,
+    get_workspace_proxy, get_workspaces, remove_member, transfer_ownership, update_member,
+    update_workspace, update_workspace_proxy,
 };
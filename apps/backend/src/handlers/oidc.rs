@@ -0,0 +1,435 @@
+use actix_web::cookie::{time, Cookie, SameSite};
+use actix_web::{http::header, web, HttpRequest, HttpResponse};
+use bcrypt::{hash, DEFAULT_COST};
+use chrono::{Duration, Utc};
+use serde_json::json;
+use sqlx::{PgConnection, PgPool};
+use url::Url;
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::error::AppError;
+use crate::models::{OidcCallbackQuery, User};
+use crate::utils::audit::{record_audit, AuditEntry, RequestOrigin};
+use crate::utils::jwt::JwtKeys;
+use crate::utils::login_lockout::{clear_failed_logins, ensure_not_locked};
+use crate::utils::oidc::{IdTokenClaims, OidcClient};
+use crate::utils::session::start_session;
+use crate::utils::token::{generate_token, hash_token};
+use crate::utils::two_factor::create_login_challenge;
+use crate::utils::workspace::{insert_workspace, PERSONAL_WORKSPACE_NAME};
+
+// Ties the callback to the browser that started the login
+const STATE_COOKIE: &str = "oidc_state";
+const STATE_EXPIRY_MINUTES: i64 = 10;
+
+pub async fn oidc_login(
+    pool: web::Data<PgPool>,
+    config: web::Data<Config>,
+    oidc: Option<web::Data<OidcClient>>,
+) -> Result<HttpResponse, AppError> {
+    let oidc = oidc.ok_or_else(sso_not_configured)?;
+
+    // Fresh values for every login, only the hash of the state is kept
+    let state = generate_token();
+    let nonce = generate_token();
+    let code_verifier = generate_token();
+    let location = oidc
+        .authorization_url(&state, &nonce, &code_verifier)
+        .await?;
+
+    let now = Utc::now().naive_utc();
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!("DELETE FROM oidc_states WHERE expires_at <= $1", now)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO oidc_states (id, state_hash, nonce, code_verifier, expires_at, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        "#,
+        Uuid::new_v4(),
+        hash_token(&state),
+        nonce,
+        code_verifier,
+        now + Duration::minutes(STATE_EXPIRY_MINUTES),
+        now
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    let cookie = Cookie::build(STATE_COOKIE, state)
+        .path("/auth/oidc")
+        .http_only(true)
+        .secure(config.oidc.redirect_url.starts_with("https://"))
+        .same_site(SameSite::Lax)
+        .max_age(time::Duration::minutes(STATE_EXPIRY_MINUTES))
+        .finish();
+
+    // Send the browser to the identity provider
+    Ok(HttpResponse::Found()
+        .insert_header((header::LOCATION, location))
+        .cookie(cookie)
+        .finish())
+}
+
+pub async fn oidc_callback(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    config: web::Data<Config>,
+    keys: web::Data<JwtKeys>,
+    oidc: Option<web::Data<OidcClient>>,
+    query: web::Query<OidcCallbackQuery>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let oidc = oidc.ok_or_else(sso_not_configured)?;
+
+    if let Some(error) = &query.error {
+        return Err(AppError::AuthError(format!(
+            "The identity provider refused the login: {}",
+            query.error_description.as_deref().unwrap_or(error)
+        )));
+    }
+    let (Some(code), Some(state)) = (&query.code, &query.state) else {
+        return Err(AppError::BadRequestError(
+            "The callback is missing the code or the state".to_string(),
+        ));
+    };
+
+    // The state has to come back to the browser it was handed to
+    let cookie_state = req.cookie(STATE_COOKIE);
+    if cookie_state.as_ref().map(|cookie| cookie.value()) != Some(state.as_str()) {
+        return Err(AppError::AuthError(
+            "The login was started elsewhere, start it again".to_string(),
+        ));
+    }
+
+    let mut tx = pool.begin().await?;
+
+    // Each state is good for one callback
+    let login = sqlx::query!(
+        r#"
+        DELETE FROM oidc_states
+        WHERE state_hash = $1 AND expires_at > $2
+        RETURNING nonce, code_verifier
+        "#,
+        hash_token(state),
+        Utc::now().naive_utc()
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| AppError::AuthError("The login has expired, start it again".to_string()))?;
+
+    let claims = oidc
+        .exchange_code(code, &login.code_verifier, &login.nonce)
+        .await?;
+
+    let user = find_or_create_user(&mut tx, &oidc, &claims, &origin).await?;
+
+    // The provider only replaces the password, a locked account stays locked
+    ensure_not_locked(&user)?;
+
+    // Accounts with 2FA still need their code, the provider only replaces the password
+    let fragment = if user.totp_enabled_at.is_some() {
+        let expires_in = Duration::minutes(config.login_challenge_expiry_minutes);
+        let challenge_token = create_login_challenge(&mut tx, user.id, expires_in).await?;
+
+        format!(
+            "two_factor_required=true&challenge_token={}&expires_in={}",
+            challenge_token,
+            expires_in.num_seconds()
+        )
+    } else {
+        clear_failed_logins(&mut *tx, user.id).await?;
+        let tokens = start_session(&mut tx, &config, &keys, user.id, &origin).await?;
+
+        record_audit(
+            &mut *tx,
+            &origin,
+            AuditEntry {
+                actor_id: Some(user.id),
+                action: "user.logged_in",
+                target_id: Some(user.id),
+                details: Some(json!({ "method": "oidc", "issuer": claims.iss })),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        format!(
+            "token={}&refresh_token={}&expires_in={}",
+            tokens.token, tokens.refresh_token, tokens.expires_in
+        )
+    };
+
+    tx.commit().await?;
+
+    // Hand the tokens to the app in the fragment, which is never sent to a server
+    let mut location = Url::parse(&config.mail.app_url)
+        .and_then(|url| url.join("auth/callback"))
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+    location.set_fragment(Some(&fragment));
+
+    let mut expired_cookie = Cookie::build(STATE_COOKIE, "").path("/auth/oidc").finish();
+    expired_cookie.make_removal();
+
+    Ok(HttpResponse::Found()
+        .insert_header((header::LOCATION, location.to_string()))
+        .cookie(expired_cookie)
+        .finish())
+}
+
+// The user linked to the provider account, linked by verified email or created on first login
+async fn find_or_create_user(
+    conn: &mut PgConnection,
+    oidc: &OidcClient,
+    claims: &IdTokenClaims,
+    origin: &RequestOrigin,
+) -> Result<User, AppError> {
+    let now = Utc::now().naive_utc();
+
+    let linked = sqlx::query_as!(
+        User,
+        r#"
+        UPDATE user_identities i SET last_login_at = $3, email = COALESCE($4, i.email)
+        FROM users u
+        WHERE u.id = i.user_id AND i.issuer = $1 AND i.subject = $2
+        RETURNING u.*
+        "#,
+        claims.iss,
+        claims.sub,
+        now,
+        claims.email
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    if let Some(user) = linked {
+        return Ok(user);
+    }
+
+    // Only an address the provider vouches for can be matched to an account
+    let email = claims
+        .email
+        .as_deref()
+        .filter(|_| claims.email_verified == Some(true))
+        .ok_or_else(|| {
+            AppError::ForbiddenError(
+                "The identity provider did not share a verified email address".to_string(),
+            )
+        })?;
+
+    let existing = sqlx::query_as!(
+        User,
+        "SELECT * FROM users WHERE LOWER(email) = LOWER($1)",
+        email
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let user = match existing {
+        // Whoever registered an unproven address may not be its owner, linking would hand
+        // the owner an account that someone else still holds the password and sessions of
+        Some(user) if user.email_verified_at.is_none() => {
+            return Err(AppError::ForbiddenError(
+                "An account with this email address exists but the address is not verified, verify it or reset the password before signing in with single sign-on".to_string(),
+            ))
+        }
+        Some(user) => user,
+        None if oidc.allow_signup() => {
+            // The account gets a random password, a password reset sets a real one
+            let password_hash = hash(generate_token(), DEFAULT_COST)
+                .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+            let user = sqlx::query_as!(
+                User,
+                r#"
+                INSERT INTO users
+                    (id, email, password_hash, name, created_at, updated_at, email_verified_at)
+                VALUES ($1, $2, $3, $4, $5, $5, $5)
+                RETURNING *
+                "#,
+                Uuid::new_v4(),
+                email,
+                password_hash,
+                claims.name,
+                now
+            )
+            .fetch_one(&mut *conn)
+            .await?;
+
+            // Every account starts with a personal workspace
+            insert_workspace(conn, PERSONAL_WORKSPACE_NAME, None, true, user.id).await?;
+
+            record_audit(
+                &mut *conn,
+                origin,
+                AuditEntry {
+                    actor_id: Some(user.id),
+                    action: "user.registered",
+                    target_id: Some(user.id),
+                    after: Some(json!({ "email": user.email, "name": user.name })),
+                    details: Some(json!({ "method": "oidc", "issuer": claims.iss })),
+                    ..Default::default()
+                },
+            )
+            .await?;
+
+            user
+        }
+        None => {
+            return Err(AppError::ForbiddenError(
+                "No account exists for this email address".to_string(),
+            ))
+        }
+    };
+
+    sqlx::query!(
+        r#"
+        INSERT INTO user_identities
+            (id, user_id, issuer, subject, email, last_login_at, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $6)
+        "#,
+        Uuid::new_v4(),
+        user.id,
+        claims.iss,
+        claims.sub,
+        email,
+        now
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    record_audit(
+        &mut *conn,
+        origin,
+        AuditEntry {
+            actor_id: Some(user.id),
+            action: "user.sso_linked",
+            target_id: Some(user.id),
+            details: Some(json!({ "issuer": claims.iss, "email": email })),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    Ok(user)
+}
+
+fn sso_not_configured() -> AppError {
+    AppError::NotFoundError("Single sign-on is not configured".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::OidcConfig;
+    use crate::db::tests::TestDatabase;
+
+    // The claims the mock provider in src/bin/mock_oidc.rs puts in its ID tokens
+    fn claims(email: &str) -> IdTokenClaims {
+        serde_json::from_value(json!({
+            "iss": "http://127.0.0.1:9090",
+            "sub": hash_token(email)[..24],
+            "email": email,
+            "email_verified": true,
+            "name": "Dev User",
+        }))
+        .unwrap()
+    }
+
+    fn oidc() -> OidcClient {
+        OidcClient::from_config(&OidcConfig {
+            enabled: true,
+            issuer_url: Some("http://127.0.0.1:9090".to_string()),
+            client_id: Some("endpoint".to_string()),
+            client_secret: None,
+            redirect_url: "http://127.0.0.1:8088/auth/oidc/callback".to_string(),
+            scopes: vec!["openid".to_string(), "email".to_string()],
+            allow_signup: false,
+        })
+        .unwrap()
+        .unwrap()
+    }
+
+    async fn insert_user(conn: &mut PgConnection, email: &str, verified: bool) -> Uuid {
+        let user_id = Uuid::new_v4();
+        sqlx::query!(
+            r#"
+            INSERT INTO users (id, email, password_hash, name, email_verified_at)
+            VALUES ($1, $2, 'hash', 'A', CASE WHEN $3 THEN NOW() END)
+            "#,
+            user_id,
+            email,
+            verified
+        )
+        .execute(&mut *conn)
+        .await
+        .unwrap();
+        user_id
+    }
+
+    async fn identities(conn: &mut PgConnection, user_id: Uuid) -> i64 {
+        sqlx::query_scalar!(
+            r#"SELECT COUNT(*) AS "count!" FROM user_identities WHERE user_id = $1"#,
+            user_id
+        )
+        .fetch_one(&mut *conn)
+        .await
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn unverified_accounts_are_not_linked() {
+        let database = TestDatabase::create().await;
+        {
+            let mut conn = database.pool.acquire().await.unwrap();
+            let user_id = insert_user(&mut conn, "squatted@x.test", false).await;
+
+            let result = find_or_create_user(
+                &mut conn,
+                &oidc(),
+                &claims("Squatted@x.test"),
+                &RequestOrigin::default(),
+            )
+            .await;
+            assert!(matches!(result, Err(AppError::ForbiddenError(_))));
+            assert_eq!(identities(&mut conn, user_id).await, 0);
+
+            let user = sqlx::query_as!(User, "SELECT * FROM users WHERE id = $1", user_id)
+                .fetch_one(&mut *conn)
+                .await
+                .unwrap();
+            assert!(user.email_verified_at.is_none());
+            assert_eq!(user.password_hash, "hash");
+        }
+        database.drop().await;
+    }
+
+    #[tokio::test]
+    async fn verified_accounts_are_linked_once() {
+        let database = TestDatabase::create().await;
+        {
+            let mut conn = database.pool.acquire().await.unwrap();
+            let user_id = insert_user(&mut conn, "owner@x.test", true).await;
+
+            for _ in 0..2 {
+                let user = find_or_create_user(
+                    &mut conn,
+                    &oidc(),
+                    &claims("owner@x.test"),
+                    &RequestOrigin::default(),
+                )
+                .await
+                .unwrap();
+                assert_eq!(user.id, user_id);
+            }
+            assert_eq!(identities(&mut conn, user_id).await, 1);
+        }
+        database.drop().await;
+    }
+}
@@ -1,17 +1,37 @@
 use actix_web::{web, HttpResponse};
-use serde_json::Value;
-use sqlx::PgPool;
+use serde_json::{json, Value};
+use sqlx::{PgPool, Postgres, QueryBuilder};
 use uuid::Uuid;
 use validator::Validate;
 
+use crate::config::Config;
 use crate::error::AppError;
-use crate::models::{CreateRequestDto, Request, UpdateRequestDto};
-use crate::utils::http::{execute_request, HttpRequestResult};
+use crate::models::{
+    Collection, CreateRequestDto, DuplicateRequestDto, EntityType, Execution, Permission, Request,
+    RequestListQuery, UpdateRequestDto,
+};
+use crate::utils::audit::{record_audit, AuditEntry, RequestOrigin};
+use crate::utils::duplicate::{copy_name, copy_request};
+use crate::utils::execution::execute_and_record;
+use crate::utils::json::to_json;
+use crate::utils::outbound::OutboundPolicy;
+use crate::utils::pagination::{
+    page_size, push_cursor_condition, push_order_by, sort_value, Cursor, Page,
+};
+use crate::utils::redact::redact_url;
+use crate::utils::resolve::resolve_request;
+use crate::utils::revision::record_revision;
+use crate::utils::secrets::SecretVault;
+use crate::utils::workspace::{authorize, target_workspace};
+
+// Number of stored executions returned by the history endpoint
+const EXECUTION_HISTORY_LIMIT: i64 = 50;
 
 pub async fn create_request(
     pool: web::Data<PgPool>,
     request_dto: web::Json<CreateRequestDto>,
     user_id: web::ReqData<Uuid>,
+    origin: RequestOrigin,
 ) -> Result<HttpResponse, AppError> {
     let user_id = user_id.into_inner();
 
@@ -20,23 +40,25 @@ pub async fn create_request(
         .validate()
         .map_err(|e| AppError::ValidationError(e.to_string()))?;
 
-    // If collection_id is provided, verify it exists and belongs to the user
-    if let Some(collection_id) = request_dto.collection_id {
-        let collection_exists = sqlx::query!(
-            r#"
-            SELECT id FROM collections
-            WHERE id = $1 AND user_id = $2
-            "#,
-            collection_id,
-            user_id
-        )
-        .fetch_optional(pool.get_ref())
-        .await?;
-
-        if collection_exists.is_none() {
-            return Err(AppError::NotFoundError("Collection not found".to_string()));
-        }
-    }
+    // If collection_id is provided, verify it exists in a workspace of the user
+    let collection_workspace_id = match request_dto.collection_id {
+        Some(collection_id) => Some(
+            sqlx::query!(
+                r#"
+                SELECT workspace_id FROM collections
+                WHERE id = $1 AND deleted_at IS NULL
+                  AND workspace_id IN (SELECT workspace_id FROM workspace_access WHERE user_id = $2)
+                "#,
+                collection_id,
+                user_id
+            )
+            .fetch_optional(pool.get_ref())
+            .await?
+            .ok_or_else(|| AppError::NotFoundError("Collection not found".to_string()))?
+            .workspace_id,
+        ),
+        None => None,
+    };
 
     // Prepare headers, body, and params
     let headers = request_dto
@@ -45,6 +67,28 @@ pub async fn create_request(
         .unwrap_or_else(|| Value::Object(serde_json::Map::new()));
     let body = request_dto.body.clone();
     let params = request_dto.params.clone();
+    let auth = to_json(request_dto.auth.as_ref())?;
+    let settings = to_json(request_dto.settings.as_ref())?
+        .unwrap_or_else(|| Value::Object(serde_json::Map::new()));
+
+    let mut tx = pool.begin().await?;
+
+    // Requests live in the workspace of their collection, otherwise in the picked or personal one
+    let workspace_id = match collection_workspace_id {
+        Some(collection_workspace_id) => {
+            if request_dto
+                .workspace_id
+                .is_some_and(|workspace_id| workspace_id != collection_workspace_id)
+            {
+                return Err(AppError::ValidationError(
+                    "The collection belongs to another workspace".to_string(),
+                ));
+            }
+            collection_workspace_id
+        }
+        None => target_workspace(&mut *tx, request_dto.workspace_id, user_id).await?,
+    };
+    authorize(&mut *tx, workspace_id, user_id, Permission::Edit).await?;
 
     // Insert the request into the database
     let request = sqlx::query_as!(
@@ -52,11 +96,12 @@ pub async fn create_request(
         r#"
         INSERT INTO requests (
             id, name, description, url, method, headers, body, params,
-            collection_id, user_id, created_at, updated_at
+            collection_id, user_id, created_at, updated_at, auth, settings, workspace_id
         )
-        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
         RETURNING id, name, description, url, method, headers, body, params,
-                  collection_id, user_id, created_at, updated_at
+                  collection_id, user_id, created_at, updated_at, auth, settings,
+                  deleted_at, workspace_id
         "#,
         Uuid::new_v4(),
         request_dto.name,
@@ -69,31 +114,80 @@ pub async fn create_request(
         request_dto.collection_id,
         user_id,
         chrono::Utc::now().naive_utc(),
-        chrono::Utc::now().naive_utc()
+        chrono::Utc::now().naive_utc(),
+        auth,
+        settings,
+        workspace_id
     )
-    .fetch_one(pool.get_ref())
+    .fetch_one(&mut *tx)
     .await?;
 
+    // Start the history of the request
+    record_revision(&mut tx, EntityType::Request, request.id, &request, user_id).await?;
+
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            workspace_id: Some(workspace_id),
+            actor_id: Some(user_id),
+            action: "request.created",
+            target_id: Some(request.id),
+            after: Some(request.audit_summary()),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+
     // Return the request
     Ok(HttpResponse::Created().json(request.to_response()))
 }
 
 pub async fn get_requests(
     pool: web::Data<PgPool>,
+    query: web::Query<RequestListQuery>,
     user_id: web::ReqData<Uuid>,
 ) -> Result<HttpResponse, AppError> {
-    // Get all requests for the user
-    let requests = sqlx::query_as!(
-        Request,
-        r#"
-        SELECT * FROM requests
-        WHERE user_id = $1
-        ORDER BY created_at DESC
-        "#,
-        user_id.into_inner()
-    )
-    .fetch_all(pool.get_ref())
-    .await?;
+    let user_id = user_id.into_inner();
+    let limit = page_size(query.limit)?;
+    let cursor = query.cursor.as_deref().map(Cursor::decode).transpose()?;
+
+    // Count all matching requests
+    let mut count_query = QueryBuilder::new("SELECT COUNT(*) FROM requests r");
+    push_request_filters(&mut count_query, user_id, &query);
+    let total: i64 = count_query
+        .build_query_scalar()
+        .fetch_one(pool.get_ref())
+        .await?;
+
+    // Get one page of matching requests, plus one row to know if there is a next page
+    let mut page_query = QueryBuilder::new("SELECT r.* FROM requests r");
+    push_request_filters(&mut page_query, user_id, &query);
+    if let Some(cursor) = &cursor {
+        push_cursor_condition(&mut page_query, "r", query.sort, query.order, cursor)?;
+    }
+    push_order_by(&mut page_query, "r", query.sort, query.order);
+    page_query.push(" LIMIT ").push_bind(limit + 1);
+
+    let mut requests = page_query
+        .build_query_as::<Request>()
+        .fetch_all(pool.get_ref())
+        .await?;
+
+    let next_cursor = if requests.len() as i64 > limit {
+        requests.truncate(limit as usize);
+        requests.last().map(|last| {
+            Cursor {
+                value: sort_value(query.sort, &last.name, last.created_at, last.updated_at),
+                id: last.id,
+            }
+            .encode()
+        })
+    } else {
+        None
+    };
 
     // Transform to response objects
     let request_responses = requests
@@ -101,8 +195,12 @@ pub async fn get_requests(
         .map(|r| r.to_response())
         .collect::<Vec<_>>();
 
-    // Return the requests
-    Ok(HttpResponse::Ok().json(request_responses))
+    // Return the page of requests
+    Ok(HttpResponse::Ok().json(Page {
+        items: request_responses,
+        total,
+        next_cursor,
+    }))
 }
 
 pub async fn get_request(
@@ -117,7 +215,8 @@ pub async fn get_request(
         Request,
         r#"
         SELECT * FROM requests
-        WHERE id = $1 AND user_id = $2
+        WHERE id = $1 AND deleted_at IS NULL
+          AND workspace_id IN (SELECT workspace_id FROM workspace_access WHERE user_id = $2)
         "#,
         request_id,
         user_id.into_inner()
@@ -135,16 +234,23 @@ pub async fn update_request(
     path: web::Path<Uuid>,
     request_dto: web::Json<UpdateRequestDto>,
     user_id: web::ReqData<Uuid>,
+    origin: RequestOrigin,
 ) -> Result<HttpResponse, AppError> {
     let request_id = path.into_inner();
     let user_id = user_id.into_inner();
 
-    // Validate the request exists and belongs to the user
+    // Validate the request data
+    request_dto
+        .validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    // Validate the request exists in a workspace of the user
     let request = sqlx::query_as!(
         Request,
         r#"
         SELECT * FROM requests
-        WHERE id = $1 AND user_id = $2
+        WHERE id = $1 AND deleted_at IS NULL
+          AND workspace_id IN (SELECT workspace_id FROM workspace_access WHERE user_id = $2)
         "#,
         request_id,
         user_id
@@ -153,15 +259,24 @@ pub async fn update_request(
     .await?
     .ok_or_else(|| AppError::NotFoundError("Request not found".to_string()))?;
 
-    // If collection_id is provided, verify it exists and belongs to the user
+    authorize(
+        pool.get_ref(),
+        request.workspace_id,
+        user_id,
+        Permission::Edit,
+    )
+    .await?;
+    let before = request.audit_summary();
+
+    // If collection_id is provided, verify it exists in the workspace of the request
     if let Some(collection_id) = request_dto.collection_id {
         let collection_exists = sqlx::query!(
             r#"
             SELECT id FROM collections
-            WHERE id = $1 AND user_id = $2
+            WHERE id = $1 AND workspace_id = $2 AND deleted_at IS NULL
             "#,
             collection_id,
-            user_id
+            request.workspace_id
         )
         .fetch_optional(pool.get_ref())
         .await?;
@@ -180,6 +295,10 @@ pub async fn update_request(
     let body = request_dto.body.clone().or(request.body);
     let params = request_dto.params.clone().or(request.params);
     let collection_id = request_dto.collection_id.or(request.collection_id);
+    let auth = to_json(request_dto.auth.as_ref())?.or(request.auth);
+    let settings = to_json(request_dto.settings.as_ref())?.unwrap_or(request.settings);
+
+    let mut tx = pool.begin().await?;
 
     // Update the request in the database
     let updated_request = sqlx::query_as!(
@@ -187,10 +306,12 @@ pub async fn update_request(
         r#"
         UPDATE requests
         SET name = $1, description = $2, url = $3, method = $4,
-            headers = $5, body = $6, params = $7, collection_id = $8, updated_at = $9
-        WHERE id = $10
+            headers = $5, body = $6, params = $7, collection_id = $8, auth = $9,
+            settings = $10, updated_at = $11
+        WHERE id = $12
         RETURNING id, name, description, url, method, headers, body, params,
-                  collection_id, user_id, created_at, updated_at
+                  collection_id, user_id, created_at, updated_at, auth, settings,
+                  deleted_at, workspace_id
         "#,
         name,
         description,
@@ -200,12 +321,41 @@ pub async fn update_request(
         body,
         params,
         collection_id,
+        auth,
+        settings,
         chrono::Utc::now().naive_utc(),
         request_id
     )
-    .fetch_one(pool.get_ref())
+    .fetch_one(&mut *tx)
     .await?;
 
+    // Keep the previous state recoverable
+    record_revision(
+        &mut tx,
+        EntityType::Request,
+        updated_request.id,
+        &updated_request,
+        user_id,
+    )
+    .await?;
+
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            workspace_id: Some(updated_request.workspace_id),
+            actor_id: Some(user_id),
+            action: "request.updated",
+            target_id: Some(request_id),
+            before: Some(before),
+            after: Some(updated_request.audit_summary()),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+
     // Return the updated request
     Ok(HttpResponse::Ok().json(updated_request.to_response()))
 }
@@ -214,41 +364,162 @@ pub async fn delete_request(
     pool: web::Data<PgPool>,
     path: web::Path<Uuid>,
     user_id: web::ReqData<Uuid>,
+    origin: RequestOrigin,
 ) -> Result<HttpResponse, AppError> {
     let request_id = path.into_inner();
+    let user_id = user_id.into_inner();
 
-    // Verify the request exists and belongs to the user
-    let request_exists = sqlx::query!(
+    // Verify the request exists in a workspace of the user
+    let request = sqlx::query_as!(
+        Request,
         r#"
-        SELECT id FROM requests
-        WHERE id = $1 AND user_id = $2
+        SELECT * FROM requests
+        WHERE id = $1 AND deleted_at IS NULL
+          AND workspace_id IN (SELECT workspace_id FROM workspace_access WHERE user_id = $2)
         "#,
         request_id,
-        user_id.into_inner()
+        user_id
     )
     .fetch_optional(pool.get_ref())
+    .await?
+    .ok_or_else(|| AppError::NotFoundError("Request not found".to_string()))?;
+
+    authorize(
+        pool.get_ref(),
+        request.workspace_id,
+        user_id,
+        Permission::Edit,
+    )
     .await?;
 
-    if request_exists.is_none() {
-        return Err(AppError::NotFoundError("Request not found".to_string()));
-    }
+    let mut tx = pool.begin().await?;
 
-    // Delete the request
+    // Move the request to the trash
     sqlx::query!(
         r#"
-        DELETE FROM requests
-        WHERE id = $1
+        UPDATE requests
+        SET deleted_at = $1
+        WHERE id = $2
         "#,
+        chrono::Utc::now().naive_utc(),
         request_id
     )
-    .execute(pool.get_ref())
+    .execute(&mut *tx)
     .await?;
 
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            workspace_id: Some(request.workspace_id),
+            actor_id: Some(user_id),
+            action: "request.deleted",
+            target_id: Some(request_id),
+            before: Some(request.audit_summary()),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+
     // Return success with no content
     Ok(HttpResponse::NoContent().finish())
 }
 
-pub async fn execute(
+pub async fn duplicate_request(
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    duplicate_dto: Option<web::Json<DuplicateRequestDto>>,
+    user_id: web::ReqData<Uuid>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let request_id = path.into_inner();
+    let user_id = user_id.into_inner();
+    let duplicate_dto = duplicate_dto
+        .map(|dto| dto.into_inner())
+        .unwrap_or_default();
+
+    // Get the request to copy
+    let source = sqlx::query_as!(
+        Request,
+        r#"
+        SELECT * FROM requests
+        WHERE id = $1 AND deleted_at IS NULL
+          AND workspace_id IN (SELECT workspace_id FROM workspace_access WHERE user_id = $2)
+        "#,
+        request_id,
+        user_id
+    )
+    .fetch_optional(pool.get_ref())
+    .await?
+    .ok_or_else(|| AppError::NotFoundError("Request not found".to_string()))?;
+
+    // If collection_id is provided, verify it exists in a workspace of the user
+    let target_workspace_id = match duplicate_dto.collection_id {
+        Some(collection_id) => {
+            sqlx::query!(
+                r#"
+            SELECT workspace_id FROM collections
+            WHERE id = $1 AND deleted_at IS NULL
+              AND workspace_id IN (SELECT workspace_id FROM workspace_access WHERE user_id = $2)
+            "#,
+                collection_id,
+                user_id
+            )
+            .fetch_optional(pool.get_ref())
+            .await?
+            .ok_or_else(|| AppError::NotFoundError("Collection not found".to_string()))?
+            .workspace_id
+        }
+        None => source.workspace_id,
+    };
+    authorize(
+        pool.get_ref(),
+        target_workspace_id,
+        user_id,
+        Permission::Edit,
+    )
+    .await?;
+
+    let name = duplicate_dto
+        .name
+        .clone()
+        .unwrap_or_else(|| copy_name(&source.name));
+    let collection_id = duplicate_dto.collection_id.or(source.collection_id);
+
+    // Insert the copy and its first revision together
+    let mut tx = pool.begin().await?;
+    let request = copy_request(
+        &mut tx,
+        &source,
+        &name,
+        collection_id,
+        target_workspace_id,
+        user_id,
+    )
+    .await?;
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            workspace_id: Some(target_workspace_id),
+            actor_id: Some(user_id),
+            action: "request.duplicated",
+            target_id: Some(request.id),
+            details: Some(json!({ "source_id": source.id })),
+            after: Some(request.audit_summary()),
+            ..Default::default()
+        },
+    )
+    .await?;
+    tx.commit().await?;
+
+    // Return the copied request
+    Ok(HttpResponse::Created().json(request.to_response()))
+}
+
+pub async fn get_effective_request(
     pool: web::Data<PgPool>,
     path: web::Path<Uuid>,
     user_id: web::ReqData<Uuid>,
@@ -260,7 +531,8 @@ pub async fn execute(
         Request,
         r#"
         SELECT * FROM requests
-        WHERE id = $1 AND user_id = $2
+        WHERE id = $1 AND deleted_at IS NULL
+          AND workspace_id IN (SELECT workspace_id FROM workspace_access WHERE user_id = $2)
         "#,
         request_id,
         user_id.into_inner()
@@ -269,11 +541,218 @@ pub async fn execute(
     .await?
     .ok_or_else(|| AppError::NotFoundError("Request not found".to_string()))?;
 
-    // Execute the HTTP request
-    let result = execute_request(&request)
-        .await
-        .map_err(|e| AppError::BadRequestError(format!("Failed to execute request: {}", e)))?;
+    // Merge the collection defaults into the request
+    let collection = get_request_collection(pool.get_ref(), &request).await?;
+    let resolved = resolve_request(&request, collection.as_ref())?;
+
+    // Return the request as it would be executed
+    Ok(HttpResponse::Ok().json(resolved))
+}
+
+pub async fn execute(
+    pool: web::Data<PgPool>,
+    config: web::Data<Config>,
+    policy: web::Data<OutboundPolicy>,
+    vault: Option<web::Data<SecretVault>>,
+    path: web::Path<Uuid>,
+    user_id: web::ReqData<Uuid>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let request_id = path.into_inner();
+    let user_id = user_id.into_inner();
+
+    // Get the request
+    let request = sqlx::query_as!(
+        Request,
+        r#"
+        SELECT * FROM requests
+        WHERE id = $1 AND deleted_at IS NULL
+          AND workspace_id IN (SELECT workspace_id FROM workspace_access WHERE user_id = $2)
+        "#,
+        request_id,
+        user_id
+    )
+    .fetch_optional(pool.get_ref())
+    .await?
+    .ok_or_else(|| AppError::NotFoundError("Request not found".to_string()))?;
+
+    authorize(
+        pool.get_ref(),
+        request.workspace_id,
+        user_id,
+        Permission::Execute,
+    )
+    .await?;
+
+    // Merge the collection defaults into the request
+    let collection = get_request_collection(pool.get_ref(), &request).await?;
+    let resolved = resolve_request(&request, collection.as_ref())?;
+
+    // Execute the HTTP request and keep the response in the history
+    let outcome = execute_and_record(
+        pool.get_ref(),
+        &config.execution,
+        &policy.into_inner(),
+        vault.as_ref().map(|vault| vault.get_ref()),
+        &resolved,
+        user_id,
+    )
+    .await;
+
+    record_audit(
+        pool.get_ref(),
+        &origin,
+        AuditEntry {
+            workspace_id: Some(request.workspace_id),
+            actor_id: Some(user_id),
+            action: "request.executed",
+            target_id: Some(request_id),
+            details: Some(json!({
+                "method": resolved.method,
+                "url": redact_url(&resolved.url),
+                "status": outcome.as_ref().ok().map(|result| result.status),
+                "error": outcome.as_ref().err().map(|e| e.to_string()),
+            })),
+            ..Default::default()
+        },
+    )
+    .await?;
+    let result = outcome?;
 
     // Return the response
     Ok(HttpResponse::Ok().json(result))
 }
+
+pub async fn get_executions(
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    user_id: web::ReqData<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let request_id = path.into_inner();
+
+    // Verify the request exists in a workspace of the user
+    let request_exists = sqlx::query!(
+        r#"
+        SELECT id FROM requests
+        WHERE id = $1 AND deleted_at IS NULL
+          AND workspace_id IN (SELECT workspace_id FROM workspace_access WHERE user_id = $2)
+        "#,
+        request_id,
+        user_id.into_inner()
+    )
+    .fetch_optional(pool.get_ref())
+    .await?;
+
+    if request_exists.is_none() {
+        return Err(AppError::NotFoundError("Request not found".to_string()));
+    }
+
+    // Get the most recent executions of the request
+    let executions = sqlx::query_as!(
+        Execution,
+        r#"
+        SELECT * FROM executions
+        WHERE request_id = $1
+        ORDER BY executed_at DESC
+        LIMIT $2
+        "#,
+        request_id,
+        EXECUTION_HISTORY_LIMIT
+    )
+    .fetch_all(pool.get_ref())
+    .await?;
+
+    // Return the executions
+    Ok(HttpResponse::Ok().json(executions))
+}
+
+// Load the collection a request inherits its defaults from
+async fn get_request_collection(
+    pool: &PgPool,
+    request: &Request,
+) -> Result<Option<Collection>, AppError> {
+    let collection_id = match request.collection_id {
+        Some(collection_id) => collection_id,
+        None => return Ok(None),
+    };
+
+    let collection = sqlx::query_as!(
+        Collection,
+        r#"
+        SELECT * FROM collections
+        WHERE id = $1 AND deleted_at IS NULL
+        "#,
+        collection_id
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(collection)
+}
+
+// Conditions shared by the count and page queries of the request list
+fn push_request_filters(
+    builder: &mut QueryBuilder<'_, Postgres>,
+    user_id: Uuid,
+    query: &RequestListQuery,
+) {
+    builder
+        .push(
+            " WHERE r.workspace_id IN \
+             (SELECT workspace_id FROM workspace_access WHERE user_id = ",
+        )
+        .push_bind(user_id)
+        .push(") AND r.deleted_at IS NULL");
+
+    if let Some(workspace_id) = query.workspace_id {
+        builder
+            .push(" AND r.workspace_id = ")
+            .push_bind(workspace_id);
+    }
+
+    if let Some(method) = &query.method {
+        builder
+            .push(" AND r.method = ")
+            .push_bind(method.to_uppercase());
+    }
+
+    if let Some(collection_id) = query.collection_id {
+        builder
+            .push(" AND r.collection_id = ")
+            .push_bind(collection_id);
+    }
+
+    if let Some(host) = &query.host {
+        // Relative URLs take their host from the collection base URL
+        builder
+            .push(
+                " AND lower(substring(CASE WHEN r.url LIKE '/%' \
+                 THEN (SELECT c.base_url FROM collections c WHERE c.id = r.collection_id) \
+                 ELSE r.url END from '://([^/:?#]+)')) = ",
+            )
+            .push_bind(host.to_lowercase());
+    }
+
+    if let Some(tag) = &query.tag {
+        builder
+            .push(
+                " AND EXISTS (SELECT 1 FROM request_tags rt \
+                 JOIN tags t ON t.id = rt.tag_id \
+                 WHERE rt.request_id = r.id AND t.name = ",
+            )
+            .push_bind(tag.clone())
+            .push(")");
+    }
+
+    if let Some(updated_after) = query.updated_after {
+        builder
+            .push(" AND r.updated_at >= ")
+            .push_bind(updated_after);
+    }
+
+    if let Some(updated_before) = query.updated_before {
+        builder
+            .push(" AND r.updated_at < ")
+            .push_bind(updated_before);
+    }
+}
@@ -0,0 +1,339 @@
+use actix_web::{web, HttpResponse};
+use serde_json::{json, Value};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::models::{Collection, EntityType, Permission, Request, Revision, RevisionResponse};
+use crate::utils::audit::{record_audit, AuditEntry, RequestOrigin};
+use crate::utils::revision::{diff_snapshots, record_revision};
+use crate::utils::workspace::authorize;
+
+pub async fn get_request_revisions(
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    user_id: web::ReqData<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let request_id = path.into_inner();
+
+    // Verify the request exists in a workspace of the user
+    let request_exists = sqlx::query!(
+        r#"
+        SELECT id FROM requests
+        WHERE id = $1 AND deleted_at IS NULL
+          AND workspace_id IN (SELECT workspace_id FROM workspace_access WHERE user_id = $2)
+        "#,
+        request_id,
+        user_id.into_inner()
+    )
+    .fetch_optional(pool.get_ref())
+    .await?;
+
+    if request_exists.is_none() {
+        return Err(AppError::NotFoundError("Request not found".to_string()));
+    }
+
+    let revisions = get_revisions(pool.get_ref(), EntityType::Request, request_id).await?;
+
+    // Return the revisions, newest first
+    Ok(HttpResponse::Ok().json(revisions))
+}
+
+pub async fn restore_request_revision(
+    pool: web::Data<PgPool>,
+    path: web::Path<(Uuid, Uuid)>,
+    user_id: web::ReqData<Uuid>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let (request_id, revision_id) = path.into_inner();
+    let user_id = user_id.into_inner();
+
+    // Verify the request exists in a workspace of the user
+    let request = sqlx::query_as!(
+        Request,
+        r#"
+        SELECT * FROM requests
+        WHERE id = $1 AND deleted_at IS NULL
+          AND workspace_id IN (SELECT workspace_id FROM workspace_access WHERE user_id = $2)
+        "#,
+        request_id,
+        user_id
+    )
+    .fetch_optional(pool.get_ref())
+    .await?
+    .ok_or_else(|| AppError::NotFoundError("Request not found".to_string()))?;
+
+    authorize(
+        pool.get_ref(),
+        request.workspace_id,
+        user_id,
+        Permission::Edit,
+    )
+    .await?;
+
+    // Load the snapshot to restore
+    let revision =
+        get_revision(pool.get_ref(), EntityType::Request, request_id, revision_id).await?;
+    let snapshot: Request = serde_json::from_value(revision.snapshot)
+        .map_err(|e| AppError::InternalServerError(format!("Invalid snapshot: {}", e)))?;
+
+    // The collection may have been deleted since the revision was taken
+    let collection_id = match snapshot.collection_id {
+        Some(collection_id) => sqlx::query!(
+            r#"
+            SELECT id FROM collections
+            WHERE id = $1 AND workspace_id = $2 AND deleted_at IS NULL
+            "#,
+            collection_id,
+            request.workspace_id
+        )
+        .fetch_optional(pool.get_ref())
+        .await?
+        .map(|collection| collection.id),
+        None => None,
+    };
+
+    let mut tx = pool.begin().await?;
+
+    // Write the snapshot back to the request
+    let restored_request = sqlx::query_as!(
+        Request,
+        r#"
+        UPDATE requests
+        SET name = $1, description = $2, url = $3, method = $4,
+            headers = $5, body = $6, params = $7, collection_id = $8, auth = $9,
+            settings = $10, updated_at = $11
+        WHERE id = $12
+        RETURNING id, name, description, url, method, headers, body, params,
+                  collection_id, user_id, created_at, updated_at, auth, settings,
+                  deleted_at, workspace_id
+        "#,
+        snapshot.name,
+        snapshot.description,
+        snapshot.url,
+        snapshot.method,
+        snapshot.headers,
+        snapshot.body,
+        snapshot.params,
+        collection_id,
+        snapshot.auth,
+        snapshot.settings,
+        chrono::Utc::now().naive_utc(),
+        request_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    // A restore is a change of its own, so it can be undone as well
+    record_revision(
+        &mut tx,
+        EntityType::Request,
+        restored_request.id,
+        &restored_request,
+        user_id,
+    )
+    .await?;
+
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            workspace_id: Some(request.workspace_id),
+            actor_id: Some(user_id),
+            action: "request.revision_restored",
+            target_id: Some(request_id),
+            details: Some(json!({ "revision_id": revision_id })),
+            before: Some(request.audit_summary()),
+            after: Some(restored_request.audit_summary()),
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    // Return the restored request
+    Ok(HttpResponse::Ok().json(restored_request.to_response()))
+}
+
+pub async fn get_collection_revisions(
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    user_id: web::ReqData<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let collection_id = path.into_inner();
+
+    // Verify the collection exists in a workspace of the user
+    let collection_exists = sqlx::query!(
+        r#"
+        SELECT id FROM collections
+        WHERE id = $1 AND deleted_at IS NULL
+          AND workspace_id IN (SELECT workspace_id FROM workspace_access WHERE user_id = $2)
+        "#,
+        collection_id,
+        user_id.into_inner()
+    )
+    .fetch_optional(pool.get_ref())
+    .await?;
+
+    if collection_exists.is_none() {
+        return Err(AppError::NotFoundError("Collection not found".to_string()));
+    }
+
+    let revisions = get_revisions(pool.get_ref(), EntityType::Collection, collection_id).await?;
+
+    // Return the revisions, newest first
+    Ok(HttpResponse::Ok().json(revisions))
+}
+
+pub async fn restore_collection_revision(
+    pool: web::Data<PgPool>,
+    path: web::Path<(Uuid, Uuid)>,
+    user_id: web::ReqData<Uuid>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let (collection_id, revision_id) = path.into_inner();
+    let user_id = user_id.into_inner();
+
+    // Verify the collection exists in a workspace of the user
+    let collection = sqlx::query_as!(
+        Collection,
+        r#"
+        SELECT * FROM collections
+        WHERE id = $1 AND deleted_at IS NULL
+          AND workspace_id IN (SELECT workspace_id FROM workspace_access WHERE user_id = $2)
+        "#,
+        collection_id,
+        user_id
+    )
+    .fetch_optional(pool.get_ref())
+    .await?
+    .ok_or_else(|| AppError::NotFoundError("Collection not found".to_string()))?;
+
+    authorize(
+        pool.get_ref(),
+        collection.workspace_id,
+        user_id,
+        Permission::Edit,
+    )
+    .await?;
+
+    // Load the snapshot to restore
+    let revision = get_revision(
+        pool.get_ref(),
+        EntityType::Collection,
+        collection_id,
+        revision_id,
+    )
+    .await?;
+    let snapshot: Collection = serde_json::from_value(revision.snapshot)
+        .map_err(|e| AppError::InternalServerError(format!("Invalid snapshot: {}", e)))?;
+
+    let mut tx = pool.begin().await?;
+
+    // Write the snapshot back to the collection
+    let restored_collection = sqlx::query_as!(
+        Collection,
+        r#"
+        UPDATE collections
+        SET name = $1, description = $2, base_url = $3, default_headers = $4,
+            default_params = $5, auth = $6, settings = $7, updated_at = $8
+        WHERE id = $9
+        RETURNING id, name, description, user_id, created_at, updated_at,
+                  base_url, default_headers, default_params, auth, settings, deleted_at,
+                  workspace_id
+        "#,
+        snapshot.name,
+        snapshot.description,
+        snapshot.base_url,
+        snapshot.default_headers,
+        snapshot.default_params,
+        snapshot.auth,
+        snapshot.settings,
+        chrono::Utc::now().naive_utc(),
+        collection_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    // A restore is a change of its own, so it can be undone as well
+    record_revision(
+        &mut tx,
+        EntityType::Collection,
+        restored_collection.id,
+        &restored_collection,
+        user_id,
+    )
+    .await?;
+
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            workspace_id: Some(collection.workspace_id),
+            actor_id: Some(user_id),
+            action: "collection.revision_restored",
+            target_id: Some(collection_id),
+            details: Some(json!({ "revision_id": revision_id })),
+            before: Some(collection.audit_summary()),
+            after: Some(restored_collection.audit_summary()),
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    // Return the restored collection
+    Ok(HttpResponse::Ok().json(restored_collection.to_response()))
+}
+
+// Load the history of an entity with the changes each revision introduced
+async fn get_revisions(
+    pool: &PgPool,
+    entity_type: EntityType,
+    entity_id: Uuid,
+) -> Result<Vec<RevisionResponse>, AppError> {
+    let revisions = sqlx::query_as!(
+        Revision,
+        r#"
+        SELECT * FROM revisions
+        WHERE entity_type = $1 AND entity_id = $2
+        ORDER BY version ASC
+        "#,
+        entity_type.as_str(),
+        entity_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut previous = Value::Null;
+    let mut responses = Vec::with_capacity(revisions.len());
+    for revision in &revisions {
+        let changes = diff_snapshots(&previous, &revision.snapshot);
+        responses.push(revision.to_response(changes));
+        previous = revision.snapshot.clone();
+    }
+    responses.reverse();
+
+    Ok(responses)
+}
+
+async fn get_revision(
+    pool: &PgPool,
+    entity_type: EntityType,
+    entity_id: Uuid,
+    revision_id: Uuid,
+) -> Result<Revision, AppError> {
+    sqlx::query_as!(
+        Revision,
+        r#"
+        SELECT * FROM revisions
+        WHERE id = $1 AND entity_type = $2 AND entity_id = $3
+        "#,
+        revision_id,
+        entity_type.as_str(),
+        entity_id
+    )
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::NotFoundError("Revision not found".to_string()))
+}
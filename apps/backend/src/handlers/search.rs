@@ -0,0 +1,134 @@
+use actix_web::{web, HttpResponse};
+use sqlx::{PgPool, QueryBuilder};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::error::AppError;
+use crate::models::{SearchQuery, SearchResult};
+use crate::utils::pagination::page_size;
+
+// Options passed to ts_headline for the result snippets
+const HEADLINE_OPTIONS: &str =
+    "StartSel=<mark>, StopSel=</mark>, MaxWords=30, MinWords=10, MaxFragments=2";
+
+pub async fn search(
+    pool: web::Data<PgPool>,
+    query: web::Query<SearchQuery>,
+    user_id: web::ReqData<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = user_id.into_inner();
+
+    // Validate the search query
+    query
+        .validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+    let limit = page_size(query.limit)?;
+
+    // Substring matches catch URL fragments and partial words the text search misses
+    let pattern = format!("%{}%", escape_like(&query.q));
+
+    // The parsed query and the workspaces to search are shared by every part of the union
+    let mut builder = QueryBuilder::new("WITH q AS (SELECT websearch_to_tsquery('simple', ");
+    builder.push_bind(&query.q).push(
+        ") AS query), \
+         workspaces_of_user AS (SELECT workspace_id AS id FROM workspace_access WHERE user_id = ",
+    );
+    builder.push_bind(user_id);
+    if let Some(workspace_id) = query.workspace_id {
+        builder.push(" AND workspace_id = ").push_bind(workspace_id);
+    }
+    builder.push(") ");
+
+    // Requests, by name, description, URL, header names and body
+    builder
+        .push(
+            "SELECT 'request' AS kind, r.id, r.workspace_id, r.id AS request_id, r.collection_id, \
+             r.name AS title, \
+             (ts_rank(request_search_vector(r.name, r.description, r.url, r.headers, r.body), \
+             q.query) + GREATEST(similarity(r.name, ",
+        )
+        .push_bind(&query.q)
+        .push("), similarity(r.url, ")
+        .push_bind(&query.q)
+        .push(
+            ")))::REAL AS rank, \
+             ts_headline('simple', concat_ws(' ', r.name, r.description, r.url, \
+             jsonb_keys_text(r.headers), r.body::TEXT), q.query, ",
+        )
+        .push_bind(HEADLINE_OPTIONS)
+        .push(
+            ") AS snippet \
+             FROM requests r, q \
+             WHERE r.workspace_id IN (SELECT id FROM workspaces_of_user) \
+             AND r.deleted_at IS NULL \
+             AND (request_search_vector(r.name, r.description, r.url, r.headers, r.body) \
+             @@ q.query OR r.name ILIKE ",
+        )
+        .push_bind(&pattern)
+        .push(" OR r.url ILIKE ")
+        .push_bind(&pattern)
+        .push(")");
+
+    // Collections, by name and description
+    builder
+        .push(
+            " UNION ALL \
+             SELECT 'collection', c.id, c.workspace_id, NULL, c.id, c.name, \
+             (ts_rank(collection_search_vector(c.name, c.description), q.query) \
+             + similarity(c.name, ",
+        )
+        .push_bind(&query.q)
+        .push(
+            "))::REAL, \
+             ts_headline('simple', concat_ws(' ', c.name, c.description), q.query, ",
+        )
+        .push_bind(HEADLINE_OPTIONS)
+        .push(
+            ") \
+             FROM collections c, q \
+             WHERE c.workspace_id IN (SELECT id FROM workspaces_of_user) \
+             AND c.deleted_at IS NULL \
+             AND (collection_search_vector(c.name, c.description) @@ q.query \
+             OR c.name ILIKE ",
+        )
+        .push_bind(&pattern)
+        .push(")");
+
+    // Stored response bodies, only when asked for as they can be large
+    if query.include_responses {
+        builder
+            .push(
+                " UNION ALL \
+                 SELECT 'response', e.id, r.workspace_id, r.id, r.collection_id, r.name, \
+                 ts_rank(to_tsvector('simple', body_search_text(e.body)), q.query)::REAL, \
+                 ts_headline('simple', body_search_text(e.body), q.query, ",
+            )
+            .push_bind(HEADLINE_OPTIONS)
+            .push(
+                ") \
+                 FROM executions e \
+                 JOIN requests r ON r.id = e.request_id, q \
+                 WHERE r.workspace_id IN (SELECT id FROM workspaces_of_user) \
+                 AND r.deleted_at IS NULL \
+                 AND to_tsvector('simple', body_search_text(e.body)) @@ q.query",
+            );
+    }
+
+    builder.push(" ORDER BY rank DESC LIMIT ").push_bind(limit);
+
+    let results = builder
+        .build_query_as::<SearchResult>()
+        .fetch_all(pool.get_ref())
+        .await?;
+
+    // Return the ranked results
+    Ok(HttpResponse::Ok().json(results))
+}
+
+// Treat LIKE wildcards in the search text literally
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
@@ -0,0 +1,313 @@
+use actix_web::{web, HttpResponse};
+use chrono::Utc;
+use serde_json::json;
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::error::AppError;
+use crate::models::{CreateSecretDto, Permission, Secret, SecretResponse, UpdateSecretDto};
+use crate::utils::audit::{record_audit, AuditEntry, RequestOrigin};
+use crate::utils::secrets::{secrets_not_configured, SecretVault};
+use crate::utils::workspace::{access_role, authorize};
+
+pub async fn get_secrets(
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    user_id: web::ReqData<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let workspace_id = path.into_inner();
+
+    // Every member may see which secrets exist, never their values
+    access_role(pool.get_ref(), workspace_id, user_id.into_inner()).await?;
+
+    let secrets = sqlx::query_as!(
+        Secret,
+        "SELECT * FROM secrets WHERE workspace_id = $1 ORDER BY name ASC",
+        workspace_id
+    )
+    .fetch_all(pool.get_ref())
+    .await?;
+
+    let secret_responses: Vec<SecretResponse> =
+        secrets.iter().map(|secret| secret.to_response()).collect();
+
+    // Return the secrets without their values
+    Ok(HttpResponse::Ok().json(secret_responses))
+}
+
+pub async fn create_secret(
+    pool: web::Data<PgPool>,
+    vault: Option<web::Data<SecretVault>>,
+    path: web::Path<Uuid>,
+    secret_dto: web::Json<CreateSecretDto>,
+    user_id: web::ReqData<Uuid>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let vault = vault.ok_or_else(secrets_not_configured)?;
+    let workspace_id = path.into_inner();
+    let user_id = user_id.into_inner();
+
+    // Validate the secret data
+    secret_dto
+        .validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    authorize(pool.get_ref(), workspace_id, user_id, Permission::Edit).await?;
+    ensure_name_available(pool.get_ref(), workspace_id, &secret_dto.name, None).await?;
+
+    let mut tx = pool.begin().await?;
+
+    let secret_id = Uuid::new_v4();
+    let (ciphertext, key_version) = vault
+        .encrypt(&mut tx, workspace_id, secret_id, &secret_dto.value)
+        .await?;
+
+    let now = Utc::now().naive_utc();
+    let secret = sqlx::query_as!(
+        Secret,
+        r#"
+        INSERT INTO secrets
+            (id, workspace_id, name, description, ciphertext, key_version, user_id, created_at, updated_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $8)
+        RETURNING *
+        "#,
+        secret_id,
+        workspace_id,
+        secret_dto.name,
+        secret_dto.description,
+        ciphertext,
+        key_version,
+        user_id,
+        now
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            workspace_id: Some(workspace_id),
+            actor_id: Some(user_id),
+            action: "secret.created",
+            target_id: Some(secret.id),
+            after: Some(secret.audit_summary()),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    // Return the secret without its value
+    Ok(HttpResponse::Created().json(secret.to_response()))
+}
+
+pub async fn update_secret(
+    pool: web::Data<PgPool>,
+    vault: Option<web::Data<SecretVault>>,
+    path: web::Path<(Uuid, Uuid)>,
+    secret_dto: web::Json<UpdateSecretDto>,
+    user_id: web::ReqData<Uuid>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let vault = vault.ok_or_else(secrets_not_configured)?;
+    let (workspace_id, secret_id) = path.into_inner();
+    let user_id = user_id.into_inner();
+
+    // Validate the secret data
+    secret_dto
+        .validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    authorize(pool.get_ref(), workspace_id, user_id, Permission::Edit).await?;
+    let secret = get_workspace_secret(pool.get_ref(), workspace_id, secret_id).await?;
+
+    if let Some(name) = &secret_dto.name {
+        ensure_name_available(pool.get_ref(), workspace_id, name, Some(secret_id)).await?;
+    }
+
+    let mut tx = pool.begin().await?;
+
+    // A new value is encrypted with the current key of the workspace
+    let (ciphertext, key_version) = match &secret_dto.value {
+        Some(value) => {
+            vault
+                .encrypt(&mut tx, workspace_id, secret_id, value)
+                .await?
+        }
+        None => (secret.ciphertext.clone(), secret.key_version),
+    };
+
+    let updated_secret = sqlx::query_as!(
+        Secret,
+        r#"
+        UPDATE secrets
+        SET name = $2, description = $3, ciphertext = $4, key_version = $5, updated_at = $6
+        WHERE id = $1
+        RETURNING *
+        "#,
+        secret_id,
+        secret_dto.name.as_deref().unwrap_or(&secret.name),
+        secret_dto
+            .description
+            .as_deref()
+            .or(secret.description.as_deref()),
+        ciphertext,
+        key_version,
+        Utc::now().naive_utc()
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            workspace_id: Some(workspace_id),
+            actor_id: Some(user_id),
+            action: "secret.updated",
+            target_id: Some(secret_id),
+            details: Some(json!({ "value_changed": secret_dto.value.is_some() })),
+            before: Some(secret.audit_summary()),
+            after: Some(updated_secret.audit_summary()),
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    // Return the updated secret without its value
+    Ok(HttpResponse::Ok().json(updated_secret.to_response()))
+}
+
+pub async fn delete_secret(
+    pool: web::Data<PgPool>,
+    path: web::Path<(Uuid, Uuid)>,
+    user_id: web::ReqData<Uuid>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let (workspace_id, secret_id) = path.into_inner();
+    let user_id = user_id.into_inner();
+
+    authorize(pool.get_ref(), workspace_id, user_id, Permission::Edit).await?;
+    let secret = get_workspace_secret(pool.get_ref(), workspace_id, secret_id).await?;
+
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!("DELETE FROM secrets WHERE id = $1", secret_id)
+        .execute(&mut *tx)
+        .await?;
+
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            workspace_id: Some(workspace_id),
+            actor_id: Some(user_id),
+            action: "secret.deleted",
+            target_id: Some(secret_id),
+            before: Some(secret.audit_summary()),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    // Return success with no content
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn rotate_secret_key(
+    pool: web::Data<PgPool>,
+    vault: Option<web::Data<SecretVault>>,
+    path: web::Path<Uuid>,
+    user_id: web::ReqData<Uuid>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let vault = vault.ok_or_else(secrets_not_configured)?;
+    let workspace_id = path.into_inner();
+    let user_id = user_id.into_inner();
+
+    // Only owners and admins manage the keys of the workspace
+    authorize(
+        pool.get_ref(),
+        workspace_id,
+        user_id,
+        Permission::ManageWorkspace,
+    )
+    .await?;
+
+    let mut tx = pool.begin().await?;
+
+    let rotation = vault.rotate_data_key(&mut tx, workspace_id).await?;
+
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            workspace_id: Some(workspace_id),
+            actor_id: Some(user_id),
+            action: "secret.key_rotated",
+            target_id: Some(workspace_id),
+            details: Some(json!({
+                "key_version": rotation.key_version,
+                "secrets": rotation.secrets,
+                "client_certificates": rotation.client_certificates,
+            })),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    // Return the new key version
+    Ok(HttpResponse::Ok().json(rotation))
+}
+
+async fn get_workspace_secret<'c>(
+    executor: impl PgExecutor<'c>,
+    workspace_id: Uuid,
+    secret_id: Uuid,
+) -> Result<Secret, AppError> {
+    let secret = sqlx::query_as!(
+        Secret,
+        "SELECT * FROM secrets WHERE id = $1 AND workspace_id = $2",
+        secret_id,
+        workspace_id
+    )
+    .fetch_optional(executor)
+    .await?
+    .ok_or_else(|| AppError::NotFoundError("Secret not found".to_string()))?;
+
+    Ok(secret)
+}
+
+async fn ensure_name_available<'c>(
+    executor: impl PgExecutor<'c>,
+    workspace_id: Uuid,
+    name: &str,
+    secret_id: Option<Uuid>,
+) -> Result<(), AppError> {
+    let secret_exists = sqlx::query!(
+        r#"
+        SELECT id FROM secrets
+        WHERE workspace_id = $1 AND name = $2 AND ($3::UUID IS NULL OR id <> $3)
+        "#,
+        workspace_id,
+        name,
+        secret_id
+    )
+    .fetch_optional(executor)
+    .await?;
+
+    if secret_exists.is_some() {
+        return Err(AppError::ConflictError(
+            "Secret with this name already exists".to_string(),
+        ));
+    }
+    Ok(())
+}
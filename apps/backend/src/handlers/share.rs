@@ -0,0 +1,323 @@
+use actix_web::{web, HttpRequest, HttpResponse};
+use bcrypt::{hash, verify, DEFAULT_COST};
+use chrono::Duration;
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::config::{Config, LoginLockoutConfig};
+use crate::error::AppError;
+use crate::models::{
+    Collection, CollectionExport, CreateShareLinkDto, Permission, Request, ShareLink,
+};
+use crate::utils::audit::{record_audit, AuditEntry, RequestOrigin};
+use crate::utils::login_lockout::{lockout_end, lockout_retry_after};
+use crate::utils::token::{generate_token, hash_token};
+use crate::utils::workspace::authorize;
+
+// Header carrying the password of a protected share link
+const SHARE_PASSWORD_HEADER: &str = "X-Share-Password";
+
+pub async fn create_share_link(
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    share_dto: web::Json<CreateShareLinkDto>,
+    user_id: web::ReqData<Uuid>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let collection_id = path.into_inner();
+    let user_id = user_id.into_inner();
+
+    // Validate the share link data
+    share_dto
+        .validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    // Sharing exposes the collection outside the workspace, like an export
+    let workspace_id = get_collection_workspace(pool.get_ref(), collection_id, user_id).await?;
+    authorize(pool.get_ref(), workspace_id, user_id, Permission::Export).await?;
+
+    let password_hash = share_dto
+        .password
+        .as_ref()
+        .map(|password| hash(password, DEFAULT_COST))
+        .transpose()
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+    let now = chrono::Utc::now().naive_utc();
+    let expires_at = share_dto
+        .expires_in_days
+        .map(|days| now + Duration::days(days));
+
+    let mut tx = pool.begin().await?;
+
+    // Insert the share link, only the hash of the token is kept
+    let token = generate_token();
+    let share_link = sqlx::query_as!(
+        ShareLink,
+        r#"
+        INSERT INTO share_links
+            (id, collection_id, workspace_id, token_hash, password_hash, expires_at, created_by, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        RETURNING *
+        "#,
+        Uuid::new_v4(),
+        collection_id,
+        workspace_id,
+        hash_token(&token),
+        password_hash,
+        expires_at,
+        user_id,
+        now
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            workspace_id: Some(workspace_id),
+            actor_id: Some(user_id),
+            action: "share.created",
+            target_id: Some(share_link.id),
+            details: Some(json!({
+                "collection_id": collection_id,
+                "has_password": share_link.password_hash.is_some(),
+                "expires_at": share_link.expires_at,
+            })),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    // Return the share link together with its token
+    let mut response = share_link.to_response();
+    response.token = Some(token);
+    Ok(HttpResponse::Created().json(response))
+}
+
+pub async fn get_share_links(
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    user_id: web::ReqData<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let collection_id = path.into_inner();
+    let user_id = user_id.into_inner();
+
+    // Only members who may share can see the links
+    let workspace_id = get_collection_workspace(pool.get_ref(), collection_id, user_id).await?;
+    authorize(pool.get_ref(), workspace_id, user_id, Permission::Export).await?;
+
+    // Get all share links of the collection, newest first
+    let share_links = sqlx::query_as!(
+        ShareLink,
+        r#"
+        SELECT * FROM share_links
+        WHERE collection_id = $1
+        ORDER BY created_at DESC
+        "#,
+        collection_id
+    )
+    .fetch_all(pool.get_ref())
+    .await?;
+
+    // Return the share links
+    Ok(HttpResponse::Ok().json(
+        share_links
+            .iter()
+            .map(|s| s.to_response())
+            .collect::<Vec<_>>(),
+    ))
+}
+
+pub async fn revoke_share_link(
+    pool: web::Data<PgPool>,
+    path: web::Path<(Uuid, Uuid)>,
+    user_id: web::ReqData<Uuid>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let (collection_id, share_id) = path.into_inner();
+    let user_id = user_id.into_inner();
+
+    // Only members who may share can revoke the links
+    let workspace_id = get_collection_workspace(pool.get_ref(), collection_id, user_id).await?;
+    authorize(pool.get_ref(), workspace_id, user_id, Permission::Export).await?;
+
+    let mut tx = pool.begin().await?;
+
+    // Revoke the share link, revoking it twice is a no-op
+    let share_link = sqlx::query!(
+        r#"
+        UPDATE share_links
+        SET revoked_at = COALESCE(revoked_at, $3)
+        WHERE id = $1 AND collection_id = $2
+        RETURNING id
+        "#,
+        share_id,
+        collection_id,
+        chrono::Utc::now().naive_utc()
+    )
+    .fetch_optional(&mut *tx)
+    .await?
+    .ok_or_else(|| AppError::NotFoundError("Share link not found".to_string()))?;
+
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            workspace_id: Some(workspace_id),
+            actor_id: Some(user_id),
+            action: "share.revoked",
+            target_id: Some(share_link.id),
+            details: Some(json!({ "collection_id": collection_id })),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    // Return success with no content
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn get_shared_collection(
+    req: HttpRequest,
+    pool: web::Data<PgPool>,
+    config: web::Data<Config>,
+    path: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    // Look the share link up by its token, revoked and expired links look missing
+    let share_link = sqlx::query_as!(
+        ShareLink,
+        "SELECT * FROM share_links WHERE token_hash = $1",
+        hash_token(&path.into_inner())
+    )
+    .fetch_optional(pool.get_ref())
+    .await?
+    .filter(|share_link| share_link.is_active())
+    .ok_or_else(|| AppError::NotFoundError("Share link not found".to_string()))?;
+
+    // Check the password of protected links, guesses lock the link like failed logins an account
+    if let Some(password_hash) = &share_link.password_hash {
+        if let Some(retry_after) = lockout_retry_after(share_link.locked_until) {
+            return Err(AppError::RateLimitError(
+                "Too many wrong passwords for this share link, try again later".to_string(),
+                retry_after,
+            ));
+        }
+
+        let password = req
+            .headers()
+            .get(SHARE_PASSWORD_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .ok_or_else(|| {
+                AppError::AuthError("This share link is protected by a password".to_string())
+            })?;
+
+        let valid_password = verify(password, password_hash)
+            .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+        if !valid_password {
+            record_failed_password(pool.get_ref(), &config.login_lockout, share_link.id).await?;
+            return Err(AppError::AuthError(
+                "Invalid share link password".to_string(),
+            ));
+        }
+    }
+
+    // Get the collection unless it was moved to the trash
+    let collection = sqlx::query_as!(
+        Collection,
+        r#"
+        SELECT * FROM collections
+        WHERE id = $1 AND deleted_at IS NULL
+        "#,
+        share_link.collection_id
+    )
+    .fetch_optional(pool.get_ref())
+    .await?
+    .ok_or_else(|| AppError::NotFoundError("Share link not found".to_string()))?;
+
+    let requests = sqlx::query_as!(
+        Request,
+        r#"
+        SELECT * FROM requests
+        WHERE collection_id = $1 AND deleted_at IS NULL
+        ORDER BY created_at ASC
+        "#,
+        collection.id
+    )
+    .fetch_all(pool.get_ref())
+    .await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE share_links SET last_accessed_at = $2, failed_password_count = 0, locked_until = NULL
+        WHERE id = $1
+        "#,
+        share_link.id,
+        chrono::Utc::now().naive_utc()
+    )
+    .execute(pool.get_ref())
+    .await?;
+
+    // Return the read-only view with the secrets redacted
+    Ok(HttpResponse::Ok().json(CollectionExport::new(&collection, &requests).redacted()))
+}
+
+// Count a wrong password, the link locks once they pile up
+async fn record_failed_password(
+    pool: &PgPool,
+    config: &LoginLockoutConfig,
+    share_link_id: Uuid,
+) -> Result<(), AppError> {
+    let failures = sqlx::query_scalar!(
+        r#"
+        UPDATE share_links SET failed_password_count = failed_password_count + 1
+        WHERE id = $1
+        RETURNING failed_password_count
+        "#,
+        share_link_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    if let Some(locked_until) = lockout_end(config, failures) {
+        sqlx::query!(
+            "UPDATE share_links SET locked_until = $2 WHERE id = $1",
+            share_link_id,
+            locked_until
+        )
+        .execute(pool)
+        .await?;
+    }
+
+    Ok(())
+}
+
+// Workspace of a collection from one of the workspaces of the user
+async fn get_collection_workspace(
+    pool: &PgPool,
+    collection_id: Uuid,
+    user_id: Uuid,
+) -> Result<Uuid, AppError> {
+    let collection = sqlx::query!(
+        r#"
+        SELECT workspace_id FROM collections
+        WHERE id = $1 AND deleted_at IS NULL
+          AND workspace_id IN (SELECT workspace_id FROM workspace_access WHERE user_id = $2)
+        "#,
+        collection_id,
+        user_id
+    )
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::NotFoundError("Collection not found".to_string()))?;
+
+    Ok(collection.workspace_id)
+}
@@ -0,0 +1,445 @@
+use actix_web::{web, HttpResponse};
+use sqlx::PgPool;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::error::AppError;
+use crate::models::{
+    CreateTagDto, Permission, SetTagsDto, Tag, TagResponse, UpdateTagDto, WorkspaceQuery,
+};
+use crate::utils::workspace::{authorize, target_workspace};
+
+pub async fn create_tag(
+    pool: web::Data<PgPool>,
+    tag_dto: web::Json<CreateTagDto>,
+    user_id: web::ReqData<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = user_id.into_inner();
+
+    // Validate the tag data
+    tag_dto
+        .validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    // Tags go to the personal workspace unless another one is picked
+    let workspace_id = target_workspace(pool.get_ref(), tag_dto.workspace_id, user_id).await?;
+    authorize(pool.get_ref(), workspace_id, user_id, Permission::Edit).await?;
+
+    // Check if the workspace already has a tag with this name
+    let tag_exists = sqlx::query!(
+        "SELECT id FROM tags WHERE workspace_id = $1 AND name = $2",
+        workspace_id,
+        tag_dto.name
+    )
+    .fetch_optional(pool.get_ref())
+    .await?;
+
+    if tag_exists.is_some() {
+        return Err(AppError::ConflictError(
+            "Tag with this name already exists".to_string(),
+        ));
+    }
+
+    // Insert the tag into the database
+    let tag = sqlx::query_as!(
+        Tag,
+        r#"
+        INSERT INTO tags (id, user_id, name, color, created_at, updated_at, workspace_id)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING id, user_id, name, color, created_at, updated_at, workspace_id
+        "#,
+        Uuid::new_v4(),
+        user_id,
+        tag_dto.name,
+        tag_dto.color,
+        chrono::Utc::now().naive_utc(),
+        chrono::Utc::now().naive_utc(),
+        workspace_id
+    )
+    .fetch_one(pool.get_ref())
+    .await?;
+
+    // Return the tag
+    Ok(HttpResponse::Created().json(tag.to_response()))
+}
+
+pub async fn get_tags(
+    pool: web::Data<PgPool>,
+    query: web::Query<WorkspaceQuery>,
+    user_id: web::ReqData<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    // Get all tags in the workspaces of the user
+    let tags = sqlx::query_as!(
+        Tag,
+        r#"
+        SELECT * FROM tags
+        WHERE workspace_id IN (SELECT workspace_id FROM workspace_access WHERE user_id = $1)
+          AND ($2::UUID IS NULL OR workspace_id = $2)
+        ORDER BY name ASC
+        "#,
+        user_id.into_inner(),
+        query.workspace_id
+    )
+    .fetch_all(pool.get_ref())
+    .await?;
+
+    // Transform to response objects
+    let tag_responses: Vec<TagResponse> = tags.into_iter().map(|t| t.to_response()).collect();
+
+    // Return the tags
+    Ok(HttpResponse::Ok().json(tag_responses))
+}
+
+pub async fn update_tag(
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    tag_dto: web::Json<UpdateTagDto>,
+    user_id: web::ReqData<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let tag_id = path.into_inner();
+    let user_id = user_id.into_inner();
+
+    // Validate the tag data
+    tag_dto
+        .validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    // Validate the tag exists in a workspace of the user
+    let tag = sqlx::query_as!(
+        Tag,
+        r#"
+        SELECT * FROM tags
+        WHERE id = $1
+          AND workspace_id IN (SELECT workspace_id FROM workspace_access WHERE user_id = $2)
+        "#,
+        tag_id,
+        user_id
+    )
+    .fetch_optional(pool.get_ref())
+    .await?
+    .ok_or_else(|| AppError::NotFoundError("Tag not found".to_string()))?;
+
+    authorize(pool.get_ref(), tag.workspace_id, user_id, Permission::Edit).await?;
+
+    // Renaming must not clash with another tag of the workspace
+    if let Some(name) = &tag_dto.name {
+        let tag_exists = sqlx::query!(
+            "SELECT id FROM tags WHERE workspace_id = $1 AND name = $2 AND id <> $3",
+            tag.workspace_id,
+            name,
+            tag_id
+        )
+        .fetch_optional(pool.get_ref())
+        .await?;
+
+        if tag_exists.is_some() {
+            return Err(AppError::ConflictError(
+                "Tag with this name already exists".to_string(),
+            ));
+        }
+    }
+
+    // Update only provided fields
+    let name = tag_dto.name.clone().unwrap_or(tag.name);
+    let color = tag_dto.color.clone().or(tag.color);
+
+    // Update the tag in the database
+    let updated_tag = sqlx::query_as!(
+        Tag,
+        r#"
+        UPDATE tags
+        SET name = $1, color = $2, updated_at = $3
+        WHERE id = $4
+        RETURNING id, user_id, name, color, created_at, updated_at, workspace_id
+        "#,
+        name,
+        color,
+        chrono::Utc::now().naive_utc(),
+        tag_id
+    )
+    .fetch_one(pool.get_ref())
+    .await?;
+
+    // Return the updated tag
+    Ok(HttpResponse::Ok().json(updated_tag.to_response()))
+}
+
+pub async fn delete_tag(
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    user_id: web::ReqData<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let tag_id = path.into_inner();
+    let user_id = user_id.into_inner();
+
+    // Verify the tag exists in a workspace of the user
+    let tag = sqlx::query!(
+        r#"
+        SELECT workspace_id FROM tags
+        WHERE id = $1
+          AND workspace_id IN (SELECT workspace_id FROM workspace_access WHERE user_id = $2)
+        "#,
+        tag_id,
+        user_id
+    )
+    .fetch_optional(pool.get_ref())
+    .await?
+    .ok_or_else(|| AppError::NotFoundError("Tag not found".to_string()))?;
+
+    authorize(pool.get_ref(), tag.workspace_id, user_id, Permission::Edit).await?;
+
+    // Delete the tag, its links to requests and collections go with it
+    sqlx::query!("DELETE FROM tags WHERE id = $1", tag_id)
+        .execute(pool.get_ref())
+        .await?;
+
+    // Return success with no content
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn get_request_tags(
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    user_id: web::ReqData<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let request_id = path.into_inner();
+
+    // Verify the request exists in a workspace of the user
+    let request_exists = sqlx::query!(
+        r#"
+        SELECT id FROM requests
+        WHERE id = $1 AND deleted_at IS NULL
+          AND workspace_id IN (SELECT workspace_id FROM workspace_access WHERE user_id = $2)
+        "#,
+        request_id,
+        user_id.into_inner()
+    )
+    .fetch_optional(pool.get_ref())
+    .await?;
+
+    if request_exists.is_none() {
+        return Err(AppError::NotFoundError("Request not found".to_string()));
+    }
+
+    let tags = get_tags_of_request(pool.get_ref(), request_id).await?;
+
+    // Return the tags
+    Ok(HttpResponse::Ok().json(tags))
+}
+
+pub async fn set_request_tags(
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    tags_dto: web::Json<SetTagsDto>,
+    user_id: web::ReqData<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let request_id = path.into_inner();
+    let user_id = user_id.into_inner();
+
+    // Verify the request exists in a workspace of the user
+    let request = sqlx::query!(
+        r#"
+        SELECT id, workspace_id FROM requests
+        WHERE id = $1 AND deleted_at IS NULL
+          AND workspace_id IN (SELECT workspace_id FROM workspace_access WHERE user_id = $2)
+        "#,
+        request_id,
+        user_id
+    )
+    .fetch_optional(pool.get_ref())
+    .await?
+    .ok_or_else(|| AppError::NotFoundError("Request not found".to_string()))?;
+
+    authorize(
+        pool.get_ref(),
+        request.workspace_id,
+        user_id,
+        Permission::Edit,
+    )
+    .await?;
+
+    verify_tags(pool.get_ref(), &tags_dto.tag_ids, request.workspace_id).await?;
+
+    let mut tx = pool.begin().await?;
+
+    // Replace the tags of the request
+    sqlx::query!("DELETE FROM request_tags WHERE request_id = $1", request_id)
+        .execute(&mut *tx)
+        .await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO request_tags (request_id, tag_id)
+        SELECT $1, UNNEST($2::UUID[])
+        ON CONFLICT DO NOTHING
+        "#,
+        request_id,
+        &tags_dto.tag_ids
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    let tags = get_tags_of_request(pool.get_ref(), request_id).await?;
+
+    // Return the new tags
+    Ok(HttpResponse::Ok().json(tags))
+}
+
+pub async fn get_collection_tags(
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    user_id: web::ReqData<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let collection_id = path.into_inner();
+
+    // Verify the collection exists in a workspace of the user
+    let collection_exists = sqlx::query!(
+        r#"
+        SELECT id FROM collections
+        WHERE id = $1 AND deleted_at IS NULL
+          AND workspace_id IN (SELECT workspace_id FROM workspace_access WHERE user_id = $2)
+        "#,
+        collection_id,
+        user_id.into_inner()
+    )
+    .fetch_optional(pool.get_ref())
+    .await?;
+
+    if collection_exists.is_none() {
+        return Err(AppError::NotFoundError("Collection not found".to_string()));
+    }
+
+    let tags = get_tags_of_collection(pool.get_ref(), collection_id).await?;
+
+    // Return the tags
+    Ok(HttpResponse::Ok().json(tags))
+}
+
+pub async fn set_collection_tags(
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    tags_dto: web::Json<SetTagsDto>,
+    user_id: web::ReqData<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let collection_id = path.into_inner();
+    let user_id = user_id.into_inner();
+
+    // Verify the collection exists in a workspace of the user
+    let collection = sqlx::query!(
+        r#"
+        SELECT id, workspace_id FROM collections
+        WHERE id = $1 AND deleted_at IS NULL
+          AND workspace_id IN (SELECT workspace_id FROM workspace_access WHERE user_id = $2)
+        "#,
+        collection_id,
+        user_id
+    )
+    .fetch_optional(pool.get_ref())
+    .await?
+    .ok_or_else(|| AppError::NotFoundError("Collection not found".to_string()))?;
+
+    authorize(
+        pool.get_ref(),
+        collection.workspace_id,
+        user_id,
+        Permission::Edit,
+    )
+    .await?;
+
+    verify_tags(pool.get_ref(), &tags_dto.tag_ids, collection.workspace_id).await?;
+
+    let mut tx = pool.begin().await?;
+
+    // Replace the tags of the collection
+    sqlx::query!(
+        "DELETE FROM collection_tags WHERE collection_id = $1",
+        collection_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO collection_tags (collection_id, tag_id)
+        SELECT $1, UNNEST($2::UUID[])
+        ON CONFLICT DO NOTHING
+        "#,
+        collection_id,
+        &tags_dto.tag_ids
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+
+    let tags = get_tags_of_collection(pool.get_ref(), collection_id).await?;
+
+    // Return the new tags
+    Ok(HttpResponse::Ok().json(tags))
+}
+
+// Make sure every tag exists in the workspace of the tagged item
+async fn verify_tags(pool: &PgPool, tag_ids: &[Uuid], workspace_id: Uuid) -> Result<(), AppError> {
+    let found = sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(DISTINCT id) AS "count!" FROM tags
+        WHERE id = ANY($1) AND workspace_id = $2
+        "#,
+        tag_ids,
+        workspace_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let mut unique_ids = tag_ids.to_vec();
+    unique_ids.sort();
+    unique_ids.dedup();
+
+    if found != unique_ids.len() as i64 {
+        return Err(AppError::NotFoundError("Tag not found".to_string()));
+    }
+
+    Ok(())
+}
+
+async fn get_tags_of_request(
+    pool: &PgPool,
+    request_id: Uuid,
+) -> Result<Vec<TagResponse>, AppError> {
+    let tags = sqlx::query_as!(
+        Tag,
+        r#"
+        SELECT t.* FROM tags t
+        JOIN request_tags rt ON rt.tag_id = t.id
+        WHERE rt.request_id = $1
+        ORDER BY t.name ASC
+        "#,
+        request_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(tags.into_iter().map(|t| t.to_response()).collect())
+}
+
+async fn get_tags_of_collection(
+    pool: &PgPool,
+    collection_id: Uuid,
+) -> Result<Vec<TagResponse>, AppError> {
+    let tags = sqlx::query_as!(
+        Tag,
+        r#"
+        SELECT t.* FROM tags t
+        JOIN collection_tags ct ON ct.tag_id = t.id
+        WHERE ct.collection_id = $1
+        ORDER BY t.name ASC
+        "#,
+        collection_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(tags.into_iter().map(|t| t.to_response()).collect())
+}
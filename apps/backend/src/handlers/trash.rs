@@ -0,0 +1,434 @@
+use actix_web::{web, HttpResponse};
+use chrono::Duration;
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::error::AppError;
+use crate::models::{
+    Permission, TrashResponse, TrashedCollection, TrashedRequest, WorkspaceQuery, WorkspaceRole,
+};
+use crate::utils::audit::{record_audit, AuditEntry, RequestOrigin};
+use crate::utils::trash::{purge_collection, purge_request};
+use crate::utils::workspace::authorize;
+
+pub async fn get_trash(
+    pool: web::Data<PgPool>,
+    config: web::Data<Config>,
+    query: web::Query<WorkspaceQuery>,
+    user_id: web::ReqData<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = user_id.into_inner();
+
+    let retention = Duration::days(config.trash_retention_days);
+
+    // Get all trashed collections for the user
+    let collections = sqlx::query!(
+        r#"
+        SELECT c.id, c.workspace_id, c.name, c.deleted_at AS "deleted_at!",
+               (SELECT COUNT(*) FROM requests r
+                WHERE r.collection_id = c.id AND r.deleted_at = c.deleted_at) AS "request_count!"
+        FROM collections c
+        WHERE c.deleted_at IS NOT NULL
+          AND c.workspace_id IN (SELECT workspace_id FROM workspace_access WHERE user_id = $1)
+          AND ($2::UUID IS NULL OR c.workspace_id = $2)
+        ORDER BY c.deleted_at DESC
+        "#,
+        user_id,
+        query.workspace_id
+    )
+    .fetch_all(pool.get_ref())
+    .await?
+    .into_iter()
+    .map(|c| TrashedCollection {
+        id: c.id,
+        workspace_id: c.workspace_id,
+        name: c.name,
+        request_count: c.request_count,
+        deleted_at: c.deleted_at,
+        purge_at: c.deleted_at + retention,
+    })
+    .collect();
+
+    // Get the trashed requests that were not deleted as part of a collection
+    let requests = sqlx::query!(
+        r#"
+        SELECT r.id, r.workspace_id, r.name, r.method, r.url, r.collection_id,
+               r.deleted_at AS "deleted_at!"
+        FROM requests r
+        LEFT JOIN collections c ON c.id = r.collection_id
+        WHERE r.deleted_at IS NOT NULL
+          AND r.workspace_id IN (SELECT workspace_id FROM workspace_access WHERE user_id = $1)
+          AND ($2::UUID IS NULL OR r.workspace_id = $2)
+          AND (c.deleted_at IS NULL OR c.deleted_at <> r.deleted_at)
+        ORDER BY r.deleted_at DESC
+        "#,
+        user_id,
+        query.workspace_id
+    )
+    .fetch_all(pool.get_ref())
+    .await?
+    .into_iter()
+    .map(|r| TrashedRequest {
+        id: r.id,
+        workspace_id: r.workspace_id,
+        name: r.name,
+        method: r.method,
+        url: r.url,
+        collection_id: r.collection_id,
+        deleted_at: r.deleted_at,
+        purge_at: r.deleted_at + retention,
+    })
+    .collect();
+
+    // Return the trash
+    Ok(HttpResponse::Ok().json(TrashResponse {
+        collections,
+        requests,
+    }))
+}
+
+pub async fn restore_request(
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    user_id: web::ReqData<Uuid>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let request_id = path.into_inner();
+    let user_id = user_id.into_inner();
+
+    // Verify the request is in the trash of a workspace of the user
+    let request = sqlx::query!(
+        r#"
+        SELECT r.workspace_id, r.name, c.deleted_at AS "collection_deleted_at?"
+        FROM requests r
+        LEFT JOIN collections c ON c.id = r.collection_id
+        WHERE r.id = $1 AND r.deleted_at IS NOT NULL
+          AND r.workspace_id IN (SELECT workspace_id FROM workspace_access WHERE user_id = $2)
+        "#,
+        request_id,
+        user_id
+    )
+    .fetch_optional(pool.get_ref())
+    .await?
+    .ok_or_else(|| AppError::NotFoundError("Request not found in trash".to_string()))?;
+
+    authorize(
+        pool.get_ref(),
+        request.workspace_id,
+        user_id,
+        Permission::Edit,
+    )
+    .await?;
+
+    if request.collection_deleted_at.is_some() {
+        return Err(AppError::ConflictError(
+            "The collection of this request is in the trash, restore it first".to_string(),
+        ));
+    }
+
+    let mut tx = pool.begin().await?;
+
+    // Take the request out of the trash
+    sqlx::query!(
+        r#"
+        UPDATE requests
+        SET deleted_at = NULL
+        WHERE id = $1
+        "#,
+        request_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            workspace_id: Some(request.workspace_id),
+            actor_id: Some(user_id),
+            action: "request.restored",
+            target_id: Some(request_id),
+            details: Some(json!({ "name": request.name })),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    // Return success with no content
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn restore_collection(
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    user_id: web::ReqData<Uuid>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let collection_id = path.into_inner();
+    let user_id = user_id.into_inner();
+
+    // Verify the collection is in the trash of a workspace of the user
+    let collection = sqlx::query!(
+        r#"
+        SELECT workspace_id, name, deleted_at AS "deleted_at!" FROM collections
+        WHERE id = $1 AND deleted_at IS NOT NULL
+          AND workspace_id IN (SELECT workspace_id FROM workspace_access WHERE user_id = $2)
+        "#,
+        collection_id,
+        user_id
+    )
+    .fetch_optional(pool.get_ref())
+    .await?
+    .ok_or_else(|| AppError::NotFoundError("Collection not found in trash".to_string()))?;
+
+    authorize(
+        pool.get_ref(),
+        collection.workspace_id,
+        user_id,
+        Permission::Edit,
+    )
+    .await?;
+
+    let mut tx = pool.begin().await?;
+
+    // Restore the requests that were deleted together with the collection
+    sqlx::query!(
+        r#"
+        UPDATE requests
+        SET deleted_at = NULL
+        WHERE collection_id = $1 AND deleted_at = $2
+        "#,
+        collection_id,
+        collection.deleted_at
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    // Take the collection out of the trash
+    sqlx::query!(
+        r#"
+        UPDATE collections
+        SET deleted_at = NULL
+        WHERE id = $1
+        "#,
+        collection_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            workspace_id: Some(collection.workspace_id),
+            actor_id: Some(user_id),
+            action: "collection.restored",
+            target_id: Some(collection_id),
+            details: Some(json!({ "name": collection.name })),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    // Return success with no content
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn purge_trashed_request(
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    user_id: web::ReqData<Uuid>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let request_id = path.into_inner();
+    let user_id = user_id.into_inner();
+
+    // Verify the request is in the trash of a workspace of the user
+    let request = sqlx::query!(
+        r#"
+        SELECT workspace_id, name FROM requests
+        WHERE id = $1 AND deleted_at IS NOT NULL
+          AND workspace_id IN (SELECT workspace_id FROM workspace_access WHERE user_id = $2)
+        "#,
+        request_id,
+        user_id
+    )
+    .fetch_optional(pool.get_ref())
+    .await?
+    .ok_or_else(|| AppError::NotFoundError("Request not found in trash".to_string()))?;
+
+    authorize(
+        pool.get_ref(),
+        request.workspace_id,
+        user_id,
+        Permission::Purge,
+    )
+    .await?;
+
+    // Permanently delete the request
+    let mut tx = pool.begin().await?;
+    purge_request(&mut tx, request_id).await?;
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            workspace_id: Some(request.workspace_id),
+            actor_id: Some(user_id),
+            action: "request.purged",
+            target_id: Some(request_id),
+            details: Some(json!({ "name": request.name })),
+            ..Default::default()
+        },
+    )
+    .await?;
+    tx.commit().await?;
+
+    // Return success with no content
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn purge_trashed_collection(
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    user_id: web::ReqData<Uuid>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let collection_id = path.into_inner();
+    let user_id = user_id.into_inner();
+
+    // Verify the collection is in the trash of a workspace of the user
+    let collection = sqlx::query!(
+        r#"
+        SELECT workspace_id, name FROM collections
+        WHERE id = $1 AND deleted_at IS NOT NULL
+          AND workspace_id IN (SELECT workspace_id FROM workspace_access WHERE user_id = $2)
+        "#,
+        collection_id,
+        user_id
+    )
+    .fetch_optional(pool.get_ref())
+    .await?
+    .ok_or_else(|| AppError::NotFoundError("Collection not found in trash".to_string()))?;
+
+    authorize(
+        pool.get_ref(),
+        collection.workspace_id,
+        user_id,
+        Permission::Purge,
+    )
+    .await?;
+
+    // Permanently delete the collection and its requests
+    let mut tx = pool.begin().await?;
+    purge_collection(&mut tx, collection_id).await?;
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            workspace_id: Some(collection.workspace_id),
+            actor_id: Some(user_id),
+            action: "collection.purged",
+            target_id: Some(collection_id),
+            details: Some(json!({ "name": collection.name })),
+            ..Default::default()
+        },
+    )
+    .await?;
+    tx.commit().await?;
+
+    // Return success with no content
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn empty_trash(
+    pool: web::Data<PgPool>,
+    query: web::Query<WorkspaceQuery>,
+    user_id: web::ReqData<Uuid>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let user_id = user_id.into_inner();
+    let purge_roles = WorkspaceRole::names_with(Permission::Purge);
+
+    let mut tx = pool.begin().await?;
+
+    // Permanently delete all trashed collections in the workspaces where the user may purge
+    let collections = sqlx::query!(
+        r#"
+        SELECT id, workspace_id, name FROM collections
+        WHERE deleted_at IS NOT NULL
+          AND workspace_id IN (
+            SELECT workspace_id FROM workspace_access
+            WHERE user_id = $1 AND role = ANY($3)
+          )
+          AND ($2::UUID IS NULL OR workspace_id = $2)
+        "#,
+        user_id,
+        query.workspace_id,
+        &purge_roles
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    for collection in collections {
+        purge_collection(&mut tx, collection.id).await?;
+        record_audit(
+            &mut *tx,
+            &origin,
+            AuditEntry {
+                workspace_id: Some(collection.workspace_id),
+                actor_id: Some(user_id),
+                action: "collection.purged",
+                target_id: Some(collection.id),
+                details: Some(json!({ "name": collection.name })),
+                ..Default::default()
+            },
+        )
+        .await?;
+    }
+
+    // Permanently delete the remaining trashed requests
+    let requests = sqlx::query!(
+        r#"
+        SELECT id, workspace_id, name FROM requests
+        WHERE deleted_at IS NOT NULL
+          AND workspace_id IN (
+            SELECT workspace_id FROM workspace_access
+            WHERE user_id = $1 AND role = ANY($3)
+          )
+          AND ($2::UUID IS NULL OR workspace_id = $2)
+        "#,
+        user_id,
+        query.workspace_id,
+        &purge_roles
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    for request in requests {
+        purge_request(&mut tx, request.id).await?;
+        record_audit(
+            &mut *tx,
+            &origin,
+            AuditEntry {
+                workspace_id: Some(request.workspace_id),
+                actor_id: Some(user_id),
+                action: "request.purged",
+                target_id: Some(request.id),
+                details: Some(json!({ "name": request.name })),
+                ..Default::default()
+            },
+        )
+        .await?;
+    }
+
+    tx.commit().await?;
+
+    // Return success with no content
+    Ok(HttpResponse::NoContent().finish())
+}
@@ -0,0 +1,255 @@
+use actix_web::{web, HttpResponse};
+use bcrypt::verify;
+use chrono::Utc;
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::error::AppError;
+use crate::models::{
+    DisableTwoFactorDto, RecoveryCodesResponse, TwoFactorCodeDto, TwoFactorSetupResponse, User,
+};
+use crate::utils::audit::{record_audit, AuditEntry, RequestOrigin};
+use crate::utils::login_lockout::{ensure_not_locked, lock_out_on_failure};
+use crate::utils::two_factor::{
+    generate_secret, matching_step, otpauth_uri, replace_recovery_codes, verify_second_factor,
+};
+
+pub async fn setup_two_factor(
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let user = get_user(pool.get_ref(), user_id.into_inner()).await?;
+
+    if user.totp_enabled_at.is_some() {
+        return Err(AppError::ConflictError(
+            "Two-factor authentication is already enabled".to_string(),
+        ));
+    }
+
+    // Running the setup again replaces a secret that was never confirmed
+    let secret = generate_secret();
+    sqlx::query!(
+        r#"
+        UPDATE users SET totp_secret = $2, totp_last_step = NULL, updated_at = $3
+        WHERE id = $1
+        "#,
+        user.id,
+        secret,
+        Utc::now().naive_utc()
+    )
+    .execute(pool.get_ref())
+    .await?;
+
+    // Return the secret for the authenticator app
+    Ok(HttpResponse::Ok().json(TwoFactorSetupResponse {
+        otpauth_uri: otpauth_uri(&secret, &user.email)?,
+        secret,
+    }))
+}
+
+pub async fn enable_two_factor(
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<Uuid>,
+    code_dto: web::Json<TwoFactorCodeDto>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let user = get_user(pool.get_ref(), user_id.into_inner()).await?;
+
+    if user.totp_enabled_at.is_some() {
+        return Err(AppError::ConflictError(
+            "Two-factor authentication is already enabled".to_string(),
+        ));
+    }
+    let secret = user.totp_secret.as_deref().ok_or_else(|| {
+        AppError::BadRequestError("Set up two-factor authentication first".to_string())
+    })?;
+
+    // A code from the app proves it holds the secret
+    let step = matching_step(secret, &user.email, &code_dto.code)?
+        .ok_or_else(|| AppError::BadRequestError("Invalid code".to_string()))?;
+
+    let mut tx = pool.begin().await?;
+
+    let now = Utc::now().naive_utc();
+    sqlx::query!(
+        r#"
+        UPDATE users SET totp_enabled_at = $2, totp_last_step = $3, updated_at = $2
+        WHERE id = $1
+        "#,
+        user.id,
+        now,
+        step
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let recovery_codes = replace_recovery_codes(&mut tx, user.id).await?;
+
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            actor_id: Some(user.id),
+            action: "user.2fa_enabled",
+            target_id: Some(user.id),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    // Return the recovery codes, they are not shown again
+    Ok(HttpResponse::Ok().json(RecoveryCodesResponse { recovery_codes }))
+}
+
+pub async fn disable_two_factor(
+    pool: web::Data<PgPool>,
+    config: web::Data<Config>,
+    user_id: web::ReqData<Uuid>,
+    disable_dto: web::Json<DisableTwoFactorDto>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let user = get_user(pool.get_ref(), user_id.into_inner()).await?;
+
+    if user.totp_enabled_at.is_none() {
+        return Err(AppError::ConflictError(
+            "Two-factor authentication is not enabled".to_string(),
+        ));
+    }
+
+    // Wrong passwords and codes count towards the lockout like failed logins
+    ensure_not_locked(&user)?;
+
+    let valid_password = verify(&disable_dto.password, &user.password_hash)
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+    if !valid_password {
+        let mut conn = pool.acquire().await?;
+        lock_out_on_failure(&mut conn, &config.login_lockout, user.id, &origin).await?;
+        return Err(AppError::BadRequestError("Invalid password".to_string()));
+    }
+
+    // Members of workspaces that require 2FA have to leave them first
+    let requiring = sqlx::query!(
+        r#"
+        SELECT w.name FROM workspaces w
+        JOIN workspace_members m ON m.workspace_id = w.id
+        WHERE m.user_id = $1 AND w.require_2fa
+        ORDER BY w.name ASC
+        "#,
+        user.id
+    )
+    .fetch_all(pool.get_ref())
+    .await?;
+
+    if !requiring.is_empty() {
+        let names: Vec<String> = requiring.into_iter().map(|w| w.name).collect();
+        return Err(AppError::ConflictError(format!(
+            "Two-factor authentication is required by these workspaces: {}",
+            names.join(", ")
+        )));
+    }
+
+    let mut tx = pool.begin().await?;
+
+    if verify_second_factor(&mut tx, &user, &disable_dto.code)
+        .await?
+        .is_none()
+    {
+        lock_out_on_failure(&mut tx, &config.login_lockout, user.id, &origin).await?;
+        tx.commit().await?;
+        return Err(AppError::BadRequestError("Invalid code".to_string()));
+    }
+
+    sqlx::query!(
+        r#"
+        UPDATE users
+        SET totp_secret = NULL, totp_enabled_at = NULL, totp_last_step = NULL, updated_at = $2
+        WHERE id = $1
+        "#,
+        user.id,
+        Utc::now().naive_utc()
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    sqlx::query!("DELETE FROM recovery_codes WHERE user_id = $1", user.id)
+        .execute(&mut *tx)
+        .await?;
+
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            actor_id: Some(user.id),
+            action: "user.2fa_disabled",
+            target_id: Some(user.id),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    // Return success with no content
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn regenerate_recovery_codes(
+    pool: web::Data<PgPool>,
+    config: web::Data<Config>,
+    user_id: web::ReqData<Uuid>,
+    code_dto: web::Json<TwoFactorCodeDto>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let user = get_user(pool.get_ref(), user_id.into_inner()).await?;
+
+    if user.totp_enabled_at.is_none() {
+        return Err(AppError::ConflictError(
+            "Two-factor authentication is not enabled".to_string(),
+        ));
+    }
+
+    ensure_not_locked(&user)?;
+
+    let mut tx = pool.begin().await?;
+
+    if verify_second_factor(&mut tx, &user, &code_dto.code)
+        .await?
+        .is_none()
+    {
+        lock_out_on_failure(&mut tx, &config.login_lockout, user.id, &origin).await?;
+        tx.commit().await?;
+        return Err(AppError::BadRequestError("Invalid code".to_string()));
+    }
+
+    // The new codes replace all previous ones, used or not
+    let recovery_codes = replace_recovery_codes(&mut tx, user.id).await?;
+
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            actor_id: Some(user.id),
+            action: "user.recovery_codes_regenerated",
+            target_id: Some(user.id),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    // Return the new recovery codes
+    Ok(HttpResponse::Ok().json(RecoveryCodesResponse { recovery_codes }))
+}
+
+async fn get_user<'c>(executor: impl PgExecutor<'c>, user_id: Uuid) -> Result<User, AppError> {
+    let user = sqlx::query_as!(User, "SELECT * FROM users WHERE id = $1", user_id)
+        .fetch_optional(executor)
+        .await?
+        .ok_or_else(|| AppError::NotFoundError("User not found".to_string()))?;
+
+    Ok(user)
+}
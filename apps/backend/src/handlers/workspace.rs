@@ -0,0 +1,685 @@
+use actix_web::{web, HttpResponse};
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::error::AppError;
+use crate::models::{
+    CreateWorkspaceDto, Permission, ProxyMode, TransferOwnershipDto, UpdateMemberDto,
+    UpdateWorkspaceDto, Workspace, WorkspaceMember, WorkspaceProxyResponse, WorkspaceResponse,
+    WorkspaceRole,
+};
+use crate::utils::audit::{record_audit, AuditEntry, RequestOrigin};
+use crate::utils::outbound::OutboundPolicy;
+use crate::utils::proxy::is_valid_proxy_url;
+use crate::utils::redact::REDACTED;
+use crate::utils::two_factor::has_two_factor;
+use crate::utils::workspace::{access_role, authorize, insert_workspace, member_role};
+
+pub async fn create_workspace(
+    pool: web::Data<PgPool>,
+    workspace_dto: web::Json<CreateWorkspaceDto>,
+    user_id: web::ReqData<Uuid>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let user_id = user_id.into_inner();
+
+    // Validate the workspace data
+    workspace_dto
+        .validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    // Insert the workspace with the user as its owner
+    let mut tx = pool.begin().await?;
+    let workspace = insert_workspace(
+        &mut tx,
+        &workspace_dto.name,
+        workspace_dto.description.as_deref(),
+        false,
+        user_id,
+    )
+    .await?;
+
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            workspace_id: Some(workspace.id),
+            actor_id: Some(user_id),
+            action: "workspace.created",
+            target_id: Some(workspace.id),
+            after: Some(workspace.audit_summary()),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    // Return the workspace
+    Ok(HttpResponse::Created().json(workspace.to_response(WorkspaceRole::Owner)))
+}
+
+pub async fn get_workspaces(
+    pool: web::Data<PgPool>,
+    user_id: web::ReqData<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    // Get all workspaces the user is a member of, the personal one first
+    let workspaces = sqlx::query!(
+        r#"
+        SELECT w.id, w.name, w.description, w.is_personal, w.require_2fa, w.created_at,
+            w.updated_at, m.role
+        FROM workspaces w
+        JOIN workspace_members m ON m.workspace_id = w.id
+        WHERE m.user_id = $1
+        ORDER BY w.is_personal DESC, w.name ASC
+        "#,
+        user_id.into_inner()
+    )
+    .fetch_all(pool.get_ref())
+    .await?;
+
+    // Transform to response objects
+    let workspace_responses: Vec<WorkspaceResponse> = workspaces
+        .into_iter()
+        .map(|w| WorkspaceResponse {
+            id: w.id,
+            name: w.name,
+            description: w.description,
+            is_personal: w.is_personal,
+            require_2fa: w.require_2fa,
+            role: w.role,
+            created_at: w.created_at,
+            updated_at: w.updated_at,
+        })
+        .collect();
+
+    // Return the workspaces
+    Ok(HttpResponse::Ok().json(workspace_responses))
+}
+
+pub async fn get_workspace(
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    user_id: web::ReqData<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let workspace_id = path.into_inner();
+
+    // Verify the user is a member of the workspace
+    let role = access_role(pool.get_ref(), workspace_id, user_id.into_inner()).await?;
+
+    // Get the workspace
+    let workspace = get_workspace_by_id(pool.get_ref(), workspace_id).await?;
+
+    // Return the workspace
+    Ok(HttpResponse::Ok().json(workspace.to_response(role)))
+}
+
+pub async fn update_workspace(
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    workspace_dto: web::Json<UpdateWorkspaceDto>,
+    user_id: web::ReqData<Uuid>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let workspace_id = path.into_inner();
+    let user_id = user_id.into_inner();
+
+    // Validate the workspace data
+    workspace_dto
+        .validate()
+        .map_err(|e| AppError::ValidationError(e.to_string()))?;
+
+    // Only owners and admins can change the workspace
+    let role = authorize(
+        pool.get_ref(),
+        workspace_id,
+        user_id,
+        Permission::ManageWorkspace,
+    )
+    .await?;
+    let workspace = get_workspace_by_id(pool.get_ref(), workspace_id).await?;
+    let before = workspace.audit_summary();
+
+    // Update only provided fields
+    let name = workspace_dto.name.clone().unwrap_or(workspace.name);
+    let description = workspace_dto.description.clone().or(workspace.description);
+    let require_2fa = workspace_dto.require_2fa.unwrap_or(workspace.require_2fa);
+
+    // Turning the requirement on must not lock out the admin doing it
+    if require_2fa && !workspace.require_2fa && !has_two_factor(pool.get_ref(), user_id).await? {
+        return Err(AppError::ConflictError(
+            "Enable two-factor authentication on your account before requiring it".to_string(),
+        ));
+    }
+
+    let mut tx = pool.begin().await?;
+
+    // Update the workspace in the database
+    let updated_workspace = sqlx::query_as!(
+        Workspace,
+        r#"
+        UPDATE workspaces
+        SET name = $1, description = $2, require_2fa = $3, updated_at = $4
+        WHERE id = $5
+        RETURNING id, name, description, is_personal, created_at, updated_at, require_2fa, proxy
+        "#,
+        name,
+        description,
+        require_2fa,
+        chrono::Utc::now().naive_utc(),
+        workspace_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            workspace_id: Some(workspace_id),
+            actor_id: Some(user_id),
+            action: "workspace.updated",
+            target_id: Some(workspace_id),
+            before: Some(before),
+            after: Some(updated_workspace.audit_summary()),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    // Return the updated workspace
+    Ok(HttpResponse::Ok().json(updated_workspace.to_response(role)))
+}
+
+pub async fn delete_workspace(
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    user_id: web::ReqData<Uuid>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let workspace_id = path.into_inner();
+    let user_id = user_id.into_inner();
+
+    // Only the owner can delete the workspace
+    authorize(
+        pool.get_ref(),
+        workspace_id,
+        user_id,
+        Permission::DeleteWorkspace,
+    )
+    .await?;
+    let workspace = get_workspace_by_id(pool.get_ref(), workspace_id).await?;
+
+    if workspace.is_personal {
+        return Err(AppError::ConflictError(
+            "The personal workspace cannot be deleted".to_string(),
+        ));
+    }
+
+    let mut tx = pool.begin().await?;
+
+    // Delete the workspace, its collections, requests, environments and tags go with it
+    sqlx::query!(
+        r#"
+        DELETE FROM workspaces
+        WHERE id = $1
+        "#,
+        workspace_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            workspace_id: Some(workspace_id),
+            actor_id: Some(user_id),
+            action: "workspace.deleted",
+            target_id: Some(workspace_id),
+            before: Some(workspace.audit_summary()),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    // Return success with no content
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn get_members(
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    user_id: web::ReqData<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let workspace_id = path.into_inner();
+
+    // Verify the user is a member of the workspace
+    access_role(pool.get_ref(), workspace_id, user_id.into_inner()).await?;
+
+    // Get all members of the workspace
+    let members = sqlx::query_as!(
+        WorkspaceMember,
+        r#"
+        SELECT u.id AS user_id, u.email, u.name, m.role, m.created_at
+        FROM workspace_members m
+        JOIN users u ON u.id = m.user_id
+        WHERE m.workspace_id = $1
+        ORDER BY m.created_at ASC
+        "#,
+        workspace_id
+    )
+    .fetch_all(pool.get_ref())
+    .await?;
+
+    // Return the members
+    Ok(HttpResponse::Ok().json(members))
+}
+
+pub async fn update_member(
+    pool: web::Data<PgPool>,
+    path: web::Path<(Uuid, Uuid)>,
+    member_dto: web::Json<UpdateMemberDto>,
+    user_id: web::ReqData<Uuid>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let (workspace_id, member_id) = path.into_inner();
+    let user_id = user_id.into_inner();
+
+    // Only owners and admins can change roles
+    authorize(
+        pool.get_ref(),
+        workspace_id,
+        user_id,
+        Permission::ManageMembers,
+    )
+    .await?;
+
+    if member_dto.role == WorkspaceRole::Owner {
+        return Err(AppError::ValidationError(
+            "Transfer the ownership to make a member the owner".to_string(),
+        ));
+    }
+
+    // The owner keeps their role until the ownership is transferred
+    let current_role = member_role(pool.get_ref(), workspace_id, member_id)
+        .await
+        .map_err(|_| AppError::NotFoundError("Member not found".to_string()))?;
+
+    if current_role == WorkspaceRole::Owner {
+        return Err(AppError::ConflictError(
+            "The role of the owner cannot be changed, transfer the ownership instead".to_string(),
+        ));
+    }
+
+    let mut tx = pool.begin().await?;
+
+    // Update the role of the member
+    let member = sqlx::query_as!(
+        WorkspaceMember,
+        r#"
+        WITH updated AS (
+            UPDATE workspace_members
+            SET role = $3
+            WHERE workspace_id = $1 AND user_id = $2
+            RETURNING user_id, role, created_at
+        )
+        SELECT u.id AS user_id, u.email, u.name, m.role, m.created_at
+        FROM updated m
+        JOIN users u ON u.id = m.user_id
+        "#,
+        workspace_id,
+        member_id,
+        member_dto.role.as_str()
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            workspace_id: Some(workspace_id),
+            actor_id: Some(user_id),
+            action: "member.role_changed",
+            target_id: Some(member_id),
+            details: Some(json!({ "from": current_role.as_str(), "to": member.role })),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    // Return the updated member
+    Ok(HttpResponse::Ok().json(member))
+}
+
+pub async fn remove_member(
+    pool: web::Data<PgPool>,
+    path: web::Path<(Uuid, Uuid)>,
+    user_id: web::ReqData<Uuid>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let (workspace_id, member_id) = path.into_inner();
+    let user_id = user_id.into_inner();
+
+    // Members can always leave, removing someone else takes an owner or admin
+    if member_id == user_id {
+        member_role(pool.get_ref(), workspace_id, user_id).await?;
+    } else {
+        authorize(
+            pool.get_ref(),
+            workspace_id,
+            user_id,
+            Permission::ManageMembers,
+        )
+        .await?;
+    }
+
+    let role = member_role(pool.get_ref(), workspace_id, member_id)
+        .await
+        .map_err(|_| AppError::NotFoundError("Member not found".to_string()))?;
+
+    if role == WorkspaceRole::Owner {
+        return Err(AppError::ConflictError(
+            "The owner cannot leave the workspace, transfer the ownership first".to_string(),
+        ));
+    }
+
+    let mut tx = pool.begin().await?;
+
+    // Remove the member
+    sqlx::query!(
+        r#"
+        DELETE FROM workspace_members
+        WHERE workspace_id = $1 AND user_id = $2
+        "#,
+        workspace_id,
+        member_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    let action = if member_id == user_id {
+        "member.left"
+    } else {
+        "member.removed"
+    };
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            workspace_id: Some(workspace_id),
+            actor_id: Some(user_id),
+            action,
+            target_id: Some(member_id),
+            details: Some(json!({ "role": role.as_str() })),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    // Return success with no content
+    Ok(HttpResponse::NoContent().finish())
+}
+
+pub async fn transfer_ownership(
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    transfer_dto: web::Json<TransferOwnershipDto>,
+    user_id: web::ReqData<Uuid>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let workspace_id = path.into_inner();
+    let user_id = user_id.into_inner();
+
+    // Only the owner can hand the workspace over
+    authorize(
+        pool.get_ref(),
+        workspace_id,
+        user_id,
+        Permission::TransferOwnership,
+    )
+    .await?;
+    let workspace = get_workspace_by_id(pool.get_ref(), workspace_id).await?;
+
+    if workspace.is_personal {
+        return Err(AppError::ConflictError(
+            "The personal workspace cannot be transferred".to_string(),
+        ));
+    }
+
+    if transfer_dto.user_id == user_id {
+        return Err(AppError::ValidationError(
+            "You already own this workspace".to_string(),
+        ));
+    }
+
+    // The new owner must already be a member
+    let previous_role = member_role(pool.get_ref(), workspace_id, transfer_dto.user_id)
+        .await
+        .map_err(|_| AppError::NotFoundError("Member not found".to_string()))?;
+
+    if workspace.require_2fa && !has_two_factor(pool.get_ref(), transfer_dto.user_id).await? {
+        return Err(AppError::ConflictError(
+            "The new owner has to enable two-factor authentication first".to_string(),
+        ));
+    }
+
+    let mut tx = pool.begin().await?;
+
+    // Swap the roles, the previous owner stays on as an admin
+    sqlx::query!(
+        r#"
+        UPDATE workspace_members
+        SET role = CASE WHEN user_id = $2 THEN $4 ELSE $5 END
+        WHERE workspace_id = $1 AND user_id IN ($2, $3)
+        "#,
+        workspace_id,
+        transfer_dto.user_id,
+        user_id,
+        WorkspaceRole::Owner.as_str(),
+        WorkspaceRole::Admin.as_str()
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            workspace_id: Some(workspace_id),
+            actor_id: Some(user_id),
+            action: "workspace.ownership_transferred",
+            target_id: Some(transfer_dto.user_id),
+            details: Some(json!({ "previous_role": previous_role.as_str() })),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    // Return the workspace as the previous owner now sees it
+    Ok(HttpResponse::Ok().json(workspace.to_response(WorkspaceRole::Admin)))
+}
+
+pub async fn get_workspace_proxy(
+    pool: web::Data<PgPool>,
+    policy: web::Data<OutboundPolicy>,
+    path: web::Path<Uuid>,
+    user_id: web::ReqData<Uuid>,
+) -> Result<HttpResponse, AppError> {
+    let workspace_id = path.into_inner();
+
+    // Every member may see which proxy their requests go through
+    access_role(pool.get_ref(), workspace_id, user_id.into_inner()).await?;
+    let workspace = get_workspace_by_id(pool.get_ref(), workspace_id).await?;
+
+    Ok(HttpResponse::Ok().json(proxy_response(&workspace, &policy)?))
+}
+
+pub async fn update_workspace_proxy(
+    pool: web::Data<PgPool>,
+    policy: web::Data<OutboundPolicy>,
+    path: web::Path<Uuid>,
+    proxy_dto: web::Json<ProxyMode>,
+    user_id: web::ReqData<Uuid>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let workspace_id = path.into_inner();
+    let user_id = user_id.into_inner();
+
+    // Only owners and admins can change the workspace
+    authorize(
+        pool.get_ref(),
+        workspace_id,
+        user_id,
+        Permission::ManageWorkspace,
+    )
+    .await?;
+    let workspace = get_workspace_by_id(pool.get_ref(), workspace_id).await?;
+    let previous = workspace.proxy_mode()?;
+
+    let mut proxy = proxy_dto.into_inner();
+    if let ProxyMode::Proxy(server) = &mut proxy {
+        if !is_valid_proxy_url(&server.url) {
+            return Err(AppError::ValidationError(
+                "Proxy URL must be an http, https, socks5 or socks5h URL without credentials"
+                    .to_string(),
+            ));
+        }
+
+        // The redacted password as it was shown keeps the stored one
+        if server.password.as_deref() == Some(REDACTED) {
+            server.password = match &previous {
+                Some(ProxyMode::Proxy(previous)) => previous.password.clone(),
+                _ => None,
+            };
+        }
+    }
+
+    let stored_proxy =
+        serde_json::to_value(&proxy).map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+    let mut tx = pool.begin().await?;
+
+    let updated_workspace = sqlx::query_as!(
+        Workspace,
+        r#"
+        UPDATE workspaces SET proxy = $1, updated_at = $2
+        WHERE id = $3
+        RETURNING id, name, description, is_personal, created_at, updated_at, require_2fa, proxy
+        "#,
+        stored_proxy,
+        chrono::Utc::now().naive_utc(),
+        workspace_id
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            workspace_id: Some(workspace_id),
+            actor_id: Some(user_id),
+            action: "workspace.proxy_updated",
+            target_id: Some(workspace_id),
+            before: previous.map(|proxy| json!(proxy.redacted())),
+            after: Some(json!(proxy.redacted())),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    // Return the proxy without its password
+    Ok(HttpResponse::Ok().json(proxy_response(&updated_workspace, &policy)?))
+}
+
+pub async fn delete_workspace_proxy(
+    pool: web::Data<PgPool>,
+    path: web::Path<Uuid>,
+    user_id: web::ReqData<Uuid>,
+    origin: RequestOrigin,
+) -> Result<HttpResponse, AppError> {
+    let workspace_id = path.into_inner();
+    let user_id = user_id.into_inner();
+
+    // Only owners and admins can change the workspace
+    authorize(
+        pool.get_ref(),
+        workspace_id,
+        user_id,
+        Permission::ManageWorkspace,
+    )
+    .await?;
+    let workspace = get_workspace_by_id(pool.get_ref(), workspace_id).await?;
+
+    let mut tx = pool.begin().await?;
+
+    sqlx::query!(
+        "UPDATE workspaces SET proxy = NULL, updated_at = $1 WHERE id = $2",
+        chrono::Utc::now().naive_utc(),
+        workspace_id
+    )
+    .execute(&mut *tx)
+    .await?;
+
+    record_audit(
+        &mut *tx,
+        &origin,
+        AuditEntry {
+            workspace_id: Some(workspace_id),
+            actor_id: Some(user_id),
+            action: "workspace.proxy_removed",
+            target_id: Some(workspace_id),
+            before: workspace.proxy_mode()?.map(|proxy| json!(proxy.redacted())),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    // Requests fall back to the proxy of the server
+    Ok(HttpResponse::NoContent().finish())
+}
+
+fn proxy_response(
+    workspace: &Workspace,
+    policy: &OutboundPolicy,
+) -> Result<WorkspaceProxyResponse, AppError> {
+    Ok(WorkspaceProxyResponse {
+        workspace_id: workspace.id,
+        proxy: workspace.proxy_mode()?.map(|proxy| proxy.redacted()),
+        server_proxy: policy.server_proxy().map(|proxy| proxy.url.clone()),
+    })
+}
+
+async fn get_workspace_by_id(pool: &PgPool, workspace_id: Uuid) -> Result<Workspace, AppError> {
+    let workspace = sqlx::query_as!(
+        Workspace,
+        r#"
+        SELECT * FROM workspaces
+        WHERE id = $1
+        "#,
+        workspace_id
+    )
+    .fetch_optional(pool)
+    .await?
+    .ok_or_else(|| AppError::NotFoundError("Workspace not found".to_string()))?;
+
+    Ok(workspace)
+}
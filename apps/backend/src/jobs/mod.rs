@@ -0,0 +1,5 @@
+pub mod session;
+pub mod trash;
+
+pub use session::spawn_session_purge;
+pub use trash::spawn_trash_purge;
@@ -0,0 +1,25 @@
+use chrono::Utc;
+use log::{error, info};
+use sqlx::PgPool;
+
+use crate::utils::session::purge_sessions;
+
+// How often expired and revoked sessions are cleaned up
+const PURGE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+// Periodically delete sessions that can no longer be used
+pub fn spawn_session_purge(pool: PgPool) {
+    actix_rt::spawn(async move {
+        let mut interval = actix_rt::time::interval(PURGE_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            match purge_sessions(&pool, Utc::now().naive_utc()).await {
+                Ok(0) => {}
+                Ok(purged) => info!("Purged {} expired sessions", purged),
+                Err(e) => error!("Failed to purge sessions: {:?}", e),
+            }
+        }
+    });
+}
@@ -0,0 +1,26 @@
+use chrono::{Duration, Utc};
+use log::{error, info};
+use sqlx::PgPool;
+
+use crate::utils::trash::purge_expired;
+
+// How often the trash is checked for expired items
+const PURGE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+// Periodically purge trashed items older than the retention period
+pub fn spawn_trash_purge(pool: PgPool, retention_days: i64) {
+    actix_rt::spawn(async move {
+        let mut interval = actix_rt::time::interval(PURGE_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            let cutoff = (Utc::now() - Duration::days(retention_days)).naive_utc();
+            match purge_expired(&pool, cutoff).await {
+                Ok(0) => {}
+                Ok(purged) => info!("Purged {} expired items from the trash", purged),
+                Err(e) => error!("Failed to purge the trash: {:?}", e),
+            }
+        }
+    });
+}
@@ -0,0 +1,46 @@
+use async_trait::async_trait;
+use lettre::message::Mailbox;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+use super::{Email, Mailer};
+use crate::error::AppError;
+
+// Stores every email as a plain text file in a directory, for development and tests
+pub struct FileMailer {
+    from: Mailbox,
+    dir: PathBuf,
+}
+
+impl FileMailer {
+    pub fn new(from: Mailbox, dir: &str) -> Result<Self, String> {
+        std::fs::create_dir_all(dir)
+            .map_err(|e| format!("Failed to create mail directory {}: {}", dir, e))?;
+
+        Ok(FileMailer {
+            from,
+            dir: PathBuf::from(dir),
+        })
+    }
+}
+
+#[async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, email: &Email) -> Result<(), AppError> {
+        let now = chrono::Utc::now();
+        let content = format!(
+            "From: {}\nTo: {}\nDate: {}\nSubject: {}\n\n{}",
+            self.from,
+            email.to,
+            now.to_rfc2822(),
+            email.subject,
+            email.body
+        );
+
+        // Timestamp first so the files sort in the order they were sent
+        let name = format!("{}-{}.txt", now.format("%Y%m%d%H%M%S%6f"), Uuid::new_v4());
+        tokio::fs::write(self.dir.join(name), content)
+            .await
+            .map_err(|e| AppError::InternalServerError(format!("Failed to write email: {}", e)))
+    }
+}
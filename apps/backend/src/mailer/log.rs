@@ -0,0 +1,19 @@
+use async_trait::async_trait;
+use log::info;
+
+use super::{Email, Mailer};
+use crate::error::AppError;
+
+// Writes emails to the log instead of sending them, for local development
+pub struct LogMailer;
+
+#[async_trait]
+impl Mailer for LogMailer {
+    async fn send(&self, email: &Email) -> Result<(), AppError> {
+        info!(
+            "Email to {}\nSubject: {}\n\n{}",
+            email.to, email.subject, email.body
+        );
+        Ok(())
+    }
+}
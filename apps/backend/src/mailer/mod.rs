@@ -0,0 +1,49 @@
+pub mod file;
+pub mod log;
+pub mod smtp;
+pub mod templates;
+
+use async_trait::async_trait;
+use lettre::message::Mailbox;
+use std::sync::Arc;
+
+use crate::config::MailConfig;
+use crate::error::AppError;
+
+pub use file::FileMailer;
+pub use log::LogMailer;
+pub use smtp::SmtpMailer;
+
+// A plain text email
+#[derive(Debug, Clone)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+// Delivers emails, picked by `mail.transport` at startup
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, email: &Email) -> Result<(), AppError>;
+}
+
+pub fn build_mailer(config: &MailConfig) -> Result<Arc<dyn Mailer>, String> {
+    let from: Mailbox = config
+        .from
+        .parse()
+        .map_err(|e| format!("Invalid mail.from address: {}", e))?;
+
+    match config.transport.as_str() {
+        "log" => Ok(Arc::new(LogMailer)),
+        "file" => Ok(Arc::new(FileMailer::new(from, &config.file_dir)?)),
+        "smtp" => {
+            let url = config
+                .smtp_url
+                .as_deref()
+                .ok_or("mail.smtp_url is required for the smtp transport")?;
+            Ok(Arc::new(SmtpMailer::new(from, url)?))
+        }
+        other => Err(format!("Unsupported mail.transport: {}", other)),
+    }
+}
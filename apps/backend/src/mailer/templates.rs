@@ -0,0 +1,52 @@
+use super::Email;
+
+pub fn verification_email(to: &str, link: &str) -> Email {
+    Email {
+        to: to.to_string(),
+        subject: "Verify your email address".to_string(),
+        body: format!(
+            "Welcome to Endpoint!\n\n\
+             Please confirm your email address by opening the link below:\n\n\
+             {}\n\n\
+             If you did not create an account, you can ignore this email.\n",
+            link
+        ),
+    }
+}
+
+pub fn password_reset_email(to: &str, link: &str) -> Email {
+    Email {
+        to: to.to_string(),
+        subject: "Reset your password".to_string(),
+        body: format!(
+            "Someone asked to reset the password of your Endpoint account.\n\n\
+             Open the link below to choose a new password:\n\n\
+             {}\n\n\
+             The link can only be used once. If you did not ask for it, you can ignore this email.\n",
+            link
+        ),
+    }
+}
+
+pub fn email_changed_email(to: &str, new_email: &str) -> Email {
+    Email {
+        to: to.to_string(),
+        subject: "Your email address was changed".to_string(),
+        body: format!(
+            "The email address of your Endpoint account was changed to {}.\n\n\
+             If you did not make this change, reset your password and contact support.\n",
+            new_email
+        ),
+    }
+}
+
+pub fn password_changed_email(to: &str) -> Email {
+    Email {
+        to: to.to_string(),
+        subject: "Your password was changed".to_string(),
+        body: "The password of your Endpoint account was changed and your other sessions \
+               were signed out.\n\n\
+               If you did not make this change, reset your password right away.\n"
+            .to_string(),
+    }
+}
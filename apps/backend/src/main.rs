@@ -1,5 +1,5 @@
 use actix_cors::Cors;
-use actix_web::{middleware, App, HttpServer};
+use actix_web::{middleware, web, App, HttpServer};
 use dotenv::dotenv;
 use log::info;
 
@@ -8,6 +8,8 @@ mod config;
 mod db;
 mod error;
 mod handlers;
+mod jobs;
+mod mailer;
 mod models;
 mod routes;
 mod utils;
@@ -20,40 +22,120 @@ async fn main() -> std::io::Result<()> {
     // Initialize logging
     env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
 
-    // Load configuration
-    let config = config::Config::from_env().expect("Failed to load configuration");
+    // Load and validate the configuration once, handlers get it through the app data
+    let config = config::Config::load().unwrap_or_else(|e| panic!("Invalid configuration: {}", e));
+
+    // Load the keys that sign access tokens, refusing to start with a bad setup
+    let jwt_keys = web::Data::new(
+        utils::jwt::JwtKeys::from_config(&config.jwt)
+            .unwrap_or_else(|e| panic!("Invalid JWT configuration: {}", e)),
+    );
+
+    // Pick the mail transport
+    let mailer: web::Data<dyn mailer::Mailer> = web::Data::from(
+        mailer::build_mailer(&config.mail)
+            .unwrap_or_else(|e| panic!("Invalid mail configuration: {}", e)),
+    );
+
+    // Rules for where the requests sent on behalf of users may go
+    let outbound_policy = web::Data::new(
+        utils::outbound::OutboundPolicy::from_config(&config.outbound)
+            .unwrap_or_else(|e| panic!("Invalid outbound configuration: {}", e)),
+    );
+
+    // Single sign-on is optional, its handlers answer 404 without it
+    let oidc_client = utils::oidc::OidcClient::from_config(&config.oidc)
+        .unwrap_or_else(|e| panic!("Invalid OIDC configuration: {}", e))
+        .map(web::Data::new);
+
+    // Secret variables need a master key, their handlers answer 404 without it
+    let secret_vault = utils::secrets::SecretVault::from_config(&config.secrets)
+        .unwrap_or_else(|e| panic!("Invalid secrets configuration: {}", e))
+        .map(web::Data::new);
+
+    // Buckets live in this process, shared by all workers
+    let rate_limiter =
+        utils::rate_limit::RateLimiter::from_config(&config.rate_limit).map(web::Data::new);
 
     // Set up database connection pool
-    let pool = db::create_pool(&config.database_url)
+    let pool = db::create_pool(&config.database)
         .await
         .expect("Failed to create database pool");
 
     // Run database migrations
-    db::run_migrations(&config.database_url)
+    db::run_migrations(&config.database.url)
         .await
         .expect("Failed to run database migrations");
 
+    // Data keys still wrapped with a retired master key are wrapped with the current one
+    if let Some(secret_vault) = &secret_vault {
+        let rewrapped = secret_vault
+            .rewrap_data_keys(&pool)
+            .await
+            .expect("Failed to wrap the workspace keys with the current master key");
+        if rewrapped > 0 {
+            info!(
+                "Wrapped {} workspace keys with the current master key",
+                rewrapped
+            );
+        }
+    }
+
+    // Purge expired items from the trash in the background
+    jobs::spawn_trash_purge(pool.clone(), config.trash_retention_days);
+
+    // Clean up sessions that expired or were revoked
+    jobs::spawn_session_purge(pool.clone());
+
     info!(
         "Starting server at {}:{}",
         config.server.host, config.server.port
     );
 
+    let app_config = web::Data::new(config.clone());
+
     // Start HTTP server
-    HttpServer::new(move || {
+    let mut server = HttpServer::new(move || {
         // CORS configuration
-        let cors = Cors::default()
-            .allow_any_origin()
+        let mut cors = Cors::default()
             .allow_any_method()
             .allow_any_header()
-            .max_age(3600);
+            .max_age(app_config.cors.max_age);
+        for origin in &app_config.cors.allowed_origins {
+            cors = match origin.as_str() {
+                "*" => cors.allow_any_origin(),
+                origin => cors.allowed_origin(origin),
+            };
+        }
 
-        App::new()
-            .app_data(actix_web::web::Data::new(pool.clone()))
-            .wrap(middleware::Logger::default())
+        let mut app = App::new()
+            .app_data(web::Data::new(pool.clone()))
+            .app_data(app_config.clone())
+            .app_data(jwt_keys.clone())
+            .app_data(outbound_policy.clone())
+            .app_data(mailer.clone());
+        if let Some(oidc_client) = &oidc_client {
+            app = app.app_data(oidc_client.clone());
+        }
+        if let Some(secret_vault) = &secret_vault {
+            app = app.app_data(secret_vault.clone());
+        }
+        if let Some(rate_limiter) = &rate_limiter {
+            app = app.app_data(rate_limiter.clone());
+        }
+
+        // The API budget is taken by the routes, behind authentication so it is kept per user
+        app.wrap(middleware::Logger::default())
             .wrap(cors)
             .configure(routes::configure)
-    })
-    .bind(format!("{}:{}", config.server.host, config.server.port))?
-    .run()
-    .await
+    });
+
+    if let Some(workers) = config.server.workers {
+        server = server.workers(workers);
+    }
+
+    server
+        .bind(format!("{}:{}", config.server.host, config.server.port))?
+        .run()
+        .await
 }
@@ -0,0 +1,85 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use validator::Validate;
+
+use super::export::{CollectionExport, ExportedRequest};
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct UpdateProfileDto {
+    #[validate(length(min = 1, max = 255, message = "Name must be 1 to 255 characters"))]
+    pub name: Option<String>,
+    #[validate(email(message = "Invalid email format"))]
+    pub email: Option<String>, // Has to be verified again once changed
+    pub current_password: Option<String>, // Required to change the email
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct ChangePasswordDto {
+    pub current_password: String,
+    #[validate(length(min = 6, message = "Password must be at least 6 characters"))]
+    pub new_password: String,
+}
+
+// What happens to the shared workspaces the user owns when the account goes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OwnedWorkspaces {
+    #[default]
+    Transfer, // To the member with the highest role, deleted when there is none
+    Delete,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeleteAccountDto {
+    pub password: String,
+    pub code: Option<String>, // A code from the app or a recovery code, with 2FA enabled
+    #[serde(default)]
+    pub owned_workspaces: OwnedWorkspaces,
+}
+
+// Everything in the workspaces the user owns, and the other workspaces they are a member of
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AccountExport {
+    pub version: u32,
+    pub exported_at: NaiveDateTime,
+    pub user: ExportedUser,
+    pub workspaces: Vec<ExportedWorkspace>,
+    pub memberships: Vec<ExportedMembership>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportedUser {
+    pub email: String,
+    pub name: Option<String>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportedWorkspace {
+    pub name: String,
+    pub description: Option<String>,
+    pub is_personal: bool,
+    pub collections: Vec<CollectionExport>,
+    pub requests: Vec<ExportedRequest>, // Requests outside of any collection
+    pub environments: Vec<ExportedEnvironment>,
+    pub secrets: Vec<ExportedSecret>, // Names only, the values never leave the server
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportedEnvironment {
+    pub name: String,
+    pub variables: Value,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportedSecret {
+    pub name: String,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportedMembership {
+    pub workspace: String,
+    pub role: String,
+}
@@ -0,0 +1,33 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::FromRow;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct AuditLogEntry {
+    pub id: Uuid,
+    pub workspace_id: Option<Uuid>,
+    pub actor_id: Option<Uuid>,
+    pub action: String, // Dotted name such as `request.updated` or `member.removed`
+    pub target_id: Option<Uuid>,
+    pub details: Value,
+    pub created_at: NaiveDateTime,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub before: Option<Value>,    // Summary of the target before the action
+    pub after: Option<Value>,     // Summary of the target after the action
+    pub api_key_id: Option<Uuid>, // Set when the action was taken with an API key
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuditLogQuery {
+    pub limit: Option<i64>,
+    pub cursor: Option<String>,
+    pub workspace_id: Option<Uuid>,
+    pub actor_id: Option<Uuid>,
+    pub action: Option<String>, // Matches the action itself or every action under it, like `member`
+    pub target_id: Option<Uuid>,
+    pub from: Option<NaiveDateTime>,
+    pub to: Option<NaiveDateTime>,
+}
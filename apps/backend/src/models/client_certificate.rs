@@ -0,0 +1,132 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+// A client certificate for mutual TLS, the key and chain only exist encrypted
+#[derive(Debug, FromRow)]
+pub struct ClientCertificate {
+    pub id: Uuid,
+    pub workspace_id: Uuid,
+    pub name: String,
+    pub host_patterns: Vec<String>,
+    pub format: String,
+    pub subject: String,
+    pub issuer: String,
+    pub expires_at: NaiveDateTime,
+    pub ciphertext: Vec<u8>, // Key and chain as PEM, encrypted with the workspace key
+    pub key_version: i32,
+    pub user_id: Option<Uuid>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CertificateFormat {
+    #[default]
+    Pem,
+    Pkcs12,
+}
+
+impl CertificateFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CertificateFormat::Pem => "pem",
+            CertificateFormat::Pkcs12 => "pkcs12",
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateClientCertificateDto {
+    #[validate(length(min = 1, max = 100, message = "Name must be 1 to 100 characters"))]
+    pub name: String,
+    #[validate(length(
+        min = 1,
+        max = 50,
+        message = "Between 1 and 50 host patterns are allowed"
+    ))]
+    pub host_patterns: Vec<String>,
+    #[serde(default)]
+    pub format: CertificateFormat,
+    // PEM text with the key and the chain, or a base64 encoded PKCS#12 file
+    #[validate(length(
+        min = 1,
+        max = 131072,
+        message = "Certificate must be 1 to 131072 characters"
+    ))]
+    pub certificate: String,
+    pub passphrase: Option<String>,
+}
+
+// A new certificate replaces the old one, like when it is renewed
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateClientCertificateDto {
+    #[validate(length(min = 1, max = 100, message = "Name must be 1 to 100 characters"))]
+    pub name: Option<String>,
+    #[validate(length(
+        min = 1,
+        max = 50,
+        message = "Between 1 and 50 host patterns are allowed"
+    ))]
+    pub host_patterns: Option<Vec<String>>,
+    #[serde(default)]
+    pub format: CertificateFormat,
+    #[validate(length(
+        min = 1,
+        max = 131072,
+        message = "Certificate must be 1 to 131072 characters"
+    ))]
+    pub certificate: Option<String>,
+    pub passphrase: Option<String>,
+}
+
+// Never carries the key, only what is needed to recognize the certificate
+#[derive(Debug, Serialize)]
+pub struct ClientCertificateResponse {
+    pub id: Uuid,
+    pub workspace_id: Uuid,
+    pub name: String,
+    pub host_patterns: Vec<String>,
+    pub format: String,
+    pub subject: String,
+    pub issuer: String,
+    pub expires_at: NaiveDateTime,
+    pub created_by: Option<Uuid>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+impl ClientCertificate {
+    pub fn to_response(&self) -> ClientCertificateResponse {
+        ClientCertificateResponse {
+            id: self.id,
+            workspace_id: self.workspace_id,
+            name: self.name.clone(),
+            host_patterns: self.host_patterns.clone(),
+            format: self.format.clone(),
+            subject: self.subject.clone(),
+            issuer: self.issuer.clone(),
+            expires_at: self.expires_at,
+            created_by: self.user_id,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        }
+    }
+
+    // What the audit log keeps of the certificate, never the key
+    pub fn audit_summary(&self) -> Value {
+        json!({
+            "name": self.name,
+            "host_patterns": self.host_patterns,
+            "format": self.format,
+            "subject": self.subject,
+            "issuer": self.issuer,
+            "expires_at": self.expires_at,
+            "key_version": self.key_version,
+        })
+    }
+}
@@ -1,9 +1,15 @@
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
 use sqlx::FromRow;
 use uuid::Uuid;
 use validator::Validate;
 
+use super::execution::{AuthConfig, ExecutionSettings};
+use crate::utils::json::double_option;
+use crate::utils::pagination::{SortField, SortOrder};
+use crate::utils::redact::redact_url;
+
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct Collection {
     pub id: Uuid,
@@ -12,26 +18,75 @@ pub struct Collection {
     pub user_id: Uuid,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
+    pub base_url: Option<String>,
+    pub default_headers: Value, // JSON object of headers inherited by requests
+    pub default_params: Value,  // JSON object of query parameters inherited by requests
+    pub auth: Option<Value>,    // JSON encoded AuthConfig inherited by requests
+    pub settings: Value,        // JSON encoded ExecutionSettings inherited by requests
+    pub deleted_at: Option<NaiveDateTime>, // Set while the collection is in the trash
+    pub workspace_id: Uuid,
 }
 
 #[derive(Debug, Serialize, Deserialize, Validate)]
 pub struct CreateCollectionDto {
+    pub workspace_id: Option<Uuid>, // Defaults to the personal workspace
     #[validate(length(min = 1, message = "Name cannot be empty"))]
     pub name: String,
     pub description: Option<String>,
+    #[validate(url(message = "Invalid base URL format"))]
+    pub base_url: Option<String>,
+    pub default_headers: Option<Value>,
+    pub default_params: Option<Value>,
+    pub auth: Option<AuthConfig>,
+    pub settings: Option<ExecutionSettings>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Validate)]
 pub struct UpdateCollectionDto {
     pub name: Option<String>,
     pub description: Option<String>,
+    #[serde(default, deserialize_with = "double_option")]
+    #[validate(url(message = "Invalid base URL format"))]
+    pub base_url: Option<Option<String>>, // Null removes the base URL
+    pub default_headers: Option<Value>,
+    pub default_params: Option<Value>,
+    #[serde(default, deserialize_with = "double_option")]
+    pub auth: Option<Option<AuthConfig>>, // Null removes the auth
+    pub settings: Option<ExecutionSettings>,
+}
+
+// Query string of the collection list endpoint
+#[derive(Debug, Deserialize)]
+pub struct CollectionListQuery {
+    pub limit: Option<i64>,
+    pub cursor: Option<String>,
+    #[serde(default)]
+    pub sort: SortField,
+    #[serde(default)]
+    pub order: SortOrder,
+    pub workspace_id: Option<Uuid>,
+    pub tag: Option<String>, // Tag name
+    pub updated_after: Option<NaiveDateTime>,
+    pub updated_before: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct DuplicateCollectionDto {
+    pub name: Option<String>,
+    pub target_collection_id: Option<Uuid>, // Copy the requests into this collection instead
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CollectionResponse {
     pub id: Uuid,
+    pub workspace_id: Uuid,
     pub name: String,
     pub description: Option<String>,
+    pub base_url: Option<String>,
+    pub default_headers: Value,
+    pub default_params: Value,
+    pub auth: Option<Value>,
+    pub settings: Value,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
 }
@@ -40,8 +95,14 @@ pub struct CollectionResponse {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CollectionWithRequestsResponse {
     pub id: Uuid,
+    pub workspace_id: Uuid,
     pub name: String,
     pub description: Option<String>,
+    pub base_url: Option<String>,
+    pub default_headers: Value,
+    pub default_params: Value,
+    pub auth: Option<Value>,
+    pub settings: Value,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
     pub requests: Vec<super::request::RequestResponse>,
@@ -51,10 +112,94 @@ impl Collection {
     pub fn to_response(&self) -> CollectionResponse {
         CollectionResponse {
             id: self.id,
+            workspace_id: self.workspace_id,
+            name: self.name.clone(),
+            description: self.description.clone(),
+            base_url: self.base_url.clone(),
+            default_headers: self.default_headers.clone(),
+            default_params: self.default_params.clone(),
+            auth: self.auth.clone(),
+            settings: self.settings.clone(),
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        }
+    }
+
+    pub fn to_response_with_requests(
+        &self,
+        requests: Vec<super::request::RequestResponse>,
+    ) -> CollectionWithRequestsResponse {
+        CollectionWithRequestsResponse {
+            id: self.id,
+            workspace_id: self.workspace_id,
             name: self.name.clone(),
             description: self.description.clone(),
+            base_url: self.base_url.clone(),
+            default_headers: self.default_headers.clone(),
+            default_params: self.default_params.clone(),
+            auth: self.auth.clone(),
+            settings: self.settings.clone(),
             created_at: self.created_at,
             updated_at: self.updated_at,
+            requests,
         }
     }
+
+    // What the audit log keeps of the collection, without credentials
+    pub fn audit_summary(&self) -> Value {
+        json!({
+            "name": self.name,
+            "description": self.description,
+            "base_url": self.base_url.as_deref().map(redact_url),
+            "has_auth": self.auth.is_some(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(body: Value) -> UpdateCollectionDto {
+        serde_json::from_value(body).unwrap()
+    }
+
+    #[test]
+    fn missing_fields_keep_their_value() {
+        let dto = update(json!({ "name": "Renamed" }));
+
+        assert!(dto.base_url.is_none());
+        assert!(dto.auth.is_none());
+    }
+
+    #[test]
+    fn null_fields_are_cleared() {
+        let dto = update(json!({ "base_url": null, "auth": null }));
+
+        assert_eq!(dto.base_url, Some(None));
+        assert!(matches!(dto.auth, Some(None)));
+    }
+
+    #[test]
+    fn given_fields_are_set() {
+        let dto = update(json!({
+            "base_url": "https://api.example.com/v1",
+            "auth": { "type": "bearer", "token": "abc" },
+        }));
+
+        assert_eq!(
+            dto.base_url,
+            Some(Some("https://api.example.com/v1".to_string()))
+        );
+        assert!(matches!(dto.auth, Some(Some(AuthConfig::Bearer { .. }))));
+        assert!(dto.validate().is_ok());
+    }
+
+    #[test]
+    fn invalid_base_url_is_refused() {
+        assert!(update(json!({ "base_url": "not a url" }))
+            .validate()
+            .is_err());
+        assert!(update(json!({ "base_url": null })).validate().is_ok());
+    }
 }
@@ -0,0 +1,66 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Environment {
+    pub id: Uuid,
+    pub workspace_id: Uuid,
+    pub name: String,
+    pub variables: Value, // JSON object of variable names to values
+    pub user_id: Uuid,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct CreateEnvironmentDto {
+    pub workspace_id: Option<Uuid>, // Defaults to the personal workspace
+    #[validate(length(min = 1, message = "Name cannot be empty"))]
+    pub name: String,
+    pub variables: Option<Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct UpdateEnvironmentDto {
+    #[validate(length(min = 1, message = "Name cannot be empty"))]
+    pub name: Option<String>,
+    pub variables: Option<Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct EnvironmentResponse {
+    pub id: Uuid,
+    pub workspace_id: Uuid,
+    pub name: String,
+    pub variables: Value,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+impl Environment {
+    pub fn to_response(&self) -> EnvironmentResponse {
+        EnvironmentResponse {
+            id: self.id,
+            workspace_id: self.workspace_id,
+            name: self.name.clone(),
+            variables: self.variables.clone(),
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        }
+    }
+
+    // What the audit log keeps of the environment, the variable names but not their values
+    pub fn audit_summary(&self) -> Value {
+        let variables = self
+            .variables
+            .as_object()
+            .map(|variables| variables.keys().cloned().collect::<Vec<_>>())
+            .unwrap_or_default();
+
+        json!({ "name": self.name, "variables": variables })
+    }
+}
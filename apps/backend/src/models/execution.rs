@@ -0,0 +1,161 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sqlx::FromRow;
+use uuid::Uuid;
+
+use crate::utils::redact::REDACTED;
+
+// Auth configuration stored on collections and requests
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AuthConfig {
+    // Explicitly disables auth inherited from the collection
+    None,
+    Bearer {
+        token: String,
+    },
+    Basic {
+        username: String,
+        password: Option<String>,
+    },
+    ApiKey {
+        key: String,
+        value: String,
+        #[serde(default)]
+        location: ApiKeyLocation,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApiKeyLocation {
+    #[default]
+    Header,
+    Query,
+}
+
+// A proxy requests are sent through, credentials go in their own fields rather than the URL
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProxyServer {
+    pub url: String, // http, https, socks5 or socks5h to let the proxy resolve host names
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub no_proxy: Vec<String>, // Sent directly, host names match their subdomains, IP addresses or CIDRs
+}
+
+// Proxy picked by a request, a collection or a workspace
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum ProxyMode {
+    // Send directly, even when the workspace or the server has a proxy
+    Direct,
+    Proxy(ProxyServer),
+}
+
+impl ProxyMode {
+    // The same choice without the password, for anyone who only needs to know the proxy
+    pub fn redacted(&self) -> ProxyMode {
+        match self {
+            ProxyMode::Direct => ProxyMode::Direct,
+            ProxyMode::Proxy(proxy) => ProxyMode::Proxy(ProxyServer {
+                password: proxy.password.as_ref().map(|_| REDACTED.to_string()),
+                ..proxy.clone()
+            }),
+        }
+    }
+}
+
+// Where the proxy of an execution came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProxySource {
+    Request, // Settings of the request or its collection
+    Workspace,
+    Server,
+}
+
+// The proxy an execution went through
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsedProxy {
+    pub url: String,
+    pub source: ProxySource,
+}
+
+// Execution settings, every field falls back to the collection and then to the client defaults
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExecutionSettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub follow_redirects: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_redirects: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub verify_tls: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<ProxyMode>,
+}
+
+impl ExecutionSettings {
+    // Values set on `self` win over the ones in `fallback`
+    pub fn merge(&self, fallback: &ExecutionSettings) -> ExecutionSettings {
+        ExecutionSettings {
+            timeout_ms: self.timeout_ms.or(fallback.timeout_ms),
+            follow_redirects: self.follow_redirects.or(fallback.follow_redirects),
+            max_redirects: self.max_redirects.or(fallback.max_redirects),
+            verify_tls: self.verify_tls.or(fallback.verify_tls),
+            proxy: self.proxy.clone().or_else(|| fallback.proxy.clone()),
+        }
+    }
+}
+
+// The request as it is sent, after the collection defaults have been applied
+#[derive(Debug, Clone, Serialize)]
+pub struct ResolvedRequest {
+    pub request_id: Uuid,
+    pub collection_id: Option<Uuid>,
+    pub workspace_id: Uuid,
+    pub method: String,
+    pub url: String,
+    pub headers: Map<String, Value>,
+    pub params: Map<String, Value>,
+    pub body: Option<Value>,
+    pub auth: Option<AuthConfig>,
+    pub settings: ExecutionSettings,
+}
+
+// A stored execution of a saved request and the response it received
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Execution {
+    pub id: Uuid,
+    pub request_id: Uuid,
+    pub user_id: Option<Uuid>, // Missing once the user who ran it deleted the account
+    pub status: i32,
+    pub headers: Value, // JSON object of response headers
+    pub body: Value,    // Response body
+    pub duration_ms: i64,
+    pub executed_at: NaiveDateTime,
+    pub proxy: Option<Value>, // UsedProxy, missing when the request was sent directly
+    pub client_certificate_id: Option<Uuid>,
+}
+
+// Outcome of one request in a collection run
+#[derive(Debug, Serialize)]
+pub struct RunResult {
+    pub request_id: Uuid,
+    pub name: String,
+    pub status: Option<u16>, // Missing when the request could not be sent
+    pub duration_ms: i64,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CollectionRunResponse {
+    pub collection_id: Uuid,
+    pub started_at: NaiveDateTime,
+    pub results: Vec<RunResult>,
+}
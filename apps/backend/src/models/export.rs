@@ -0,0 +1,99 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::collection::Collection;
+use super::request::Request;
+use crate::utils::redact::{redact_auth, redact_url, redact_value};
+
+// Version of the export document, bumped when its shape changes
+pub const EXPORT_FORMAT_VERSION: u32 = 1;
+
+// A collection and its requests without ids, so it can be imported anywhere
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CollectionExport {
+    pub version: u32,
+    pub exported_at: NaiveDateTime,
+    pub collection: ExportedCollection,
+    pub requests: Vec<ExportedRequest>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportedCollection {
+    pub name: String,
+    pub description: Option<String>,
+    pub base_url: Option<String>,
+    pub default_headers: Value,
+    pub default_params: Value,
+    pub auth: Option<Value>,
+    pub settings: Value,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportedRequest {
+    pub name: String,
+    pub description: Option<String>,
+    pub url: String,
+    pub method: String,
+    pub headers: Value,
+    pub body: Option<Value>,
+    pub params: Option<Value>,
+    pub auth: Option<Value>,
+    pub settings: Value,
+}
+
+impl ExportedRequest {
+    pub fn new(request: &Request) -> ExportedRequest {
+        ExportedRequest {
+            name: request.name.clone(),
+            description: request.description.clone(),
+            url: request.url.clone(),
+            method: request.method.clone(),
+            headers: request.headers.clone(),
+            body: request.body.clone(),
+            params: request.params.clone(),
+            auth: request.auth.clone(),
+            settings: request.settings.clone(),
+        }
+    }
+}
+
+impl CollectionExport {
+    pub fn new(collection: &Collection, requests: &[Request]) -> CollectionExport {
+        CollectionExport {
+            version: EXPORT_FORMAT_VERSION,
+            exported_at: chrono::Utc::now().naive_utc(),
+            collection: ExportedCollection {
+                name: collection.name.clone(),
+                description: collection.description.clone(),
+                base_url: collection.base_url.clone(),
+                default_headers: collection.default_headers.clone(),
+                default_params: collection.default_params.clone(),
+                auth: collection.auth.clone(),
+                settings: collection.settings.clone(),
+            },
+            requests: requests.iter().map(ExportedRequest::new).collect(),
+        }
+    }
+
+    // Strip credentials and secret looking values, for viewers outside the workspace
+    pub fn redacted(mut self) -> CollectionExport {
+        let collection = &mut self.collection;
+        collection.base_url = collection.base_url.as_deref().map(redact_url);
+        collection.default_headers = redact_value(&collection.default_headers);
+        collection.default_params = redact_value(&collection.default_params);
+        collection.auth = collection.auth.as_ref().map(redact_auth);
+        collection.settings = redact_value(&collection.settings);
+
+        for request in &mut self.requests {
+            request.url = redact_url(&request.url);
+            request.headers = redact_value(&request.headers);
+            request.body = request.body.as_ref().map(redact_value);
+            request.params = request.params.as_ref().map(redact_value);
+            request.auth = request.auth.as_ref().map(redact_auth);
+            request.settings = redact_value(&request.settings);
+        }
+
+        self
+    }
+}
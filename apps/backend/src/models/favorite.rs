@@ -0,0 +1,11 @@
+use serde::{Deserialize, Serialize};
+
+use super::collection::CollectionResponse;
+use super::request::RequestResponse;
+
+// Pinned items of a user, most recently pinned first
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FavoritesResponse {
+    pub collections: Vec<CollectionResponse>,
+    pub requests: Vec<RequestResponse>,
+}
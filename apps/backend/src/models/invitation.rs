@@ -0,0 +1,87 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+use super::WorkspaceRole;
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Invitation {
+    pub id: Uuid,
+    pub workspace_id: Uuid,
+    pub email: String,
+    pub role: String,
+    pub token_hash: String,
+    pub status: String, // pending, accepted, declined or revoked
+    pub invited_by: Option<Uuid>,
+    pub expires_at: NaiveDateTime,
+    pub responded_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct CreateInvitationDto {
+    #[validate(email(message = "Invalid email format"))]
+    pub email: String,
+    pub role: Option<WorkspaceRole>, // Defaults to editor
+    #[validate(range(min = 1, max = 30, message = "Expiry must be 1 to 30 days"))]
+    pub expires_in_days: Option<i64>, // Defaults to the configured expiry
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InvitationResponse {
+    pub id: Uuid,
+    pub workspace_id: Uuid,
+    pub email: String,
+    pub role: String,
+    pub status: String,
+    pub invited_by: Option<Uuid>,
+    pub expires_at: NaiveDateTime,
+    pub responded_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>, // Only returned when the invitation is created
+}
+
+// What the holder of an invitation token sees before accepting it
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InvitationPreview {
+    pub workspace_id: Uuid,
+    pub workspace_name: String,
+    pub email: String,
+    pub role: String,
+    pub status: String,
+    pub invited_by: Option<String>, // Name or email of the inviter
+    pub expires_at: NaiveDateTime,
+}
+
+impl Invitation {
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= chrono::Utc::now().naive_utc()
+    }
+
+    // Status as shown to users, pending invitations past their expiry are expired
+    pub fn display_status(&self) -> String {
+        if self.status == "pending" && self.is_expired() {
+            "expired".to_string()
+        } else {
+            self.status.clone()
+        }
+    }
+
+    pub fn to_response(&self) -> InvitationResponse {
+        InvitationResponse {
+            id: self.id,
+            workspace_id: self.workspace_id,
+            email: self.email.clone(),
+            role: self.role.clone(),
+            status: self.display_status(),
+            invited_by: self.invited_by,
+            expires_at: self.expires_at,
+            responded_at: self.responded_at,
+            created_at: self.created_at,
+            token: None,
+        }
+    }
+}
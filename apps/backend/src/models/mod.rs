@@ -1,10 +1,73 @@
+pub mod account;
+pub mod api_key;
+pub mod audit;
+pub mod client_certificate;
 pub mod collection;
+pub mod email_token;
+pub mod environment;
+pub mod execution;
+pub mod export;
+pub mod favorite;
+pub mod invitation;
+pub mod oidc;
 pub mod request;
+pub mod revision;
+pub mod search;
+pub mod secret;
+pub mod share;
+pub mod tag;
+pub mod trash;
+pub mod two_factor;
 pub mod user;
+pub mod workspace;
 
+pub use account::{
+    AccountExport, ChangePasswordDto, DeleteAccountDto, ExportedEnvironment, ExportedMembership,
+    ExportedSecret, ExportedUser, ExportedWorkspace, OwnedWorkspaces, UpdateProfileDto,
+};
+pub use api_key::{ApiKey, ApiKeyScope, CreateApiKeyDto};
+pub use audit::{AuditLogEntry, AuditLogQuery};
+pub use client_certificate::{
+    CertificateFormat, ClientCertificate, ClientCertificateResponse, CreateClientCertificateDto,
+    UpdateClientCertificateDto,
+};
 pub use collection::{
-    Collection, CollectionResponse, CollectionWithRequestsResponse, CreateCollectionDto,
-    UpdateCollectionDto,
+    Collection, CollectionListQuery, CollectionResponse, CreateCollectionDto,
+    DuplicateCollectionDto, UpdateCollectionDto,
+};
+pub use email_token::{EmailToken, EmailTokenPurpose};
+pub use environment::{
+    CreateEnvironmentDto, Environment, EnvironmentResponse, UpdateEnvironmentDto,
+};
+pub use execution::{
+    ApiKeyLocation, AuthConfig, CollectionRunResponse, Execution, ExecutionSettings, ProxyMode,
+    ProxyServer, ProxySource, ResolvedRequest, RunResult, UsedProxy,
+};
+pub use export::{CollectionExport, ExportedRequest, EXPORT_FORMAT_VERSION};
+pub use favorite::FavoritesResponse;
+pub use invitation::{CreateInvitationDto, Invitation, InvitationPreview};
+pub use oidc::OidcCallbackQuery;
+pub use request::{
+    CreateRequestDto, DuplicateRequestDto, Request, RequestListQuery, UpdateRequestDto,
+};
+pub use revision::{EntityType, FieldChange, Revision, RevisionResponse};
+pub use search::{SearchQuery, SearchResult};
+pub use secret::{
+    CreateSecretDto, Secret, SecretKeyRotationResponse, SecretResponse, UpdateSecretDto,
+};
+pub use share::{CreateShareLinkDto, ShareLink};
+pub use tag::{CreateTagDto, SetTagsDto, Tag, TagResponse, UpdateTagDto};
+pub use trash::{TrashResponse, TrashedCollection, TrashedRequest};
+pub use two_factor::{
+    DisableTwoFactorDto, LoginChallengeResponse, RecoveryCodesResponse, TwoFactorCodeDto,
+    TwoFactorLoginDto, TwoFactorSetupResponse,
+};
+pub use user::{
+    AuthResponse, CreateUserDto, LoginDto, PasswordResetDto, PasswordResetRequestDto,
+    RefreshTokenDto, TokenResponse, User, VerifyEmailDto,
+};
+pub use workspace::{
+    CreateWorkspaceDto, Permission, TransferOwnershipDto, UpdateMemberDto, UpdateWorkspaceDto,
+    Workspace, WorkspaceMember, WorkspaceProxyResponse, WorkspaceQuery, WorkspaceResponse,
+    WorkspaceRole,
 };
-pub use request::{CreateRequestDto, Request, RequestResponse, UpdateRequestDto};
-pub use user::{AuthResponse, CreateUserDto, LoginDto, User, UserResponse};
@@ -0,0 +1,10 @@
+use serde::Deserialize;
+
+// Query string the identity provider sends the browser back with
+#[derive(Debug, Deserialize)]
+pub struct OidcCallbackQuery {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>, // Set instead of the code when the login was refused
+    pub error_description: Option<String>,
+}
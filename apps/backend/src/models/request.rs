@@ -1,9 +1,14 @@
 use chrono::NaiveDateTime;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serde_json::{json, Value};
 use sqlx::FromRow;
+use url::Url;
 use uuid::Uuid;
-use validator::Validate;
+use validator::{Validate, ValidationError};
+
+use super::execution::{AuthConfig, ExecutionSettings};
+use crate::utils::pagination::{SortField, SortOrder};
+use crate::utils::redact::redact_url;
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct Request {
@@ -19,14 +24,35 @@ pub struct Request {
     pub user_id: Uuid,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
+    pub auth: Option<Value>, // JSON encoded AuthConfig, overrides the collection auth
+    pub settings: Value,     // JSON encoded ExecutionSettings, overrides the collection settings
+    pub deleted_at: Option<NaiveDateTime>, // Set while the request is in the trash
+    pub workspace_id: Uuid,
+}
+
+// Request URLs are either absolute or a path relative to the collection base URL
+fn validate_request_url(url: &str) -> Result<(), ValidationError> {
+    if url.starts_with('/') {
+        return Ok(());
+    }
+
+    match Url::parse(url) {
+        Ok(parsed) if parsed.scheme() == "http" || parsed.scheme() == "https" => Ok(()),
+        _ => {
+            let mut error = ValidationError::new("url");
+            error.message = Some("Invalid URL format".into());
+            Err(error)
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Validate)]
 pub struct CreateRequestDto {
+    pub workspace_id: Option<Uuid>, // Defaults to the workspace of the collection, then the personal one
     #[validate(length(min = 1, message = "Name cannot be empty"))]
     pub name: String,
     pub description: Option<String>,
-    #[validate(url(message = "Invalid URL format"))]
+    #[validate(custom = "validate_request_url")]
     pub url: String,
     #[validate(length(min = 1, message = "Method cannot be empty"))]
     pub method: String,
@@ -34,23 +60,53 @@ pub struct CreateRequestDto {
     pub body: Option<Value>,
     pub params: Option<Value>,
     pub collection_id: Option<Uuid>,
+    pub auth: Option<AuthConfig>,
+    pub settings: Option<ExecutionSettings>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Validate)]
 pub struct UpdateRequestDto {
     pub name: Option<String>,
     pub description: Option<String>,
+    #[validate(custom = "validate_request_url")]
     pub url: Option<String>,
     pub method: Option<String>,
     pub headers: Option<Value>,
     pub body: Option<Value>,
     pub params: Option<Value>,
     pub collection_id: Option<Uuid>,
+    pub auth: Option<AuthConfig>,
+    pub settings: Option<ExecutionSettings>,
+}
+
+// Query string of the request list endpoint
+#[derive(Debug, Deserialize)]
+pub struct RequestListQuery {
+    pub limit: Option<i64>,
+    pub cursor: Option<String>,
+    #[serde(default)]
+    pub sort: SortField,
+    #[serde(default)]
+    pub order: SortOrder,
+    pub workspace_id: Option<Uuid>,
+    pub method: Option<String>,
+    pub collection_id: Option<Uuid>,
+    pub host: Option<String>,
+    pub tag: Option<String>, // Tag name
+    pub updated_after: Option<NaiveDateTime>,
+    pub updated_before: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct DuplicateRequestDto {
+    pub name: Option<String>,
+    pub collection_id: Option<Uuid>, // Defaults to the collection of the source request
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RequestResponse {
     pub id: Uuid,
+    pub workspace_id: Uuid,
     pub name: String,
     pub description: Option<String>,
     pub url: String,
@@ -59,6 +115,8 @@ pub struct RequestResponse {
     pub body: Option<Value>,
     pub params: Option<Value>,
     pub collection_id: Option<Uuid>,
+    pub auth: Option<Value>,
+    pub settings: Value,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
 }
@@ -67,6 +125,7 @@ impl Request {
     pub fn to_response(&self) -> RequestResponse {
         RequestResponse {
             id: self.id,
+            workspace_id: self.workspace_id,
             name: self.name.clone(),
             description: self.description.clone(),
             url: self.url.clone(),
@@ -75,8 +134,20 @@ impl Request {
             body: self.body.clone(),
             params: self.params.clone(),
             collection_id: self.collection_id,
+            auth: self.auth.clone(),
+            settings: self.settings.clone(),
             created_at: self.created_at,
             updated_at: self.updated_at,
         }
     }
+
+    // What the audit log keeps of the request, without credentials
+    pub fn audit_summary(&self) -> Value {
+        json!({
+            "name": self.name,
+            "method": self.method,
+            "url": redact_url(&self.url),
+            "collection_id": self.collection_id,
+        })
+    }
 }
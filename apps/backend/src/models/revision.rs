@@ -0,0 +1,63 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::FromRow;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntityType {
+    Request,
+    Collection,
+}
+
+impl EntityType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EntityType::Request => "request",
+            EntityType::Collection => "collection",
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Revision {
+    pub id: Uuid,
+    pub entity_type: String,
+    pub entity_id: Uuid,
+    pub version: i32,
+    pub snapshot: Value, // Full state of the entity after the change
+    pub user_id: Option<Uuid>,
+    pub created_at: NaiveDateTime,
+}
+
+// A single top-level field that differs between two snapshots
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FieldChange {
+    pub field: String,
+    pub before: Value,
+    pub after: Value,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RevisionResponse {
+    pub id: Uuid,
+    pub version: i32,
+    pub user_id: Option<Uuid>,
+    pub created_at: NaiveDateTime,
+    pub snapshot: Value,
+    pub changes: Vec<FieldChange>, // Differences from the previous revision
+}
+
+impl Revision {
+    pub fn to_response(&self, changes: Vec<FieldChange>) -> RevisionResponse {
+        RevisionResponse {
+            id: self.id,
+            version: self.version,
+            user_id: self.user_id,
+            created_at: self.created_at,
+            snapshot: self.snapshot.clone(),
+            changes,
+        }
+    }
+}
@@ -0,0 +1,26 @@
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct SearchQuery {
+    #[validate(length(min = 2, message = "Search query must be at least 2 characters"))]
+    pub q: String,
+    #[serde(default)]
+    pub include_responses: bool, // Also search the bodies of stored responses
+    pub workspace_id: Option<Uuid>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct SearchResult {
+    pub kind: String, // 'request', 'collection' or 'response'
+    pub id: Uuid,
+    pub workspace_id: Uuid,
+    pub request_id: Option<Uuid>,
+    pub collection_id: Option<Uuid>,
+    pub title: String,
+    pub rank: f32,
+    pub snippet: String, // Matches are wrapped in <mark> tags
+}
@@ -0,0 +1,109 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::{Validate, ValidationError};
+
+use crate::utils::secrets::placeholder;
+
+// A secret variable, the value only exists encrypted
+#[derive(Debug, FromRow)]
+pub struct Secret {
+    pub id: Uuid,
+    pub workspace_id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub ciphertext: Vec<u8>, // Nonce followed by the value encrypted with the workspace key
+    pub key_version: i32,
+    pub user_id: Option<Uuid>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+// Letters, digits and underscores, so the name can be written into a placeholder
+fn validate_secret_name(name: &str) -> Result<(), ValidationError> {
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+    if !valid {
+        let mut error = ValidationError::new("name");
+        error.message = Some(
+            "Name can only contain letters, digits and underscores and cannot start with a digit"
+                .into(),
+        );
+        return Err(error);
+    }
+    Ok(())
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct CreateSecretDto {
+    #[validate(
+        length(min = 1, max = 100, message = "Name must be 1 to 100 characters"),
+        custom = "validate_secret_name"
+    )]
+    pub name: String,
+    #[validate(length(min = 1, max = 65536, message = "Value must be 1 to 65536 characters"))]
+    pub value: String,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate)]
+pub struct UpdateSecretDto {
+    #[validate(
+        length(min = 1, max = 100, message = "Name must be 1 to 100 characters"),
+        custom = "validate_secret_name"
+    )]
+    pub name: Option<String>,
+    #[validate(length(min = 1, max = 65536, message = "Value must be 1 to 65536 characters"))]
+    pub value: Option<String>,
+    pub description: Option<String>,
+}
+
+// Never carries the value, only what is needed to refer to the secret
+#[derive(Debug, Serialize)]
+pub struct SecretResponse {
+    pub id: Uuid,
+    pub workspace_id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub placeholder: String, // What to write into a request to use the secret
+    pub created_by: Option<Uuid>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SecretKeyRotationResponse {
+    pub key_version: i32,
+    pub secrets: usize, // Secrets encrypted again with the new key
+    pub client_certificates: usize,
+}
+
+impl Secret {
+    pub fn to_response(&self) -> SecretResponse {
+        SecretResponse {
+            id: self.id,
+            workspace_id: self.workspace_id,
+            name: self.name.clone(),
+            description: self.description.clone(),
+            placeholder: placeholder(&self.name),
+            created_by: self.user_id,
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        }
+    }
+
+    // What the audit log keeps of the secret, never the value
+    pub fn audit_summary(&self) -> Value {
+        json!({
+            "name": self.name,
+            "description": self.description,
+            "key_version": self.key_version,
+        })
+    }
+}
@@ -0,0 +1,65 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct ShareLink {
+    pub id: Uuid,
+    pub collection_id: Uuid,
+    pub workspace_id: Uuid,
+    pub token_hash: String,
+    pub password_hash: Option<String>, // Viewers must send the password when set
+    pub expires_at: Option<NaiveDateTime>, // Never expires when missing
+    pub revoked_at: Option<NaiveDateTime>,
+    pub created_by: Option<Uuid>,
+    pub last_accessed_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+    pub failed_password_count: i32, // Wrong passwords in a row
+    pub locked_until: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct CreateShareLinkDto {
+    #[validate(length(min = 6, message = "Password must be at least 6 characters"))]
+    pub password: Option<String>,
+    #[validate(range(min = 1, max = 365, message = "Expiry must be 1 to 365 days"))]
+    pub expires_in_days: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ShareLinkResponse {
+    pub id: Uuid,
+    pub collection_id: Uuid,
+    pub has_password: bool,
+    pub expires_at: Option<NaiveDateTime>,
+    pub revoked_at: Option<NaiveDateTime>,
+    pub last_accessed_at: Option<NaiveDateTime>,
+    pub created_at: NaiveDateTime,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>, // Only returned when the link is created
+}
+
+impl ShareLink {
+    // Revoked and expired links no longer open the collection
+    pub fn is_active(&self) -> bool {
+        self.revoked_at.is_none()
+            && self
+                .expires_at
+                .is_none_or(|expires_at| expires_at > chrono::Utc::now().naive_utc())
+    }
+
+    pub fn to_response(&self) -> ShareLinkResponse {
+        ShareLinkResponse {
+            id: self.id,
+            collection_id: self.collection_id,
+            has_password: self.password_hash.is_some(),
+            expires_at: self.expires_at,
+            revoked_at: self.revoked_at,
+            last_accessed_at: self.last_accessed_at,
+            created_at: self.created_at,
+            token: None,
+        }
+    }
+}
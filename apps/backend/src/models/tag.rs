@@ -0,0 +1,62 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::FromRow;
+use uuid::Uuid;
+use validator::Validate;
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Tag {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub color: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+    pub workspace_id: Uuid,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct CreateTagDto {
+    pub workspace_id: Option<Uuid>, // Defaults to the personal workspace
+    #[validate(length(min = 1, max = 50, message = "Name must be 1 to 50 characters"))]
+    pub name: String,
+    #[validate(length(max = 20, message = "Color must be at most 20 characters"))]
+    pub color: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct UpdateTagDto {
+    #[validate(length(min = 1, max = 50, message = "Name must be 1 to 50 characters"))]
+    pub name: Option<String>,
+    #[validate(length(max = 20, message = "Color must be at most 20 characters"))]
+    pub color: Option<String>,
+}
+
+// Replaces the full set of tags on a request or collection
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SetTagsDto {
+    pub tag_ids: Vec<Uuid>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TagResponse {
+    pub id: Uuid,
+    pub workspace_id: Uuid,
+    pub name: String,
+    pub color: Option<String>,
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+impl Tag {
+    pub fn to_response(&self) -> TagResponse {
+        TagResponse {
+            id: self.id,
+            workspace_id: self.workspace_id,
+            name: self.name.clone(),
+            color: self.color.clone(),
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TrashedCollection {
+    pub id: Uuid,
+    pub workspace_id: Uuid,
+    pub name: String,
+    pub request_count: i64, // Requests that were deleted together with the collection
+    pub deleted_at: NaiveDateTime,
+    pub purge_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TrashedRequest {
+    pub id: Uuid,
+    pub workspace_id: Uuid,
+    pub name: String,
+    pub method: String,
+    pub url: String,
+    pub collection_id: Option<Uuid>,
+    pub deleted_at: NaiveDateTime,
+    pub purge_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TrashResponse {
+    pub collections: Vec<TrashedCollection>,
+    pub requests: Vec<TrashedRequest>,
+}
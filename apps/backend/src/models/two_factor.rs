@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+
+// Secret to add to an authenticator app, 2FA is on once a code from it is confirmed
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TwoFactorSetupResponse {
+    pub secret: String,      // Base32, for typing into the app
+    pub otpauth_uri: String, // For rendering as a QR code
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TwoFactorCodeDto {
+    pub code: String,
+}
+
+// Codes are only shown once, a new set replaces the previous one
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecoveryCodesResponse {
+    pub recovery_codes: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DisableTwoFactorDto {
+    pub password: String,
+    pub code: String, // A code from the app or a recovery code
+}
+
+// Answer to a correct password when the account has 2FA enabled
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LoginChallengeResponse {
+    pub two_factor_required: bool,
+    pub challenge_token: String,
+    pub expires_in: i64, // Seconds left to send the code
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TwoFactorLoginDto {
+    pub challenge_token: String,
+    pub code: String, // A code from the app or a recovery code
+}
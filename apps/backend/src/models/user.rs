@@ -12,6 +12,12 @@ pub struct User {
     pub name: Option<String>,
     pub created_at: NaiveDateTime,
     pub updated_at: NaiveDateTime,
+    pub email_verified_at: Option<NaiveDateTime>,
+    pub totp_secret: Option<String>, // Base32, set from the 2FA setup on
+    pub totp_enabled_at: Option<NaiveDateTime>,
+    pub totp_last_step: Option<i64>,
+    pub failed_login_count: i32,
+    pub locked_until: Option<NaiveDateTime>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Validate)]
@@ -35,13 +41,47 @@ pub struct UserResponse {
     pub id: Uuid,
     pub email: String,
     pub name: Option<String>,
+    pub email_verified: bool,
+    pub two_factor_enabled: bool,
     pub created_at: NaiveDateTime,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct AuthResponse {
     pub user: UserResponse,
+    #[serde(flatten)]
+    pub tokens: TokenResponse,
+}
+
+// A short-lived access token and the refresh token to get the next one
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TokenResponse {
     pub token: String,
+    pub refresh_token: String,
+    pub expires_in: i64, // Seconds until the access token expires
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RefreshTokenDto {
+    pub refresh_token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VerifyEmailDto {
+    pub token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct PasswordResetRequestDto {
+    #[validate(email(message = "Invalid email format"))]
+    pub email: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct PasswordResetDto {
+    pub token: String,
+    #[validate(length(min = 6, message = "Password must be at least 6 characters"))]
+    pub password: String,
 }
 
 impl User {
@@ -50,6 +90,8 @@ impl User {
             id: self.id,
             email: self.email.clone(),
             name: self.name.clone(),
+            email_verified: self.email_verified_at.is_some(),
+            two_factor_enabled: self.totp_enabled_at.is_some(),
             created_at: self.created_at,
         }
     }
@@ -0,0 +1,290 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::FromRow;
+use std::str::FromStr;
+use uuid::Uuid;
+use validator::Validate;
+
+use super::execution::ProxyMode;
+use crate::error::AppError;
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Workspace {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub is_personal: bool, // Created with the account, cannot be deleted
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+    pub require_2fa: bool, // Members without two-factor authentication are locked out
+    pub proxy: Option<Value>, // JSON encoded ProxyMode, the proxy of the server is used without one
+}
+
+// Roles of workspace members, from most to least privileged
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkspaceRole {
+    Owner,
+    Admin,
+    Editor,
+    Runner,
+    Viewer,
+}
+
+// Operations guarded by the role of the member, reading only needs membership
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    Execute,           // Send a single request
+    Run,               // Run all requests of a collection
+    Export,            // Export a collection or share it by link
+    Edit,              // Create, update, delete, duplicate and restore
+    Purge,             // Permanently delete from the trash
+    ManageMembers,     // Invite, add and remove members and change their roles
+    ManageWorkspace,   // Rename the workspace
+    ViewAudit,         // Read the audit log of the workspace
+    TransferOwnership, // Hand the workspace over to another member
+    DeleteWorkspace,   // Delete the workspace with everything in it
+}
+
+impl WorkspaceRole {
+    pub const ALL: [WorkspaceRole; 5] = [
+        WorkspaceRole::Owner,
+        WorkspaceRole::Admin,
+        WorkspaceRole::Editor,
+        WorkspaceRole::Runner,
+        WorkspaceRole::Viewer,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WorkspaceRole::Owner => "owner",
+            WorkspaceRole::Admin => "admin",
+            WorkspaceRole::Editor => "editor",
+            WorkspaceRole::Runner => "runner",
+            WorkspaceRole::Viewer => "viewer",
+        }
+    }
+
+    pub fn can(&self, permission: Permission) -> bool {
+        match permission {
+            Permission::Execute | Permission::Run => *self != WorkspaceRole::Viewer,
+            Permission::Export | Permission::Edit => matches!(
+                self,
+                WorkspaceRole::Owner | WorkspaceRole::Admin | WorkspaceRole::Editor
+            ),
+            Permission::Purge
+            | Permission::ManageMembers
+            | Permission::ManageWorkspace
+            | Permission::ViewAudit => {
+                matches!(self, WorkspaceRole::Owner | WorkspaceRole::Admin)
+            }
+            Permission::TransferOwnership | Permission::DeleteWorkspace => {
+                *self == WorkspaceRole::Owner
+            }
+        }
+    }
+
+    // Names of the roles that have the permission, for filtering in SQL
+    pub fn names_with(permission: Permission) -> Vec<String> {
+        Self::ALL
+            .iter()
+            .filter(|role| role.can(permission))
+            .map(|role| role.as_str().to_string())
+            .collect()
+    }
+}
+
+impl FromStr for WorkspaceRole {
+    type Err = AppError;
+
+    fn from_str(role: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|candidate| candidate.as_str() == role)
+            .ok_or_else(|| AppError::InternalServerError(format!("Unknown role: {}", role)))
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct CreateWorkspaceDto {
+    #[validate(length(min = 1, message = "Name cannot be empty"))]
+    pub name: String,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Validate)]
+pub struct UpdateWorkspaceDto {
+    #[validate(length(min = 1, message = "Name cannot be empty"))]
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub require_2fa: Option<bool>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateMemberDto {
+    pub role: WorkspaceRole,
+}
+
+// The member who becomes the owner, the current owner stays on as an admin
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TransferOwnershipDto {
+    pub user_id: Uuid,
+}
+
+// Query string shared by the list endpoints that can be narrowed to one workspace
+#[derive(Debug, Deserialize)]
+pub struct WorkspaceQuery {
+    pub workspace_id: Option<Uuid>,
+}
+
+// The proxy of the workspace, its password is never shown
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WorkspaceProxyResponse {
+    pub workspace_id: Uuid,
+    pub proxy: Option<ProxyMode>,
+    pub server_proxy: Option<String>, // URL of the proxy used when the workspace has none
+}
+
+// A member of a workspace together with their account details
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct WorkspaceMember {
+    pub user_id: Uuid,
+    pub email: String,
+    pub name: Option<String>,
+    pub role: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WorkspaceResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub is_personal: bool,
+    pub require_2fa: bool,
+    pub role: String, // Role of the current user
+    pub created_at: NaiveDateTime,
+    pub updated_at: NaiveDateTime,
+}
+
+impl Workspace {
+    pub fn to_response(&self, role: WorkspaceRole) -> WorkspaceResponse {
+        WorkspaceResponse {
+            id: self.id,
+            name: self.name.clone(),
+            description: self.description.clone(),
+            is_personal: self.is_personal,
+            require_2fa: self.require_2fa,
+            role: role.as_str().to_string(),
+            created_at: self.created_at,
+            updated_at: self.updated_at,
+        }
+    }
+
+    pub fn proxy_mode(&self) -> Result<Option<ProxyMode>, AppError> {
+        self.proxy
+            .clone()
+            .map(serde_json::from_value)
+            .transpose()
+            .map_err(|e| AppError::InternalServerError(format!("Invalid stored proxy: {}", e)))
+    }
+
+    pub fn audit_summary(&self) -> Value {
+        json!({
+            "name": self.name,
+            "description": self.description,
+            "require_2fa": self.require_2fa,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL_PERMISSIONS: [Permission; 10] = [
+        Permission::Execute,
+        Permission::Run,
+        Permission::Export,
+        Permission::Edit,
+        Permission::Purge,
+        Permission::ManageMembers,
+        Permission::ManageWorkspace,
+        Permission::ViewAudit,
+        Permission::TransferOwnership,
+        Permission::DeleteWorkspace,
+    ];
+
+    fn granted(role: WorkspaceRole) -> Vec<Permission> {
+        ALL_PERMISSIONS
+            .into_iter()
+            .filter(|permission| role.can(*permission))
+            .collect()
+    }
+
+    #[test]
+    fn owner_can_do_everything() {
+        assert_eq!(granted(WorkspaceRole::Owner), ALL_PERMISSIONS.to_vec());
+    }
+
+    #[test]
+    fn admin_can_do_everything_but_give_away_or_delete_the_workspace() {
+        assert_eq!(
+            granted(WorkspaceRole::Admin),
+            vec![
+                Permission::Execute,
+                Permission::Run,
+                Permission::Export,
+                Permission::Edit,
+                Permission::Purge,
+                Permission::ManageMembers,
+                Permission::ManageWorkspace,
+                Permission::ViewAudit,
+            ]
+        );
+    }
+
+    #[test]
+    fn editor_can_edit_but_not_manage() {
+        assert_eq!(
+            granted(WorkspaceRole::Editor),
+            vec![
+                Permission::Execute,
+                Permission::Run,
+                Permission::Export,
+                Permission::Edit,
+            ]
+        );
+    }
+
+    #[test]
+    fn runner_can_run_but_not_edit_or_export() {
+        assert_eq!(
+            granted(WorkspaceRole::Runner),
+            vec![Permission::Execute, Permission::Run]
+        );
+    }
+
+    #[test]
+    fn viewer_can_only_read() {
+        assert!(granted(WorkspaceRole::Viewer).is_empty());
+    }
+
+    #[test]
+    fn roles_round_trip_through_their_names() {
+        for role in WorkspaceRole::ALL {
+            assert_eq!(role.as_str().parse::<WorkspaceRole>().unwrap(), role);
+        }
+        assert!("member".parse::<WorkspaceRole>().is_err());
+    }
+
+    #[test]
+    fn role_names_with_permission() {
+        assert_eq!(
+            WorkspaceRole::names_with(Permission::Purge),
+            vec!["owner".to_string(), "admin".to_string()]
+        );
+        assert_eq!(WorkspaceRole::names_with(Permission::Run).len(), 4);
+    }
+}
@@ -0,0 +1,25 @@
+use crate::app_middleware::{RateLimit, SessionAuth};
+use crate::handlers::{create_api_key, get_api_keys, revoke_api_key};
+use crate::utils::rate_limit::RateLimitScope;
+use actix_web::{
+    dev::{ServiceFactory, ServiceRequest, ServiceResponse},
+    web, Error, Scope,
+};
+
+// Keys are managed from a signed-in session, a key cannot create or revoke keys
+pub fn api_key_routes() -> Scope<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse,
+        Error = Error,
+        InitError = (),
+    >,
+> {
+    web::scope("/api-keys")
+        .wrap(RateLimit::new(RateLimitScope::Api))
+        .wrap(SessionAuth)
+        .route("", web::post().to(create_api_key))
+        .route("", web::get().to(get_api_keys))
+        .route("/{id}", web::delete().to(revoke_api_key))
+}
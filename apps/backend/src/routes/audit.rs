@@ -0,0 +1,23 @@
+use crate::app_middleware::{Auth, RateLimit};
+use crate::handlers::{get_audit_log, get_my_audit_log};
+use crate::utils::rate_limit::RateLimitScope;
+use actix_web::{
+    dev::{ServiceFactory, ServiceRequest, ServiceResponse},
+    web, Error, Scope,
+};
+
+pub fn audit_routes() -> Scope<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse,
+        Error = Error,
+        InitError = (),
+    >,
+> {
+    web::scope("/audit")
+        .wrap(RateLimit::new(RateLimitScope::Api))
+        .wrap(Auth)
+        .route("", web::get().to(get_audit_log))
+        .route("/me", web::get().to(get_my_audit_log))
+}
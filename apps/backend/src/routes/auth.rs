@@ -1,14 +1,103 @@
-use crate::app_middleware::Auth;
-use crate::handlers::{get_current_user, login, register};
-use actix_web::{web, Scope};
+use crate::app_middleware::{RateLimit, SessionAuth};
+use crate::handlers::{
+    change_password, delete_account, disable_two_factor, enable_two_factor, export_account,
+    get_current_user, get_jwks, login, login_two_factor, logout, logout_everywhere, oidc_callback,
+    oidc_login, refresh, regenerate_recovery_codes, register, request_password_reset,
+    resend_verification, reset_password, setup_two_factor, update_profile, verify_email,
+};
+use crate::utils::rate_limit::RateLimitScope;
+use actix_web::{
+    dev::{ServiceFactory, ServiceRequest, ServiceResponse},
+    web, Error, Scope,
+};
 
 pub fn auth_routes() -> Scope {
     web::scope("/auth")
-        .route("/register", web::post().to(register))
-        .route("/login", web::post().to(login))
+        // Endpoints that take credentials or tokens share a small budget per IP address,
+        // the signed in ones below count against the API budget of their user
+        .service(
+            web::resource("/register")
+                .wrap(RateLimit::new(RateLimitScope::Auth))
+                .route(web::post().to(register)),
+        )
+        .service(
+            web::resource("/login")
+                .wrap(RateLimit::new(RateLimitScope::Auth))
+                .route(web::post().to(login)),
+        )
+        .service(
+            web::resource("/login/2fa")
+                .wrap(RateLimit::new(RateLimitScope::Auth))
+                .route(web::post().to(login_two_factor)),
+        )
+        .service(
+            web::resource("/refresh")
+                .wrap(RateLimit::new(RateLimitScope::Auth))
+                .route(web::post().to(refresh)),
+        )
+        .service(
+            web::resource("/verify-email")
+                .wrap(RateLimit::new(RateLimitScope::Auth))
+                .route(web::post().to(verify_email)),
+        )
+        .service(
+            web::resource("/password-reset")
+                .wrap(RateLimit::new(RateLimitScope::Auth))
+                .route(web::post().to(request_password_reset)),
+        )
+        .service(
+            web::resource("/password-reset/confirm")
+                .wrap(RateLimit::new(RateLimitScope::Auth))
+                .route(web::post().to(reset_password)),
+        )
+        .service(
+            web::resource("/oidc/login")
+                .wrap(RateLimit::new(RateLimitScope::Auth))
+                .route(web::get().to(oidc_login)),
+        )
+        .service(
+            web::resource("/oidc/callback")
+                .wrap(RateLimit::new(RateLimitScope::Auth))
+                .route(web::get().to(oidc_callback)),
+        )
+        .service(
+            web::scope("/logout")
+                .wrap(RateLimit::new(RateLimitScope::Api))
+                .wrap(SessionAuth)
+                .route("", web::post().to(logout))
+                .route("/all", web::post().to(logout_everywhere)),
+        )
         .service(
             web::scope("/me")
-                .wrap(Auth)
-                .route("", web::get().to(get_current_user)),
+                .wrap(RateLimit::new(RateLimitScope::Api))
+                .wrap(SessionAuth)
+                .route("", web::get().to(get_current_user))
+                .route("", web::put().to(update_profile))
+                .route("", web::delete().to(delete_account))
+                .route("/password", web::put().to(change_password))
+                .route("/export", web::get().to(export_account))
+                .route("/verify-email", web::post().to(resend_verification))
+                .route("/2fa/setup", web::post().to(setup_two_factor))
+                .route("/2fa/enable", web::post().to(enable_two_factor))
+                .route("/2fa/disable", web::post().to(disable_two_factor))
+                .route(
+                    "/2fa/recovery-codes",
+                    web::post().to(regenerate_recovery_codes),
+                ),
         )
 }
+
+// Public keys for verifying access tokens outside of this service
+pub fn jwks_routes() -> Scope<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse,
+        Error = Error,
+        InitError = (),
+    >,
+> {
+    web::scope("/.well-known")
+        .wrap(RateLimit::new(RateLimitScope::Api))
+        .route("/jwks.json", web::get().to(get_jwks))
+}
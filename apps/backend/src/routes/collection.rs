@@ -1,7 +1,12 @@
-use crate::app_middleware::Auth;
+use crate::app_middleware::{Auth, RateLimit};
 use crate::handlers::{
-    create_collection, delete_collection, get_collection, get_collections, update_collection,
+    create_collection, create_share_link, delete_collection, duplicate_collection,
+    export_collection, favorite_collection, get_collection, get_collection_revisions,
+    get_collection_tags, get_collections, get_share_links, restore_collection_revision,
+    revoke_share_link, run_collection, set_collection_tags, unfavorite_collection,
+    update_collection,
 };
+use crate::utils::rate_limit::RateLimitScope;
 use actix_web::{
     dev::{ServiceFactory, ServiceRequest, ServiceResponse},
     web, Error, Scope,
@@ -17,10 +22,29 @@ pub fn collection_routes() -> Scope<
     >,
 > {
     web::scope("/collections")
+        .wrap(RateLimit::new(RateLimitScope::Api))
         .wrap(Auth)
         .route("", web::post().to(create_collection))
         .route("", web::get().to(get_collections))
         .route("/{id}", web::get().to(get_collection))
         .route("/{id}", web::put().to(update_collection))
         .route("/{id}", web::delete().to(delete_collection))
+        .route("/{id}/duplicate", web::post().to(duplicate_collection))
+        .route("/{id}/export", web::get().to(export_collection))
+        .route("/{id}/favorite", web::put().to(favorite_collection))
+        .route("/{id}/favorite", web::delete().to(unfavorite_collection))
+        .route("/{id}/revisions", web::get().to(get_collection_revisions))
+        .route(
+            "/{id}/revisions/{revision_id}/restore",
+            web::post().to(restore_collection_revision),
+        )
+        .route("/{id}/run", web::post().to(run_collection))
+        .route("/{id}/shares", web::post().to(create_share_link))
+        .route("/{id}/shares", web::get().to(get_share_links))
+        .route(
+            "/{id}/shares/{share_id}",
+            web::delete().to(revoke_share_link),
+        )
+        .route("/{id}/tags", web::get().to(get_collection_tags))
+        .route("/{id}/tags", web::put().to(set_collection_tags))
 }
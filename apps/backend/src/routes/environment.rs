@@ -0,0 +1,28 @@
+use crate::app_middleware::{Auth, RateLimit};
+use crate::handlers::{
+    create_environment, delete_environment, get_environment, get_environments, update_environment,
+};
+use crate::utils::rate_limit::RateLimitScope;
+use actix_web::{
+    dev::{ServiceFactory, ServiceRequest, ServiceResponse},
+    web, Error, Scope,
+};
+
+pub fn environment_routes() -> Scope<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse,
+        Error = Error,
+        InitError = (),
+    >,
+> {
+    web::scope("/environments")
+        .wrap(RateLimit::new(RateLimitScope::Api))
+        .wrap(Auth)
+        .route("", web::post().to(create_environment))
+        .route("", web::get().to(get_environments))
+        .route("/{id}", web::get().to(get_environment))
+        .route("/{id}", web::put().to(update_environment))
+        .route("/{id}", web::delete().to(delete_environment))
+}
@@ -0,0 +1,24 @@
+use crate::app_middleware::{Auth, RateLimit};
+use crate::handlers::{accept_invitation, decline_invitation, get_invitation};
+use crate::utils::rate_limit::RateLimitScope;
+use actix_web::{
+    dev::{ServiceFactory, ServiceRequest, ServiceResponse},
+    web, Error, Scope,
+};
+
+pub fn invitation_routes() -> Scope<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse,
+        Error = Error,
+        InitError = (),
+    >,
+> {
+    web::scope("/invitations")
+        .wrap(RateLimit::new(RateLimitScope::Api))
+        .wrap(Auth)
+        .route("/{token}", web::get().to(get_invitation))
+        .route("/{token}/accept", web::post().to(accept_invitation))
+        .route("/{token}/decline", web::post().to(decline_invitation))
+}
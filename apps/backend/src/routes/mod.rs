@@ -1,27 +1,46 @@
+pub mod api_key;
+pub mod audit;
 pub mod auth;
 pub mod collection;
+pub mod environment;
+pub mod favorite;
+pub mod invitation;
 pub mod request;
+pub mod search;
+pub mod share;
+pub mod tag;
+pub mod trash;
+pub mod workspace;
 
-pub use auth::auth_routes;
+pub use api_key::api_key_routes;
+pub use audit::audit_routes;
+pub use auth::{auth_routes, jwks_routes};
 pub use collection::collection_routes;
+pub use environment::environment_routes;
+pub use favorite::favorite_routes;
+pub use invitation::invitation_routes;
 pub use request::request_routes;
+pub use search::search_routes;
+pub use share::share_routes;
+pub use tag::tag_routes;
+pub use trash::trash_routes;
+pub use workspace::workspace_routes;
 
-use actix_web::{web, Scope};
+use actix_web::web;
 
 pub fn configure(cfg: &mut web::ServiceConfig) {
-    // Configure authentication routes directly here
-    cfg.service(
-        web::scope("/auth")
-            .route("/register", web::post().to(crate::handlers::register))
-            .route("/login", web::post().to(crate::handlers::login))
-            .service(
-                web::scope("/me")
-                    .wrap(crate::app_middleware::Auth) // Apply Auth middleware to this scope
-                    .route("", web::get().to(crate::handlers::get_current_user)),
-            ),
-    );
-
-    // Configure other routes (collection and request)
-    // cfg.configure(collection::configure)
-    //     .configure(request::configure);
+    cfg.service(api_key_routes())
+        .service(audit_routes())
+        .service(auth_routes())
+        .service(collection_routes())
+        .service(environment_routes())
+        .service(favorite_routes())
+        .service(invitation_routes())
+        .service(jwks_routes())
+        .service(request_routes())
+        .service(search_routes())
+        .service(share_routes())
+        .service(tag_routes())
+        .service(trash_routes())
+        .service(workspace_routes());
 }
@@ -1,7 +1,10 @@
-use crate::app_middleware::Auth;
+use crate::app_middleware::{Auth, RateLimit};
 use crate::handlers::{
-    create_request, delete_request, execute, get_request, get_requests, update_request,
+    create_request, delete_request, duplicate_request, execute, favorite_request,
+    get_effective_request, get_executions, get_request, get_request_revisions, get_request_tags,
+    get_requests, restore_request_revision, set_request_tags, unfavorite_request, update_request,
 };
+use crate::utils::rate_limit::RateLimitScope;
 use actix_web::{
     dev::{ServiceFactory, ServiceRequest, ServiceResponse},
     web, Error, Scope,
@@ -17,11 +20,28 @@ pub fn request_routes() -> Scope<
     >,
 > {
     web::scope("/requests")
+        .wrap(RateLimit::new(RateLimitScope::Api))
         .wrap(Auth)
         .route("", web::post().to(create_request))
         .route("", web::get().to(get_requests))
         .route("/{id}", web::get().to(get_request))
         .route("/{id}", web::put().to(update_request))
         .route("/{id}", web::delete().to(delete_request))
-        .route("/{id}/execute", web::post().to(execute))
+        .route("/{id}/duplicate", web::post().to(duplicate_request))
+        .route("/{id}/effective", web::get().to(get_effective_request))
+        .service(
+            web::resource("/{id}/execute")
+                .wrap(RateLimit::new(RateLimitScope::Execute))
+                .route(web::post().to(execute)),
+        )
+        .route("/{id}/executions", web::get().to(get_executions))
+        .route("/{id}/favorite", web::put().to(favorite_request))
+        .route("/{id}/favorite", web::delete().to(unfavorite_request))
+        .route("/{id}/revisions", web::get().to(get_request_revisions))
+        .route(
+            "/{id}/revisions/{revision_id}/restore",
+            web::post().to(restore_request_revision),
+        )
+        .route("/{id}/tags", web::get().to(get_request_tags))
+        .route("/{id}/tags", web::put().to(set_request_tags))
 }
@@ -0,0 +1,22 @@
+use crate::app_middleware::{Auth, RateLimit};
+use crate::handlers::search;
+use crate::utils::rate_limit::RateLimitScope;
+use actix_web::{
+    dev::{ServiceFactory, ServiceRequest, ServiceResponse},
+    web, Error, Scope,
+};
+
+pub fn search_routes() -> Scope<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse,
+        Error = Error,
+        InitError = (),
+    >,
+> {
+    web::scope("/search")
+        .wrap(RateLimit::new(RateLimitScope::Api))
+        .wrap(Auth)
+        .route("", web::get().to(search))
+}
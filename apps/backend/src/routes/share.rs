@@ -0,0 +1,22 @@
+use crate::app_middleware::RateLimit;
+use crate::handlers::get_shared_collection;
+use crate::utils::rate_limit::RateLimitScope;
+use actix_web::{
+    dev::{ServiceFactory, ServiceRequest, ServiceResponse},
+    web, Error, Scope,
+};
+
+// Read-only views opened by share links, reachable without an account
+pub fn share_routes() -> Scope<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse,
+        Error = Error,
+        InitError = (),
+    >,
+> {
+    web::scope("/shared")
+        .wrap(RateLimit::new(RateLimitScope::Api))
+        .route("/{token}", web::get().to(get_shared_collection))
+}
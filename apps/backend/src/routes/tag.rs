@@ -0,0 +1,25 @@
+use crate::app_middleware::{Auth, RateLimit};
+use crate::handlers::{create_tag, delete_tag, get_tags, update_tag};
+use crate::utils::rate_limit::RateLimitScope;
+use actix_web::{
+    dev::{ServiceFactory, ServiceRequest, ServiceResponse},
+    web, Error, Scope,
+};
+
+pub fn tag_routes() -> Scope<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse,
+        Error = Error,
+        InitError = (),
+    >,
+> {
+    web::scope("/tags")
+        .wrap(RateLimit::new(RateLimitScope::Api))
+        .wrap(Auth)
+        .route("", web::post().to(create_tag))
+        .route("", web::get().to(get_tags))
+        .route("/{id}", web::put().to(update_tag))
+        .route("/{id}", web::delete().to(delete_tag))
+}
@@ -0,0 +1,36 @@
+use crate::app_middleware::{Auth, RateLimit};
+use crate::handlers::{
+    empty_trash, get_trash, purge_trashed_collection, purge_trashed_request, restore_collection,
+    restore_request,
+};
+use crate::utils::rate_limit::RateLimitScope;
+use actix_web::{
+    dev::{ServiceFactory, ServiceRequest, ServiceResponse},
+    web, Error, Scope,
+};
+
+pub fn trash_routes() -> Scope<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse,
+        Error = Error,
+        InitError = (),
+    >,
+> {
+    web::scope("/trash")
+        .wrap(RateLimit::new(RateLimitScope::Api))
+        .wrap(Auth)
+        .route("", web::get().to(get_trash))
+        .route("", web::delete().to(empty_trash))
+        .route("/requests/{id}/restore", web::post().to(restore_request))
+        .route("/requests/{id}", web::delete().to(purge_trashed_request))
+        .route(
+            "/collections/{id}/restore",
+            web::post().to(restore_collection),
+        )
+        .route(
+            "/collections/{id}",
+            web::delete().to(purge_trashed_collection),
+        )
+}
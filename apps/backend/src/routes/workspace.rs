@@ -0,0 +1,105 @@
+use crate::app_middleware::{Auth, RateLimit, SessionAuth};
+use crate::handlers::{
+    create_client_certificate, create_invitation, create_secret, create_workspace,
+    delete_client_certificate, delete_secret, delete_workspace, delete_workspace_proxy,
+    get_client_certificates, get_invitations, get_members, get_secrets, get_workspace,
+    get_workspace_proxy, get_workspaces, remove_member, revoke_invitation, rotate_secret_key,
+    transfer_ownership, update_client_certificate, update_member, update_secret, update_workspace,
+    update_workspace_proxy,
+};
+use crate::utils::rate_limit::RateLimitScope;
+use actix_web::{
+    dev::{ServiceFactory, ServiceRequest, ServiceResponse},
+    guard, web, Error, Scope,
+};
+
+pub fn workspace_routes() -> Scope<
+    impl ServiceFactory<
+        ServiceRequest,
+        Config = (),
+        Response = ServiceResponse,
+        Error = Error,
+        InitError = (),
+    >,
+> {
+    // Running the workspace takes a signed in user, API keys only reach what is in it
+    web::scope("/workspaces")
+        .service(
+            web::resource("/{id}")
+                .guard(guard::Delete())
+                .wrap(RateLimit::new(RateLimitScope::Api))
+                .wrap(SessionAuth)
+                .route(web::delete().to(delete_workspace)),
+        )
+        .service(
+            web::resource("/{id}/members/{user_id}")
+                .wrap(RateLimit::new(RateLimitScope::Api))
+                .wrap(SessionAuth)
+                .route(web::put().to(update_member))
+                .route(web::delete().to(remove_member)),
+        )
+        .service(
+            web::resource("/{id}/transfer")
+                .wrap(RateLimit::new(RateLimitScope::Api))
+                .wrap(SessionAuth)
+                .route(web::post().to(transfer_ownership)),
+        )
+        .service(
+            web::resource("/{id}/proxy")
+                .guard(guard::Any(guard::Put()).or(guard::Delete()))
+                .wrap(RateLimit::new(RateLimitScope::Api))
+                .wrap(SessionAuth)
+                .route(web::put().to(update_workspace_proxy))
+                .route(web::delete().to(delete_workspace_proxy)),
+        )
+        .service(
+            web::resource("/{id}/invitations")
+                .wrap(RateLimit::new(RateLimitScope::Api))
+                .wrap(SessionAuth)
+                .route(web::post().to(create_invitation))
+                .route(web::get().to(get_invitations)),
+        )
+        .service(
+            web::resource("/{id}/invitations/{invitation_id}")
+                .wrap(RateLimit::new(RateLimitScope::Api))
+                .wrap(SessionAuth)
+                .route(web::delete().to(revoke_invitation)),
+        )
+        .service(
+            web::resource("/{id}/secrets/rotate-key")
+                .wrap(RateLimit::new(RateLimitScope::Api))
+                .wrap(SessionAuth)
+                .route(web::post().to(rotate_secret_key)),
+        )
+        .service(
+            web::scope("")
+                .wrap(RateLimit::new(RateLimitScope::Api))
+                .wrap(Auth)
+                .route("", web::post().to(create_workspace))
+                .route("", web::get().to(get_workspaces))
+                .route("/{id}", web::get().to(get_workspace))
+                .route("/{id}", web::put().to(update_workspace))
+                .route("/{id}/members", web::get().to(get_members))
+                .route("/{id}/proxy", web::get().to(get_workspace_proxy))
+                .route("/{id}/secrets", web::get().to(get_secrets))
+                .route("/{id}/secrets", web::post().to(create_secret))
+                .route("/{id}/secrets/{secret_id}", web::put().to(update_secret))
+                .route("/{id}/secrets/{secret_id}", web::delete().to(delete_secret))
+                .route(
+                    "/{id}/client-certificates",
+                    web::get().to(get_client_certificates),
+                )
+                .route(
+                    "/{id}/client-certificates",
+                    web::post().to(create_client_certificate),
+                )
+                .route(
+                    "/{id}/client-certificates/{certificate_id}",
+                    web::put().to(update_client_certificate),
+                )
+                .route(
+                    "/{id}/client-certificates/{certificate_id}",
+                    web::delete().to(delete_client_certificate),
+                ),
+        )
+}
@@ -0,0 +1,313 @@
+use chrono::Utc;
+use serde_json::json;
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::models::{
+    AccountExport, Collection, CollectionExport, Environment, ExportedEnvironment,
+    ExportedMembership, ExportedRequest, ExportedSecret, ExportedUser, ExportedWorkspace,
+    OwnedWorkspaces, Request, User, Workspace, WorkspaceRole, EXPORT_FORMAT_VERSION,
+};
+use crate::utils::audit::{record_audit, AuditEntry, RequestOrigin};
+
+// Everything the user would lose with the account, items in the trash are left out
+pub async fn build_account_export(
+    conn: &mut PgConnection,
+    user: &User,
+) -> Result<AccountExport, AppError> {
+    let owned = sqlx::query_as!(
+        Workspace,
+        r#"
+        SELECT w.* FROM workspaces w
+        JOIN workspace_members m ON m.workspace_id = w.id
+        WHERE m.user_id = $1 AND m.role = $2
+        ORDER BY w.is_personal DESC, w.created_at ASC
+        "#,
+        user.id,
+        WorkspaceRole::Owner.as_str()
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let mut workspaces = Vec::with_capacity(owned.len());
+    for workspace in owned {
+        workspaces.push(export_workspace(conn, workspace).await?);
+    }
+
+    let memberships = sqlx::query!(
+        r#"
+        SELECT w.name, m.role FROM workspaces w
+        JOIN workspace_members m ON m.workspace_id = w.id
+        WHERE m.user_id = $1 AND m.role <> $2
+        ORDER BY w.name ASC
+        "#,
+        user.id,
+        WorkspaceRole::Owner.as_str()
+    )
+    .fetch_all(&mut *conn)
+    .await?
+    .into_iter()
+    .map(|member| ExportedMembership {
+        workspace: member.name,
+        role: member.role,
+    })
+    .collect();
+
+    Ok(AccountExport {
+        version: EXPORT_FORMAT_VERSION,
+        exported_at: Utc::now().naive_utc(),
+        user: ExportedUser {
+            email: user.email.clone(),
+            name: user.name.clone(),
+            created_at: user.created_at,
+        },
+        workspaces,
+        memberships,
+    })
+}
+
+async fn export_workspace(
+    conn: &mut PgConnection,
+    workspace: Workspace,
+) -> Result<ExportedWorkspace, AppError> {
+    let collections = sqlx::query_as!(
+        Collection,
+        r#"
+        SELECT * FROM collections
+        WHERE workspace_id = $1 AND deleted_at IS NULL
+        ORDER BY created_at ASC
+        "#,
+        workspace.id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let mut collection_exports = Vec::with_capacity(collections.len());
+    for collection in &collections {
+        let requests = sqlx::query_as!(
+            Request,
+            r#"
+            SELECT * FROM requests
+            WHERE collection_id = $1 AND deleted_at IS NULL
+            ORDER BY created_at ASC
+            "#,
+            collection.id
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        collection_exports.push(CollectionExport::new(collection, &requests));
+    }
+
+    // Requests that are not in any collection of the workspace stand on their own
+    let loose_requests = sqlx::query_as!(
+        Request,
+        r#"
+        SELECT * FROM requests r
+        WHERE r.workspace_id = $1 AND r.deleted_at IS NULL
+          AND NOT EXISTS (
+              SELECT 1 FROM collections c
+              WHERE c.id = r.collection_id AND c.workspace_id = $1 AND c.deleted_at IS NULL
+          )
+        ORDER BY r.created_at ASC
+        "#,
+        workspace.id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let environments = sqlx::query_as!(
+        Environment,
+        "SELECT * FROM environments WHERE workspace_id = $1 ORDER BY name ASC",
+        workspace.id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    let secrets = sqlx::query_as!(
+        ExportedSecret,
+        "SELECT name, description FROM secrets WHERE workspace_id = $1 ORDER BY name ASC",
+        workspace.id
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    Ok(ExportedWorkspace {
+        name: workspace.name,
+        description: workspace.description,
+        is_personal: workspace.is_personal,
+        collections: collection_exports,
+        requests: loose_requests.iter().map(ExportedRequest::new).collect(),
+        environments: environments
+            .into_iter()
+            .map(|environment| ExportedEnvironment {
+                name: environment.name,
+                variables: environment.variables,
+            })
+            .collect(),
+        secrets,
+    })
+}
+
+// Hand over or delete the workspaces the user owns, the personal one always goes
+pub async fn release_workspaces(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    owned_workspaces: OwnedWorkspaces,
+    origin: &RequestOrigin,
+) -> Result<(), AppError> {
+    let owned = sqlx::query_as!(
+        Workspace,
+        r#"
+        SELECT w.* FROM workspaces w
+        JOIN workspace_members m ON m.workspace_id = w.id
+        WHERE m.user_id = $1 AND m.role = $2
+        "#,
+        user_id,
+        WorkspaceRole::Owner.as_str()
+    )
+    .fetch_all(&mut *conn)
+    .await?;
+
+    for workspace in owned {
+        let successor = match owned_workspaces {
+            OwnedWorkspaces::Transfer if !workspace.is_personal => {
+                find_successor(conn, &workspace, user_id).await?
+            }
+            _ => None,
+        };
+
+        let Some(successor) = successor else {
+            // Collections, requests, environments and tags go with the workspace
+            sqlx::query!("DELETE FROM workspaces WHERE id = $1", workspace.id)
+                .execute(&mut *conn)
+                .await?;
+
+            record_audit(
+                &mut *conn,
+                origin,
+                AuditEntry {
+                    workspace_id: Some(workspace.id),
+                    actor_id: Some(user_id),
+                    action: "workspace.deleted",
+                    target_id: Some(workspace.id),
+                    details: Some(json!({ "reason": "account_deleted" })),
+                    before: Some(workspace.audit_summary()),
+                    ..Default::default()
+                },
+            )
+            .await?;
+            continue;
+        };
+
+        sqlx::query!(
+            "UPDATE workspace_members SET role = $3 WHERE workspace_id = $1 AND user_id = $2",
+            workspace.id,
+            successor.user_id,
+            WorkspaceRole::Owner.as_str()
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        record_audit(
+            &mut *conn,
+            origin,
+            AuditEntry {
+                workspace_id: Some(workspace.id),
+                actor_id: Some(user_id),
+                action: "workspace.ownership_transferred",
+                target_id: Some(successor.user_id),
+                details: Some(json!({
+                    "previous_role": successor.role,
+                    "reason": "account_deleted",
+                })),
+                ..Default::default()
+            },
+        )
+        .await?;
+    }
+
+    // What the user created in the remaining workspaces now belongs to their owners
+    sqlx::query!(
+        r#"
+        UPDATE collections c SET user_id = o.user_id
+        FROM workspace_members o
+        WHERE o.workspace_id = c.workspace_id AND o.role = $2 AND c.user_id = $1
+        "#,
+        user_id,
+        WorkspaceRole::Owner.as_str()
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE requests r SET user_id = o.user_id
+        FROM workspace_members o
+        WHERE o.workspace_id = r.workspace_id AND o.role = $2 AND r.user_id = $1
+        "#,
+        user_id,
+        WorkspaceRole::Owner.as_str()
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE environments e SET user_id = o.user_id
+        FROM workspace_members o
+        WHERE o.workspace_id = e.workspace_id AND o.role = $2 AND e.user_id = $1
+        "#,
+        user_id,
+        WorkspaceRole::Owner.as_str()
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE tags t SET user_id = o.user_id
+        FROM workspace_members o
+        WHERE o.workspace_id = t.workspace_id AND o.role = $2 AND t.user_id = $1
+        "#,
+        user_id,
+        WorkspaceRole::Owner.as_str()
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+struct Successor {
+    user_id: Uuid,
+    role: String,
+}
+
+// The member with the highest role who joined first, with 2FA when the workspace requires it
+async fn find_successor(
+    conn: &mut PgConnection,
+    workspace: &Workspace,
+    user_id: Uuid,
+) -> Result<Option<Successor>, AppError> {
+    let successor = sqlx::query_as!(
+        Successor,
+        r#"
+        SELECT m.user_id, m.role FROM workspace_members m
+        JOIN users u ON u.id = m.user_id
+        WHERE m.workspace_id = $1 AND m.user_id <> $2
+          AND (NOT $3 OR u.totp_enabled_at IS NOT NULL)
+        ORDER BY array_position($4::TEXT[], m.role::TEXT) ASC, m.created_at ASC
+        LIMIT 1
+        "#,
+        workspace.id,
+        user_id,
+        workspace.require_2fa,
+        &WorkspaceRole::ALL.map(|role| role.as_str().to_string())
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    Ok(successor)
+}
@@ -0,0 +1,54 @@
+use actix_web::http::Method;
+use chrono::Utc;
+use sqlx::PgExecutor;
+
+use crate::error::AppError;
+use crate::models::{ApiKey, ApiKeyScope};
+use crate::utils::token::{generate_token, hash_token};
+
+// Marks keys of this service, so leaked ones are easy to spot
+const KEY_PREFIX: &str = "ep_";
+// Characters of the key kept in the clear to tell keys apart
+const DISPLAY_PREFIX_LEN: usize = 11;
+
+// New random key together with the part of it that is shown in lists
+pub fn generate_api_key() -> (String, String) {
+    let key = format!("{}{}", KEY_PREFIX, generate_token());
+    let prefix = key[..DISPLAY_PREFIX_LEN].to_string();
+    (key, prefix)
+}
+
+// Scope a request needs, reading only needs `read` and sending requests needs `execute`
+pub fn required_scope(method: &Method, path: &str) -> ApiKeyScope {
+    let path = path.trim_end_matches('/');
+
+    if matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS) {
+        ApiKeyScope::Read
+    } else if *method == Method::POST && (path.ends_with("/execute") || path.ends_with("/run")) {
+        ApiKeyScope::Execute
+    } else {
+        ApiKeyScope::Write
+    }
+}
+
+// Find the active key and note its use, unknown, revoked and expired keys are rejected the same way
+pub async fn authenticate_api_key<'c>(
+    executor: impl PgExecutor<'c>,
+    key: &str,
+) -> Result<ApiKey, AppError> {
+    let now = Utc::now().naive_utc();
+
+    sqlx::query_as!(
+        ApiKey,
+        r#"
+        UPDATE api_keys SET last_used_at = $2
+        WHERE key_hash = $1 AND revoked_at IS NULL AND (expires_at IS NULL OR expires_at > $2)
+        RETURNING *
+        "#,
+        hash_token(key),
+        now
+    )
+    .fetch_optional(executor)
+    .await?
+    .ok_or_else(|| AppError::AuthError("Invalid or expired API key".to_string()))
+}
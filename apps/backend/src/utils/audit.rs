@@ -0,0 +1,88 @@
+use actix_web::{dev::Payload, http::header, web, FromRequest, HttpMessage, HttpRequest};
+use futures::future::{ready, Ready};
+use serde_json::Value;
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use crate::app_middleware::ApiKeyId;
+use crate::config::Config;
+use crate::error::AppError;
+use crate::utils::rate_limit::client_ip;
+
+// Where an HTTP request came from, stored with the audit entries it causes
+#[derive(Debug, Clone, Default)]
+pub struct RequestOrigin {
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    pub api_key_id: Option<Uuid>,
+}
+
+impl FromRequest for RequestOrigin {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        // Forwarded addresses are only believed when the server is told it sits behind a proxy
+        let trust_forwarded_for = req
+            .app_data::<web::Data<Config>>()
+            .is_some_and(|config| config.rate_limit.trust_forwarded_for);
+        let ip_address = client_ip(req, trust_forwarded_for);
+        let user_agent = req
+            .headers()
+            .get(header::USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string());
+        let api_key_id = req.extensions().get::<ApiKeyId>().map(|ApiKeyId(id)| *id);
+
+        ready(Ok(RequestOrigin {
+            ip_address,
+            user_agent,
+            api_key_id,
+        }))
+    }
+}
+
+// One audited action, `before` and `after` summarize the state of the target around it
+#[derive(Debug, Default)]
+pub struct AuditEntry<'a> {
+    pub workspace_id: Option<Uuid>,
+    pub actor_id: Option<Uuid>, // Missing for failed logins
+    pub action: &'a str,
+    pub target_id: Option<Uuid>,
+    pub details: Option<Value>,
+    pub before: Option<Value>,
+    pub after: Option<Value>,
+}
+
+// Append an entry to the audit log
+pub async fn record_audit<'c>(
+    executor: impl PgExecutor<'c>,
+    origin: &RequestOrigin,
+    entry: AuditEntry<'_>,
+) -> Result<(), AppError> {
+    sqlx::query!(
+        r#"
+        INSERT INTO audit_log (
+            id, workspace_id, actor_id, action, target_id, details,
+            ip_address, user_agent, api_key_id, before, after, created_at
+        )
+        VALUES ($1, $2, $3, $4, $5, COALESCE($6, '{}'::JSONB), $7, $8, $9, $10, $11, $12)
+        "#,
+        Uuid::new_v4(),
+        entry.workspace_id,
+        entry.actor_id,
+        entry.action,
+        entry.target_id,
+        entry.details,
+        origin.ip_address,
+        origin.user_agent,
+        origin.api_key_id,
+        entry.before,
+        entry.after,
+        chrono::Utc::now().naive_utc()
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
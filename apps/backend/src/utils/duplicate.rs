@@ -0,0 +1,141 @@
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::models::{Collection, EntityType, Request};
+use crate::utils::revision::record_revision;
+
+// Insert a copy of a request and start its history
+pub async fn copy_request(
+    conn: &mut PgConnection,
+    source: &Request,
+    name: &str,
+    collection_id: Option<Uuid>,
+    workspace_id: Uuid,
+    user_id: Uuid,
+) -> Result<Request, AppError> {
+    let now = chrono::Utc::now().naive_utc();
+
+    let request = sqlx::query_as!(
+        Request,
+        r#"
+        INSERT INTO requests (
+            id, name, description, url, method, headers, body, params,
+            collection_id, user_id, created_at, updated_at, auth, settings, workspace_id
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15)
+        RETURNING id, name, description, url, method, headers, body, params,
+                  collection_id, user_id, created_at, updated_at, auth, settings,
+                  deleted_at, workspace_id
+        "#,
+        Uuid::new_v4(),
+        name,
+        source.description,
+        source.url,
+        source.method,
+        source.headers,
+        source.body,
+        source.params,
+        collection_id,
+        user_id,
+        now,
+        now,
+        source.auth,
+        source.settings,
+        workspace_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    // The copy keeps the tags of the source that exist in its workspace
+    sqlx::query!(
+        r#"
+        INSERT INTO request_tags (request_id, tag_id)
+        SELECT $1, rt.tag_id FROM request_tags rt
+        JOIN tags t ON t.id = rt.tag_id
+        WHERE rt.request_id = $2 AND t.workspace_id = $3
+        "#,
+        request.id,
+        source.id,
+        workspace_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    record_revision(
+        &mut *conn,
+        EntityType::Request,
+        request.id,
+        &request,
+        user_id,
+    )
+    .await?;
+
+    Ok(request)
+}
+
+// Insert a copy of a collection without its requests into the same workspace and start its history
+pub async fn copy_collection(
+    conn: &mut PgConnection,
+    source: &Collection,
+    name: &str,
+    user_id: Uuid,
+) -> Result<Collection, AppError> {
+    let now = chrono::Utc::now().naive_utc();
+
+    let collection = sqlx::query_as!(
+        Collection,
+        r#"
+        INSERT INTO collections (
+            id, name, description, user_id, created_at, updated_at,
+            base_url, default_headers, default_params, auth, settings, workspace_id
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+        RETURNING id, name, description, user_id, created_at, updated_at,
+                  base_url, default_headers, default_params, auth, settings, deleted_at,
+                  workspace_id
+        "#,
+        Uuid::new_v4(),
+        name,
+        source.description,
+        user_id,
+        now,
+        now,
+        source.base_url,
+        source.default_headers,
+        source.default_params,
+        source.auth,
+        source.settings,
+        source.workspace_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    // The copy keeps the tags of the source
+    sqlx::query!(
+        r#"
+        INSERT INTO collection_tags (collection_id, tag_id)
+        SELECT $1, tag_id FROM collection_tags WHERE collection_id = $2
+        "#,
+        collection.id,
+        source.id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    record_revision(
+        &mut *conn,
+        EntityType::Collection,
+        collection.id,
+        &collection,
+        user_id,
+    )
+    .await?;
+
+    Ok(collection)
+}
+
+// Name used for a copy when the caller does not pick one
+pub fn copy_name(name: &str) -> String {
+    format!("{} (copy)", name)
+}
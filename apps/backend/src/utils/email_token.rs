@@ -0,0 +1,79 @@
+use chrono::{Duration, Utc};
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::models::{EmailToken, EmailTokenPurpose};
+use crate::utils::token::{generate_token, hash_token};
+
+// Issue a token for the address, replacing the unused ones of the same purpose
+pub async fn create_email_token(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    email: &str,
+    purpose: EmailTokenPurpose,
+    expires_in: Duration,
+) -> Result<String, AppError> {
+    let now = Utc::now().naive_utc();
+
+    sqlx::query!(
+        r#"
+        DELETE FROM email_tokens
+        WHERE user_id = $1 AND purpose = $2 AND used_at IS NULL
+        "#,
+        user_id,
+        purpose.as_str()
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    // Only the hash of the token is kept
+    let token = generate_token();
+    sqlx::query!(
+        r#"
+        INSERT INTO email_tokens (id, user_id, purpose, email, token_hash, expires_at, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        "#,
+        Uuid::new_v4(),
+        user_id,
+        purpose.as_str(),
+        email,
+        hash_token(&token),
+        now + expires_in,
+        now
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(token)
+}
+
+// Mark an open token as used, unknown, used and expired tokens are all rejected the same way
+pub async fn consume_email_token(
+    conn: &mut PgConnection,
+    token: &str,
+    purpose: EmailTokenPurpose,
+) -> Result<EmailToken, AppError> {
+    let now = Utc::now().naive_utc();
+
+    sqlx::query_as!(
+        EmailToken,
+        r#"
+        UPDATE email_tokens
+        SET used_at = $3
+        WHERE token_hash = $1 AND purpose = $2 AND used_at IS NULL AND expires_at > $3
+        RETURNING *
+        "#,
+        hash_token(token),
+        purpose.as_str(),
+        now
+    )
+    .fetch_optional(&mut *conn)
+    .await?
+    .ok_or_else(|| AppError::BadRequestError("This link is invalid or has expired".to_string()))
+}
+
+// Link in an email that opens the app with the token
+pub fn email_link(app_url: &str, path: &str, token: &str) -> String {
+    format!("{}/{}?token={}", app_url.trim_end_matches('/'), path, token)
+}
@@ -0,0 +1,125 @@
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+use uuid::Uuid;
+
+use crate::config::ExecutionConfig;
+use crate::error::AppError;
+use crate::models::{ProxyMode, ResolvedRequest};
+use crate::utils::client_certificate::find_identity;
+use crate::utils::http::{execute_request, HttpRequestResult};
+use crate::utils::outbound::OutboundPolicy;
+use crate::utils::proxy::SelectedProxy;
+use crate::utils::secrets::{
+    fill_secrets, mask_secrets, mask_secrets_in_value, secret_names, SecretVault,
+};
+
+// Send a resolved request and store the response so it shows up in the history and in search
+pub async fn execute_and_record(
+    pool: &PgPool,
+    limits: &ExecutionConfig,
+    policy: &Arc<OutboundPolicy>,
+    vault: Option<&SecretVault>,
+    resolved: &ResolvedRequest,
+    user_id: Uuid,
+) -> Result<HttpRequestResult, AppError> {
+    // Secrets are decrypted here and nowhere else, and only for the request being sent
+    let names = secret_names(resolved);
+    let secrets = match vault {
+        _ if names.is_empty() => HashMap::new(),
+        Some(vault) => {
+            let mut conn = pool.acquire().await?;
+            vault
+                .reveal(&mut conn, resolved.workspace_id, &names)
+                .await?
+        }
+        None => {
+            return Err(AppError::BadRequestError(
+                "This request uses secrets but secret storage is not configured".to_string(),
+            ))
+        }
+    };
+    let outgoing = fill_secrets(resolved, &secrets);
+
+    // The client certificate bound to the host, if the workspace has one for it
+    let identity = {
+        let mut conn = pool.acquire().await?;
+        find_identity(&mut conn, vault, resolved.workspace_id, &outgoing.url).await?
+    };
+
+    // The proxy of the request or its collection, then the one of the workspace, then the server
+    let workspace_proxy = sqlx::query_scalar!(
+        "SELECT proxy FROM workspaces WHERE id = $1",
+        resolved.workspace_id
+    )
+    .fetch_one(pool)
+    .await?
+    .map(serde_json::from_value::<ProxyMode>)
+    .transpose()
+    .map_err(|e| AppError::InternalServerError(format!("Invalid stored proxy: {}", e)))?;
+    let proxy = SelectedProxy::select(
+        resolved.settings.proxy.as_ref(),
+        workspace_proxy.as_ref(),
+        policy.server_proxy(),
+    );
+
+    // Execute the HTTP request
+    let started_at = Instant::now();
+    let result = execute_request(&outgoing, limits, policy, proxy.as_ref(), identity.as_ref())
+        .await
+        .map_err(|e| match e {
+            AppError::BadRequestError(message) => {
+                AppError::BadRequestError(mask_secrets(&message, &secrets))
+            }
+            AppError::OutboundPolicyError(message) => {
+                AppError::OutboundPolicyError(mask_secrets(&message, &secrets))
+            }
+            e => e,
+        })?;
+    let duration_ms = started_at.elapsed().as_millis() as i64;
+
+    // Secret values echoed back by the server are not kept or shown either
+    let result = HttpRequestResult {
+        status: result.status,
+        headers: result
+            .headers
+            .into_iter()
+            .map(|(name, value)| (name, mask_secrets(&value, &secrets)))
+            .collect(),
+        body: mask_secrets_in_value(&result.body, &secrets),
+        proxy: result.proxy,
+        client_certificate: result.client_certificate,
+    };
+
+    let headers = serde_json::to_value(&result.headers)
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+    let proxy = result
+        .proxy
+        .as_ref()
+        .map(serde_json::to_value)
+        .transpose()
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+    sqlx::query!(
+        r#"
+        INSERT INTO executions
+            (id, request_id, user_id, status, headers, body, duration_ms, executed_at, proxy,
+             client_certificate_id)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+        "#,
+        Uuid::new_v4(),
+        resolved.request_id,
+        user_id,
+        result.status as i32,
+        headers,
+        result.body,
+        duration_ms,
+        chrono::Utc::now().naive_utc(),
+        proxy,
+        result.client_certificate
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(result)
+}
@@ -1,44 +1,131 @@
-use crate::models::Request;
-use reqwest::Client;
+use crate::config::ExecutionConfig;
+use crate::error::AppError;
+use crate::models::{ApiKeyLocation, AuthConfig, ResolvedRequest, UsedProxy};
+use crate::utils::client_certificate::ClientIdentity;
+use crate::utils::outbound::{find_violation, OutboundPolicy};
+use crate::utils::proxy::SelectedProxy;
+use reqwest::header::{self, HeaderName};
+use reqwest::{redirect, Client, Method, RequestBuilder, Response, StatusCode};
 use serde_json::Value;
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use url::Url;
+use uuid::Uuid;
+
+// Headers that never follow a redirect to another host, the same ones reqwest drops
+const SENSITIVE_HEADERS: [HeaderName; 4] = [
+    header::AUTHORIZATION,
+    header::COOKIE,
+    header::PROXY_AUTHORIZATION,
+    header::WWW_AUTHENTICATE,
+];
 
 #[derive(Debug, serde::Serialize)]
 pub struct HttpRequestResult {
     pub status: u16,
     pub headers: HashMap<String, String>,
     pub body: Value,
+    pub proxy: Option<UsedProxy>, // Missing when the request was sent directly
+    pub client_certificate: Option<Uuid>, // The certificate presented for mutual TLS
 }
 
-pub async fn execute_request(req: &Request) -> Result<HttpRequestResult, reqwest::Error> {
-    let client = Client::new();
+// What changes about the request from one redirect to the next
+struct Hop {
+    url: Url,
+    method: Method,
+    with_body: bool,
+    with_credentials: bool, // Auth and sensitive headers only go to the host they were meant for
+}
 
-    let mut request_builder = client.request(req.method.parse().unwrap(), &req.url);
+pub async fn execute_request(
+    req: &ResolvedRequest,
+    limits: &ExecutionConfig,
+    policy: &Arc<OutboundPolicy>,
+    proxy: Option<&SelectedProxy>,
+    identity: Option<&ClientIdentity>,
+) -> Result<HttpRequestResult, AppError> {
+    let settings = &req.settings;
 
-    // Set headers
-    if let Value::Object(map) = &req.headers {
-        for (key, value) in map {
-            if let Some(val) = value.as_str() {
-                request_builder = request_builder.header(key, val);
-            } else {
-                eprintln!("Header value for '{}' is not a string: {:?}", key, value);
-            }
+    let url = Url::parse(&req.url)
+        .map_err(|e| AppError::BadRequestError(format!("Failed to execute request: {}", e)))?;
+    let method = req
+        .method
+        .parse::<Method>()
+        .map_err(|_| AppError::BadRequestError(format!("Invalid method: {}", req.method)))?;
+
+    // Settings of the request can lower the server limits but never raise them
+    let max_redirects = settings.follow_redirects.unwrap_or(true).then(|| {
+        settings
+            .max_redirects
+            .unwrap_or(limits.max_redirects)
+            .min(limits.max_redirects)
+    });
+    let timeout_ms = settings
+        .timeout_ms
+        .unwrap_or(limits.timeout_ms)
+        .min(limits.max_timeout_ms);
+
+    // Redirects are followed here rather than by reqwest, so every target passes the policy
+    // the same way the first one did, whether it goes out directly or through the proxy
+    let mut hop = Hop {
+        url,
+        method,
+        with_body: true,
+        with_credentials: true,
+    };
+    let mut redirects = 0;
+    let mut presented = None;
+    let (mut response, used_proxy) = loop {
+        let hop_proxy = proxy.filter(|proxy| proxy.used_for(&hop.url).is_some());
+        match hop_proxy {
+            Some(_) => policy.check_target(&hop.url).await?,
+            None => policy.check_url(&hop.url)?,
         }
-    }
 
-    // Set query parameters
-    if let Some(params_json) = req.params.as_ref() {
-        if let Some(map) = params_json.as_object() {
-            request_builder = request_builder.query(&map);
+        // The certificate is only of use to a TLS connection with the target, and an HTTPS
+        // proxy would be offered it as well
+        let hop_identity = identity.filter(|_| hop.url.scheme() == "https");
+        if hop_identity.is_some() && hop_proxy.is_some_and(|proxy| proxy.uses_tls()) {
+            return Err(AppError::BadRequestError(
+                "Client certificates cannot be sent through an HTTPS proxy".to_string(),
+            ));
         }
-    }
+        presented = presented.or(hop_identity.map(|identity| identity.id));
 
-    // Set body
-    if let Some(body_json) = req.body.as_ref() {
-        request_builder = request_builder.json(body_json);
-    }
+        let client = build_client(
+            policy,
+            hop_proxy,
+            hop_identity,
+            settings.verify_tls,
+            timeout_ms,
+        )
+        .await?;
+        let response = build_request(&client, req, &hop, redirects == 0)
+            .send()
+            .await
+            .map_err(request_error)?;
+
+        // The certificate is only presented to the hosts it is bound to
+        let next = redirect_target(&response, &hop.url)
+            .filter(|next| identity.is_none_or(|identity| identity.binds(next)));
+        let (Some(next), Some(max_redirects)) = (next, max_redirects) else {
+            break (
+                response,
+                hop_proxy.and_then(|proxy| proxy.used_for(&hop.url)),
+            );
+        };
+        if redirects == max_redirects {
+            return Err(AppError::BadRequestError(format!(
+                "Failed to execute request: more than {} redirects",
+                max_redirects
+            )));
+        }
+
+        hop = next_hop(hop, response.status(), next);
+        redirects += 1;
+    };
 
-    let response = request_builder.send().await?;
     let status = response.status().as_u16();
     let headers = response
         .headers()
@@ -46,14 +133,461 @@ pub async fn execute_request(req: &Request) -> Result<HttpRequestResult, reqwest
         .map(|(k, v)| (k.to_string(), v.to_str().unwrap_or("").to_string()))
         .collect();
 
-    let body = response
-        .json::<Value>()
-        .await
+    // Stop reading as soon as the body goes over the limit, whatever length it claims
+    let max_bytes = limits.max_response_bytes;
+    if response
+        .content_length()
+        .is_some_and(|length| length > max_bytes)
+    {
+        return Err(response_too_large(max_bytes));
+    }
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(request_error)? {
+        if (bytes.len() + chunk.len()) as u64 > max_bytes {
+            return Err(response_too_large(max_bytes));
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+
+    let body = serde_json::from_slice::<Value>(&bytes)
         .unwrap_or_else(|_| serde_json::json!({ "raw_body": "Unable to parse JSON body" }));
 
     Ok(HttpRequestResult {
         status,
         headers,
         body,
+        proxy: used_proxy,
+        client_certificate: presented,
     })
 }
+
+// A client for a single hop. Going through a proxy, the only name reqwest resolves is the
+// proxy itself, so the policy resolver is left out for the proxy of the server alone
+async fn build_client(
+    policy: &Arc<OutboundPolicy>,
+    proxy: Option<&SelectedProxy>,
+    identity: Option<&ClientIdentity>,
+    verify_tls: Option<bool>,
+    timeout_ms: u64,
+) -> Result<Client, AppError> {
+    // Only the proxy picked here is used, never one from the environment of the server
+    let mut client_builder = Client::builder()
+        .redirect(redirect::Policy::none())
+        .timeout(Duration::from_millis(timeout_ms))
+        .danger_accept_invalid_certs(!verify_tls.unwrap_or(true))
+        .no_proxy();
+    match proxy {
+        Some(proxy) => {
+            client_builder = client_builder.proxy(proxy.build(policy).await?);
+            if !proxy.is_trusted() {
+                client_builder = client_builder.dns_resolver(policy.resolver());
+            }
+        }
+        None => client_builder = client_builder.dns_resolver(policy.resolver()),
+    }
+    // Identities are read from PEM, which only the rustls backend supports
+    if let Some(identity) = identity {
+        client_builder = client_builder
+            .use_rustls_tls()
+            .identity(identity.identity.clone());
+    }
+
+    client_builder.build().map_err(request_error)
+}
+
+fn build_request(client: &Client, req: &ResolvedRequest, hop: &Hop, first: bool) -> RequestBuilder {
+    let mut request_builder = client.request(hop.method.clone(), hop.url.clone());
+
+    // Set headers
+    for (key, value) in &req.headers {
+        let dropped = (!hop.with_credentials
+            && SENSITIVE_HEADERS
+                .iter()
+                .any(|name| name.as_str().eq_ignore_ascii_case(key)))
+            || (!hop.with_body && key.to_ascii_lowercase().starts_with("content-"));
+        if dropped {
+            continue;
+        }
+        if let Some(val) = value.as_str() {
+            request_builder = request_builder.header(key, val);
+        } else {
+            eprintln!("Header value for '{}' is not a string: {:?}", key, value);
+        }
+    }
+
+    // Set query parameters, a redirect target carries its own
+    if first && !req.params.is_empty() {
+        request_builder = request_builder.query(&req.params);
+    }
+
+    // Set auth
+    let auth = req.auth.as_ref().filter(|_| hop.with_credentials);
+    match auth {
+        Some(AuthConfig::Bearer { token }) => {
+            request_builder = request_builder.bearer_auth(token);
+        }
+        Some(AuthConfig::Basic { username, password }) => {
+            request_builder = request_builder.basic_auth(username, password.as_ref());
+        }
+        Some(AuthConfig::ApiKey {
+            key,
+            value,
+            location,
+        }) => {
+            request_builder = match location {
+                ApiKeyLocation::Header => request_builder.header(key, value),
+                ApiKeyLocation::Query if first => request_builder.query(&[(key, value)]),
+                ApiKeyLocation::Query => request_builder,
+            };
+        }
+        Some(AuthConfig::None) | None => {}
+    }
+
+    // Set body
+    if let Some(body_json) = req.body.as_ref().filter(|_| hop.with_body) {
+        request_builder = request_builder.json(body_json);
+    }
+
+    request_builder
+}
+
+// Where a redirect response points, relative locations are taken from the current URL
+fn redirect_target(response: &Response, url: &Url) -> Option<Url> {
+    if !matches!(
+        response.status(),
+        StatusCode::MOVED_PERMANENTLY
+            | StatusCode::FOUND
+            | StatusCode::SEE_OTHER
+            | StatusCode::TEMPORARY_REDIRECT
+            | StatusCode::PERMANENT_REDIRECT
+    ) {
+        return None;
+    }
+    let location = response.headers().get(header::LOCATION)?.to_str().ok()?;
+    url.join(location).ok()
+}
+
+// Follows the rules of reqwest: 301, 302 and 303 turn into a GET without a body,
+// 307 and 308 repeat the request as it was
+fn next_hop(hop: Hop, status: StatusCode, url: Url) -> Hop {
+    let same_host = url.host_str() == hop.url.host_str()
+        && url.port_or_known_default() == hop.url.port_or_known_default();
+    let (method, with_body) = match status {
+        StatusCode::TEMPORARY_REDIRECT | StatusCode::PERMANENT_REDIRECT => {
+            (hop.method, hop.with_body)
+        }
+        _ if hop.method == Method::HEAD => (Method::HEAD, false),
+        _ => (Method::GET, false),
+    };
+
+    Hop {
+        url,
+        method,
+        with_body,
+        with_credentials: hop.with_credentials && same_host,
+    }
+}
+
+fn request_error(error: reqwest::Error) -> AppError {
+    match find_violation(&error) {
+        Some(violation) => AppError::OutboundPolicyError(violation.to_string()),
+        None => AppError::BadRequestError(format!("Failed to execute request: {}", error)),
+    }
+}
+
+fn response_too_large(max_bytes: u64) -> AppError {
+    AppError::OutboundPolicyError(format!("The response is larger than {} bytes", max_bytes))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::config::{OutboundConfig, ProxyConfig};
+    use crate::models::{ExecutionSettings, ProxySource};
+    use crate::utils::client_certificate::tests::identity;
+    use serde_json::Map;
+    use std::sync::Mutex;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    // A public address written as an IP, so nothing has to be resolved to reach it
+    const PUBLIC_URL: &str = "http://93.184.216.34/data";
+
+    // Answers every request with the given response and keeps the request lines it saw,
+    // good enough to stand in for an HTTP proxy or a server
+    pub(crate) async fn fake_server(response: &'static str) -> (u16, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+
+        let requests = seen.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut received = Vec::new();
+                let mut buffer = [0; 1024];
+                while !received.windows(4).any(|window| window == b"\r\n\r\n") {
+                    match stream.read(&mut buffer).await {
+                        Ok(0) | Err(_) => break,
+                        Ok(read) => received.extend_from_slice(&buffer[..read]),
+                    }
+                }
+                let request = String::from_utf8_lossy(&received);
+                let line = request.lines().next().unwrap_or_default().to_string();
+                requests.lock().unwrap().push(line);
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+
+        (port, seen)
+    }
+
+    pub(crate) fn outbound_config() -> OutboundConfig {
+        OutboundConfig {
+            allow_private_networks: false,
+            allowed_hosts: Vec::new(),
+            allowed_cidrs: Vec::new(),
+            denied_hosts: Vec::new(),
+            denied_cidrs: Vec::new(),
+            allowed_ports: Vec::new(),
+            denied_ports: Vec::new(),
+            proxy: ProxyConfig {
+                url: None,
+                username: None,
+                password: None,
+                no_proxy: Vec::new(),
+            },
+        }
+    }
+
+    pub(crate) fn limits() -> ExecutionConfig {
+        ExecutionConfig {
+            timeout_ms: 5000,
+            max_timeout_ms: 5000,
+            max_redirects: 5,
+            max_response_bytes: 1024 * 1024,
+        }
+    }
+
+    pub(crate) fn request(url: &str) -> ResolvedRequest {
+        ResolvedRequest {
+            request_id: Uuid::new_v4(),
+            collection_id: None,
+            workspace_id: Uuid::new_v4(),
+            method: "GET".to_string(),
+            url: url.to_string(),
+            headers: Map::new(),
+            params: Map::new(),
+            body: None,
+            auth: None,
+            settings: ExecutionSettings::default(),
+        }
+    }
+
+    // A policy with the fake server as the proxy of the server
+    fn proxied_policy(port: u16, no_proxy: &[&str]) -> Arc<OutboundPolicy> {
+        let mut config = outbound_config();
+        config.proxy.url = Some(format!("http://localhost:{}", port));
+        config.proxy.no_proxy = no_proxy.iter().map(|host| host.to_string()).collect();
+        Arc::new(OutboundPolicy::from_config(&config).unwrap())
+    }
+
+    async fn send_through_server_proxy(
+        policy: &Arc<OutboundPolicy>,
+        url: &str,
+    ) -> Result<HttpRequestResult, AppError> {
+        let proxy = SelectedProxy::select(None, None, policy.server_proxy());
+        execute_request(&request(url), &limits(), policy, proxy.as_ref(), None).await
+    }
+
+    #[tokio::test]
+    async fn proxied_request_reaches_public_target() {
+        let (port, seen) = fake_server(
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: 11\r\n\r\n{\"ok\":true}",
+        )
+        .await;
+        let policy = proxied_policy(port, &[]);
+
+        let result = send_through_server_proxy(&policy, PUBLIC_URL)
+            .await
+            .unwrap();
+
+        assert_eq!(result.status, 200);
+        assert_eq!(result.body, serde_json::json!({ "ok": true }));
+        assert_eq!(
+            result.proxy.map(|proxy| proxy.source),
+            Some(ProxySource::Server)
+        );
+        assert_eq!(
+            seen.lock().unwrap().as_slice(),
+            [format!("GET {} HTTP/1.1", PUBLIC_URL)]
+        );
+    }
+
+    #[tokio::test]
+    async fn proxied_request_to_internal_name_is_refused() {
+        let (port, seen) = fake_server("HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n").await;
+        let policy = proxied_policy(port, &[]);
+
+        for url in ["http://localhost:8080/", "http://LOCALHOST/admin"] {
+            let result = send_through_server_proxy(&policy, url).await;
+            assert!(
+                matches!(result, Err(AppError::OutboundPolicyError(_))),
+                "{} was not refused",
+                url
+            );
+        }
+        assert!(seen.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn proxied_redirect_to_internal_name_is_refused() {
+        let (port, seen) = fake_server(
+            "HTTP/1.1 302 Found\r\nlocation: http://localhost:8080/latest/meta-data\r\ncontent-length: 0\r\n\r\n",
+        )
+        .await;
+        let policy = proxied_policy(port, &[]);
+
+        let result = send_through_server_proxy(&policy, PUBLIC_URL).await;
+
+        assert!(matches!(result, Err(AppError::OutboundPolicyError(_))));
+        assert_eq!(seen.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn proxied_redirect_to_internal_address_is_refused() {
+        let (port, seen) = fake_server(
+            "HTTP/1.1 307 Temporary Redirect\r\nlocation: http://169.254.169.254/\r\ncontent-length: 0\r\n\r\n",
+        )
+        .await;
+        let policy = proxied_policy(port, &[]);
+
+        let result = send_through_server_proxy(&policy, PUBLIC_URL).await;
+
+        assert!(matches!(result, Err(AppError::OutboundPolicyError(_))));
+        assert_eq!(seen.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn server_proxy_host_is_not_trusted_as_target() {
+        let (port, seen) = fake_server("HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n").await;
+        let policy = proxied_policy(port, &["localhost"]);
+
+        // On the no-proxy list the request would go straight to the host of the proxy
+        let result =
+            send_through_server_proxy(&policy, &format!("http://localhost:{}/", port)).await;
+
+        assert!(matches!(result, Err(AppError::OutboundPolicyError(_))));
+        assert!(seen.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn user_proxy_on_internal_address_is_refused() {
+        let (port, seen) = fake_server("HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n").await;
+        let policy = Arc::new(OutboundPolicy::from_config(&outbound_config()).unwrap());
+        let workspace_proxy = crate::models::ProxyMode::Proxy(crate::models::ProxyServer {
+            url: format!("http://localhost:{}", port),
+            username: None,
+            password: None,
+            no_proxy: Vec::new(),
+        });
+        let proxy = SelectedProxy::select(None, Some(&workspace_proxy), None);
+
+        let result = execute_request(
+            &request(PUBLIC_URL),
+            &limits(),
+            &policy,
+            proxy.as_ref(),
+            None,
+        )
+        .await;
+
+        assert!(matches!(result, Err(AppError::OutboundPolicyError(_))));
+        assert!(seen.lock().unwrap().is_empty());
+    }
+
+    // Lets the fake servers on the loopback address through the policy
+    fn loopback_policy() -> Arc<OutboundPolicy> {
+        let mut config = outbound_config();
+        config.allowed_hosts = vec!["127.0.0.1".to_string()];
+        Arc::new(OutboundPolicy::from_config(&config).unwrap())
+    }
+
+    #[tokio::test]
+    async fn redirect_away_from_bound_host_is_returned() {
+        let (port, seen) = fake_server(
+            "HTTP/1.1 302 Found\r\nlocation: http://93.184.216.34/\r\ncontent-length: 0\r\n\r\n",
+        )
+        .await;
+        let identity = identity(&["127.0.0.1"]);
+
+        let result = execute_request(
+            &request(&format!("http://127.0.0.1:{}/", port)),
+            &limits(),
+            &loopback_policy(),
+            None,
+            Some(&identity),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(result.status, 302);
+        assert_eq!(seen.lock().unwrap().len(), 1);
+        // Plain HTTP never presented the certificate
+        assert_eq!(result.client_certificate, None);
+    }
+
+    #[tokio::test]
+    async fn redirect_to_bound_host_is_followed() {
+        let (target_port, target_seen) = fake_server(
+            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: 2\r\n\r\n{}",
+        )
+        .await;
+        let response = format!(
+            "HTTP/1.1 307 Temporary Redirect\r\nlocation: http://127.0.0.1:{}/moved\r\ncontent-length: 0\r\n\r\n",
+            target_port
+        );
+        let (port, seen) = fake_server(Box::leak(response.into_boxed_str())).await;
+        let identity = identity(&["127.0.0.1"]);
+
+        let result = execute_request(
+            &request(&format!("http://127.0.0.1:{}/", port)),
+            &limits(),
+            &loopback_policy(),
+            None,
+            Some(&identity),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(result.status, 200);
+        assert_eq!(seen.lock().unwrap().len(), 1);
+        assert_eq!(
+            target_seen.lock().unwrap().as_slice(),
+            ["GET /moved HTTP/1.1"]
+        );
+    }
+
+    #[tokio::test]
+    async fn identity_is_not_sent_through_https_proxy() {
+        let policy = Arc::new(OutboundPolicy::from_config(&outbound_config()).unwrap());
+        let workspace_proxy = crate::models::ProxyMode::Proxy(crate::models::ProxyServer {
+            url: "https://93.184.216.34:8443".to_string(),
+            username: None,
+            password: None,
+            no_proxy: Vec::new(),
+        });
+        let proxy = SelectedProxy::select(None, Some(&workspace_proxy), None);
+        let identity = identity(&["93.184.216.34"]);
+
+        let result = execute_request(
+            &request("https://93.184.216.34/"),
+            &limits(),
+            &policy,
+            proxy.as_ref(),
+            Some(&identity),
+        )
+        .await;
+
+        assert!(matches!(result, Err(AppError::BadRequestError(_))));
+    }
+}
@@ -0,0 +1,22 @@
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+
+use crate::error::AppError;
+
+// Serialize an optional typed DTO field into the JSONB column value
+pub fn to_json<T: Serialize>(value: Option<&T>) -> Result<Option<Value>, AppError> {
+    value
+        .map(serde_json::to_value)
+        .transpose()
+        .map_err(|e| AppError::InternalServerError(e.to_string()))
+}
+
+// Tells a field sent as null apart from one left out, `Some(None)` clears the stored value.
+// Goes with `#[serde(default)]` so a missing field stays `None`
+pub fn double_option<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
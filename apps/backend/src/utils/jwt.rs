@@ -0,0 +1,291 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{Duration, Utc};
+use jsonwebtoken::jwk::{
+    AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm,
+    OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse, RSAKeyParameters, RSAKeyType,
+};
+use jsonwebtoken::Validation;
+use jsonwebtoken::{decode, decode_header, encode, Algorithm, DecodingKey, EncodingKey, Header};
+use rsa::pkcs8::DecodePublicKey;
+use rsa::traits::PublicKeyParts;
+use rsa::RsaPublicKey;
+use std::collections::HashMap;
+use std::fs;
+use uuid::Uuid;
+
+use crate::app_middleware::Claims;
+use crate::config::JwtConfig;
+use crate::error::AppError;
+
+// DER header of an Ed25519 SubjectPublicKeyInfo, the raw 32 byte key follows it
+const ED25519_SPKI_PREFIX: [u8; 12] = [
+    0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00,
+];
+
+// Keys used to sign and verify access tokens, loaded and checked once at startup
+pub struct JwtKeys {
+    algorithm: Algorithm,
+    key_id: Option<String>,
+    encoding_key: EncodingKey,
+    decoding_keys: HashMap<Option<String>, DecodingKey>, // By `kid`, retired keys included
+    jwks: JwkSet,
+    issuer: String,
+    audience: String,
+    expires_in: Duration,
+}
+
+impl JwtKeys {
+    pub fn from_config(config: &JwtConfig) -> Result<Self, String> {
+        let expires_in = parse_duration(&config.expires_in)
+            .ok_or_else(|| format!("Invalid JWT_EXPIRES_IN: {}", config.expires_in))?;
+        if expires_in <= Duration::zero() || expires_in > Duration::days(1) {
+            return Err("JWT_EXPIRES_IN must be between 1s and 1d".to_string());
+        }
+        if config.issuer.trim().is_empty() || config.audience.trim().is_empty() {
+            return Err("JWT_ISSUER and JWT_AUDIENCE cannot be empty".to_string());
+        }
+
+        let mut keys = match config.algorithm.as_str() {
+            "HS256" => {
+                let secret = config
+                    .secret
+                    .as_deref()
+                    .filter(|secret| !secret.is_empty())
+                    .ok_or("JWT_SECRET is required for HS256")?;
+                if config.verification_keys.is_some() {
+                    return Err("JWT_VERIFICATION_KEYS needs RS256 or EdDSA".to_string());
+                }
+
+                JwtKeys {
+                    algorithm: Algorithm::HS256,
+                    key_id: config.key_id.clone(),
+                    encoding_key: EncodingKey::from_secret(secret.as_bytes()),
+                    decoding_keys: HashMap::from([(
+                        config.key_id.clone(),
+                        DecodingKey::from_secret(secret.as_bytes()),
+                    )]),
+                    // Shared secrets are never published
+                    jwks: JwkSet { keys: Vec::new() },
+                    issuer: config.issuer.clone(),
+                    audience: config.audience.clone(),
+                    expires_in,
+                }
+            }
+            "RS256" | "EdDSA" => {
+                let algorithm = if config.algorithm == "RS256" {
+                    Algorithm::RS256
+                } else {
+                    Algorithm::EdDSA
+                };
+                let key_id = config
+                    .key_id
+                    .clone()
+                    .ok_or("JWT_KEY_ID is required for asymmetric keys")?;
+                let private_key = read_key_file(config.private_key_file.as_deref(), "private")?;
+                let encoding_key = match algorithm {
+                    Algorithm::RS256 => EncodingKey::from_rsa_pem(&private_key),
+                    _ => EncodingKey::from_ed_pem(&private_key),
+                }
+                .map_err(|e| format!("Invalid JWT private key: {}", e))?;
+
+                let mut keys = JwtKeys {
+                    algorithm,
+                    key_id: Some(key_id.clone()),
+                    encoding_key,
+                    decoding_keys: HashMap::new(),
+                    jwks: JwkSet { keys: Vec::new() },
+                    issuer: config.issuer.clone(),
+                    audience: config.audience.clone(),
+                    expires_in,
+                };
+
+                let public_key = read_key_file(config.public_key_file.as_deref(), "public")?;
+                keys.add_public_key(key_id, &public_key)?;
+
+                // Keys that no longer sign but whose tokens are still accepted
+                for entry in config
+                    .verification_keys
+                    .as_deref()
+                    .unwrap_or("")
+                    .split(',')
+                    .filter(|entry| !entry.trim().is_empty())
+                {
+                    let (kid, path) = entry
+                        .split_once('=')
+                        .ok_or_else(|| format!("Invalid JWT_VERIFICATION_KEYS entry: {}", entry))?;
+                    let public_key = read_key_file(Some(path.trim()), "verification")?;
+                    keys.add_public_key(kid.trim().to_string(), &public_key)?;
+                }
+
+                keys
+            }
+            other => return Err(format!("Unsupported JWT_ALGORITHM: {}", other)),
+        };
+
+        keys.jwks
+            .keys
+            .sort_by(|a, b| a.common.key_id.cmp(&b.common.key_id));
+        Ok(keys)
+    }
+
+    pub fn expires_in(&self) -> Duration {
+        self.expires_in
+    }
+
+    // Public keys for other services to verify tokens with
+    pub fn jwks(&self) -> &JwkSet {
+        &self.jwks
+    }
+
+    // Sign an access token for a session of the user
+    pub fn encode_access_token(&self, user_id: Uuid, session_id: Uuid) -> Result<String, AppError> {
+        let now = Utc::now();
+        let claims = Claims {
+            sub: user_id.to_string(),
+            sid: session_id.to_string(),
+            iss: self.issuer.clone(),
+            aud: self.audience.clone(),
+            iat: now.timestamp() as usize,
+            exp: (now + self.expires_in).timestamp() as usize,
+        };
+
+        let mut header = Header::new(self.algorithm);
+        header.kid = self.key_id.clone();
+
+        encode(&header, &claims, &self.encoding_key)
+            .map_err(|e| AppError::InternalServerError(e.to_string()))
+    }
+
+    // Verify an access token with the key named by its `kid`
+    pub fn decode_access_token(&self, token: &str) -> Result<Claims, AppError> {
+        let header = decode_header(token)
+            .map_err(|e| AppError::AuthError(format!("Invalid token: {}", e)))?;
+        let key = self
+            .decoding_keys
+            .get(&header.kid)
+            .ok_or_else(|| AppError::AuthError("Invalid token: unknown signing key".to_string()))?;
+
+        let mut validation = Validation::new(self.algorithm);
+        validation.set_issuer(&[&self.issuer]);
+        validation.set_audience(&[&self.audience]);
+        validation.set_required_spec_claims(&["exp", "sub", "iss", "aud"]);
+
+        decode::<Claims>(token, key, &validation)
+            .map(|token_data| token_data.claims)
+            .map_err(|e| AppError::AuthError(format!("Invalid token: {}", e)))
+    }
+
+    fn add_public_key(&mut self, kid: String, pem: &[u8]) -> Result<(), String> {
+        if self.decoding_keys.contains_key(&Some(kid.clone())) {
+            return Err(format!("Duplicate JWT key id: {}", kid));
+        }
+
+        let invalid = |e: &dyn std::fmt::Display| format!("Invalid JWT public key {}: {}", kid, e);
+        let (decoding_key, key_algorithm, parameters) = match self.algorithm {
+            Algorithm::RS256 => {
+                let pem = std::str::from_utf8(pem).map_err(|e| invalid(&e))?;
+                let public_key = RsaPublicKey::from_public_key_pem(pem).map_err(|e| invalid(&e))?;
+                let n = URL_SAFE_NO_PAD.encode(public_key.n().to_bytes_be());
+                let e = URL_SAFE_NO_PAD.encode(public_key.e().to_bytes_be());
+                (
+                    DecodingKey::from_rsa_components(&n, &e).map_err(|e| invalid(&e))?,
+                    KeyAlgorithm::RS256,
+                    AlgorithmParameters::RSA(RSAKeyParameters {
+                        key_type: RSAKeyType::RSA,
+                        n,
+                        e,
+                    }),
+                )
+            }
+            _ => {
+                let der = pem::parse(pem).map_err(|e| invalid(&e))?;
+                let x = der
+                    .contents()
+                    .strip_prefix(&ED25519_SPKI_PREFIX)
+                    .filter(|x| x.len() == 32)
+                    .ok_or_else(|| invalid(&"not an Ed25519 key"))?;
+                let x = URL_SAFE_NO_PAD.encode(x);
+                (
+                    DecodingKey::from_ed_components(&x).map_err(|e| invalid(&e))?,
+                    KeyAlgorithm::EdDSA,
+                    AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                        key_type: OctetKeyPairType::OctetKeyPair,
+                        curve: EllipticCurve::Ed25519,
+                        x,
+                    }),
+                )
+            }
+        };
+
+        self.jwks.keys.push(Jwk {
+            common: CommonParameters {
+                public_key_use: Some(PublicKeyUse::Signature),
+                key_algorithm: Some(key_algorithm),
+                key_id: Some(kid.clone()),
+                ..Default::default()
+            },
+            algorithm: parameters,
+        });
+        self.decoding_keys.insert(Some(kid), decoding_key);
+
+        Ok(())
+    }
+}
+
+fn read_key_file(path: Option<&str>, kind: &str) -> Result<Vec<u8>, String> {
+    let path = path.ok_or_else(|| format!("The JWT {} key file is not configured", kind))?;
+    fs::read(path).map_err(|e| format!("Failed to read JWT {} key {}: {}", kind, path, e))
+}
+
+// Durations like `90s`, `15m`, `12h` or `7d`
+fn parse_duration(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    let (amount, unit) = value.split_at(value.find(|c: char| !c.is_ascii_digit())?);
+    let amount = amount.parse::<i64>().ok()?;
+
+    // Amounts too large for a duration are invalid rather than a panic
+    match unit {
+        "s" => Duration::try_seconds(amount),
+        "m" => Duration::try_minutes(amount),
+        "h" => Duration::try_hours(amount),
+        "d" => Duration::try_days(amount),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations_are_read_in_every_unit() {
+        assert_eq!(parse_duration("90s"), Some(Duration::seconds(90)));
+        assert_eq!(parse_duration("15m"), Some(Duration::minutes(15)));
+        assert_eq!(parse_duration("12h"), Some(Duration::hours(12)));
+        assert_eq!(parse_duration("7d"), Some(Duration::days(7)));
+        assert_eq!(parse_duration(" 1h "), Some(Duration::hours(1)));
+        assert_eq!(parse_duration("0s"), Some(Duration::zero()));
+    }
+
+    #[test]
+    fn malformed_durations_are_refused() {
+        for value in [
+            "", "15", "m", "15 m", "15min", "15M", "-15m", "1.5h", "1h30m",
+        ] {
+            assert_eq!(parse_duration(value), None, "{:?} was accepted", value);
+        }
+    }
+
+    #[test]
+    fn overflowing_durations_are_refused() {
+        for value in [
+            "9223372036854775807d",
+            "9223372036854775807h",
+            "9223372036854775807m",
+            "9223372036854775807s",
+            "99999999999999999999s",
+        ] {
+            assert_eq!(parse_duration(value), None, "{:?} was accepted", value);
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use chrono::{Duration, NaiveDateTime, Utc};
+use serde_json::json;
+use sqlx::{PgConnection, PgExecutor};
+use uuid::Uuid;
+
+use crate::config::LoginLockoutConfig;
+use crate::error::AppError;
+use crate::models::User;
+use crate::utils::audit::{record_audit, AuditEntry, RequestOrigin};
+
+// A locked account is refused before the password is even looked at
+pub fn ensure_not_locked(user: &User) -> Result<(), AppError> {
+    match lockout_retry_after(user.locked_until) {
+        Some(retry_after) => Err(AppError::RateLimitError(
+            "Too many failed logins, try again later".to_string(),
+            retry_after,
+        )),
+        None => Ok(()),
+    }
+}
+
+// Seconds until a lockout ends, nothing once it has
+pub fn lockout_retry_after(locked_until: Option<NaiveDateTime>) -> Option<u64> {
+    let remaining = (locked_until? - Utc::now().naive_utc()).num_seconds();
+    if remaining <= 0 {
+        return None;
+    }
+    Some(remaining as u64 + 1)
+}
+
+// When the failures in a row lock until, nothing below the limit
+pub fn lockout_end(config: &LoginLockoutConfig, failures: i32) -> Option<NaiveDateTime> {
+    let seconds = lockout_seconds(config, failures)?;
+    Some(Utc::now().naive_utc() + Duration::seconds(seconds))
+}
+
+// Count a failed login, returns when the account is locked until if this one locked it
+pub async fn record_failed_login(
+    conn: &mut PgConnection,
+    config: &LoginLockoutConfig,
+    user_id: Uuid,
+) -> Result<Option<NaiveDateTime>, AppError> {
+    let failures = sqlx::query_scalar!(
+        r#"
+        UPDATE users SET failed_login_count = failed_login_count + 1
+        WHERE id = $1
+        RETURNING failed_login_count
+        "#,
+        user_id
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    let Some(locked_until) = lockout_end(config, failures) else {
+        return Ok(None);
+    };
+
+    sqlx::query!(
+        "UPDATE users SET locked_until = $2 WHERE id = $1",
+        user_id,
+        locked_until
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(Some(locked_until))
+}
+
+// Count the failure against the account and note in the audit log when it locks
+pub async fn lock_out_on_failure(
+    conn: &mut PgConnection,
+    config: &LoginLockoutConfig,
+    user_id: Uuid,
+    origin: &RequestOrigin,
+) -> Result<(), AppError> {
+    let Some(locked_until) = record_failed_login(conn, config, user_id).await? else {
+        return Ok(());
+    };
+
+    record_audit(
+        &mut *conn,
+        origin,
+        AuditEntry {
+            actor_id: Some(user_id),
+            action: "user.locked_out",
+            target_id: Some(user_id),
+            details: Some(json!({ "locked_until": locked_until })),
+            ..Default::default()
+        },
+    )
+    .await
+}
+
+// How long the failures in a row lock the account for, nothing below the limit.
+// Every failure past the limit doubles the lockout
+fn lockout_seconds(config: &LoginLockoutConfig, failures: i32) -> Option<i64> {
+    if failures < config.max_failures {
+        return None;
+    }
+
+    let doublings = (failures - config.max_failures).min(31) as u32;
+    Some(
+        config
+            .lockout_secs
+            .saturating_mul(1i64 << doublings)
+            .min(config.max_lockout_secs),
+    )
+}
+
+// A completed login starts the count over
+pub async fn clear_failed_logins<'c>(
+    executor: impl PgExecutor<'c>,
+    user_id: Uuid,
+) -> Result<(), AppError> {
+    sqlx::query!(
+        r#"
+        UPDATE users SET failed_login_count = 0, locked_until = NULL
+        WHERE id = $1 AND (failed_login_count > 0 OR locked_until IS NOT NULL)
+        "#,
+        user_id
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::tests::TestDatabase;
+
+    fn config() -> LoginLockoutConfig {
+        LoginLockoutConfig {
+            max_failures: 5,
+            lockout_secs: 60,
+            max_lockout_secs: 3600,
+        }
+    }
+
+    async fn user(conn: &mut PgConnection, user_id: Uuid) -> User {
+        sqlx::query_as!(User, "SELECT * FROM users WHERE id = $1", user_id)
+            .fetch_one(&mut *conn)
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn failures_below_the_limit_do_not_lock() {
+        for failures in [0, 1, 4] {
+            assert_eq!(lockout_seconds(&config(), failures), None);
+        }
+    }
+
+    #[test]
+    fn lockout_doubles_with_every_further_failure() {
+        assert_eq!(lockout_seconds(&config(), 5), Some(60));
+        assert_eq!(lockout_seconds(&config(), 6), Some(120));
+        assert_eq!(lockout_seconds(&config(), 7), Some(240));
+        assert_eq!(lockout_seconds(&config(), 10), Some(1920));
+    }
+
+    #[test]
+    fn lockout_stops_growing_at_the_maximum() {
+        for failures in [11, 40, 1000, i32::MAX] {
+            assert_eq!(lockout_seconds(&config(), failures), Some(3600));
+        }
+
+        // The longest lockout the configuration allows, a year
+        let config = LoginLockoutConfig {
+            max_lockout_secs: 365 * 24 * 60 * 60,
+            lockout_secs: 365 * 24 * 60 * 60,
+            ..config()
+        };
+        assert_eq!(lockout_seconds(&config, 100), Some(365 * 24 * 60 * 60));
+    }
+
+    #[test]
+    fn lockout_ends_after_its_seconds() {
+        assert_eq!(lockout_end(&config(), 4), None);
+        assert_eq!(lockout_retry_after(lockout_end(&config(), 4)), None);
+
+        let remaining = lockout_retry_after(lockout_end(&config(), 6)).unwrap();
+        assert!((120..=121).contains(&remaining));
+
+        let ended = Utc::now().naive_utc() - Duration::seconds(1);
+        assert_eq!(lockout_retry_after(Some(ended)), None);
+    }
+
+    #[tokio::test]
+    async fn failed_logins_lock_the_account_until_cleared() {
+        let database = TestDatabase::create().await;
+        {
+            let mut conn = database.pool.acquire().await.unwrap();
+            let user_id = Uuid::new_v4();
+            sqlx::query!(
+                "INSERT INTO users (id, email, password_hash, name) VALUES ($1, 'a@x.test', '', 'A')",
+                user_id
+            )
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+
+            for _ in 0..4 {
+                assert_eq!(
+                    record_failed_login(&mut conn, &config(), user_id)
+                        .await
+                        .unwrap(),
+                    None
+                );
+                assert!(ensure_not_locked(&user(&mut conn, user_id).await).is_ok());
+            }
+
+            let locked_until = record_failed_login(&mut conn, &config(), user_id)
+                .await
+                .unwrap()
+                .unwrap();
+            let remaining = (locked_until - Utc::now().naive_utc()).num_seconds();
+            assert!((58..=60).contains(&remaining));
+            assert!(matches!(
+                ensure_not_locked(&user(&mut conn, user_id).await),
+                Err(AppError::RateLimitError(_, retry_after)) if (59..=61).contains(&retry_after)
+            ));
+
+            clear_failed_logins(&mut *conn, user_id).await.unwrap();
+            let user = user(&mut conn, user_id).await;
+            assert_eq!(user.failed_login_count, 0);
+            assert!(ensure_not_locked(&user).is_ok());
+        }
+        database.drop().await;
+    }
+}
@@ -1 +1,25 @@
+pub mod account;
+pub mod api_key;
+pub mod audit;
+pub mod client_certificate;
+pub mod duplicate;
+pub mod email_token;
+pub mod execution;
 pub mod http;
+pub mod json;
+pub mod jwt;
+pub mod login_lockout;
+pub mod oidc;
+pub mod outbound;
+pub mod pagination;
+pub mod proxy;
+pub mod rate_limit;
+pub mod redact;
+pub mod resolve;
+pub mod revision;
+pub mod secrets;
+pub mod session;
+pub mod token;
+pub mod trash;
+pub mod two_factor;
+pub mod workspace;
@@ -0,0 +1,298 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use log::error;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use url::Url;
+
+use crate::config::OidcConfig;
+use crate::error::AppError;
+
+// How long the discovery document and the provider keys are reused before fetching them again
+const METADATA_TTL: Duration = Duration::from_secs(60 * 60);
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+// ID tokens have to be signed with the keys of the provider, never with a shared secret
+const ALLOWED_ALGORITHMS: [Algorithm; 7] = [
+    Algorithm::RS256,
+    Algorithm::RS384,
+    Algorithm::RS512,
+    Algorithm::PS256,
+    Algorithm::ES256,
+    Algorithm::ES384,
+    Algorithm::EdDSA,
+];
+
+// The parts of the discovery document the login needs
+#[derive(Debug, Clone, Deserialize)]
+struct Discovery {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    jwks_uri: String,
+    #[serde(default)]
+    token_endpoint_auth_methods_supported: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+struct ProviderMetadata {
+    discovery: Discovery,
+    jwks: JwkSet,
+    fetched_at: Instant,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+// Claims of a validated ID token that are used to find or create the user
+#[derive(Debug, Deserialize)]
+pub struct IdTokenClaims {
+    pub iss: String,
+    pub sub: String,
+    pub email: Option<String>,
+    pub email_verified: Option<bool>,
+    pub name: Option<String>,
+    nonce: Option<String>,
+}
+
+// Talks to the configured provider, discovery and keys are fetched on first use
+pub struct OidcClient {
+    issuer_url: String,
+    client_id: String,
+    client_secret: Option<String>,
+    redirect_url: String,
+    scopes: Vec<String>,
+    allow_signup: bool,
+    http: reqwest::Client,
+    metadata: RwLock<Option<ProviderMetadata>>,
+}
+
+impl OidcClient {
+    // Nothing to build when single sign-on is turned off
+    pub fn from_config(config: &OidcConfig) -> Result<Option<Self>, String> {
+        if !config.enabled {
+            return Ok(None);
+        }
+
+        let http = reqwest::Client::builder()
+            .timeout(HTTP_TIMEOUT)
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .map_err(|e| format!("Failed to build the OIDC HTTP client: {}", e))?;
+
+        Ok(Some(OidcClient {
+            issuer_url: config.issuer_url.clone().unwrap_or_default(),
+            client_id: config.client_id.clone().unwrap_or_default(),
+            client_secret: config.client_secret.clone(),
+            redirect_url: config.redirect_url.clone(),
+            scopes: config.scopes.clone(),
+            allow_signup: config.allow_signup,
+            http,
+            metadata: RwLock::new(None),
+        }))
+    }
+
+    pub fn allow_signup(&self) -> bool {
+        self.allow_signup
+    }
+
+    // Where to send the browser, the provider sends it back to the callback with a code
+    pub async fn authorization_url(
+        &self,
+        state: &str,
+        nonce: &str,
+        code_verifier: &str,
+    ) -> Result<String, AppError> {
+        let metadata = self.metadata(false).await?;
+        let mut url = Url::parse(&metadata.discovery.authorization_endpoint)
+            .map_err(|e| provider_error("Invalid authorization endpoint", e))?;
+
+        url.query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &self.client_id)
+            .append_pair("redirect_uri", &self.redirect_url)
+            .append_pair("scope", &self.scopes.join(" "))
+            .append_pair("state", state)
+            .append_pair("nonce", nonce)
+            .append_pair("code_challenge", &code_challenge(code_verifier))
+            .append_pair("code_challenge_method", "S256");
+
+        Ok(url.to_string())
+    }
+
+    // Trade the code from the callback for an ID token and validate it
+    pub async fn exchange_code(
+        &self,
+        code: &str,
+        code_verifier: &str,
+        nonce: &str,
+    ) -> Result<IdTokenClaims, AppError> {
+        let metadata = self.metadata(false).await?;
+        let discovery = &metadata.discovery;
+
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", self.redirect_url.as_str()),
+            ("client_id", self.client_id.as_str()),
+            ("code_verifier", code_verifier),
+        ];
+        let mut request = self.http.post(&discovery.token_endpoint);
+
+        // Client authentication defaults to HTTP basic, as in the spec
+        if let Some(secret) = &self.client_secret {
+            let post = discovery
+                .token_endpoint_auth_methods_supported
+                .iter()
+                .any(|method| method == "client_secret_post");
+            if post {
+                form.push(("client_secret", secret.as_str()));
+            } else {
+                request =
+                    request.basic_auth(form_encode(&self.client_id), Some(form_encode(secret)));
+            }
+        }
+
+        let response = request
+            .form(&form)
+            .send()
+            .await
+            .map_err(|e| provider_error("Token request failed", e))?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            error!("OIDC token endpoint answered {}: {}", status, body);
+            return Err(AppError::AuthError(
+                "The identity provider did not accept the login".to_string(),
+            ));
+        }
+        let tokens: TokenResponse = response
+            .json()
+            .await
+            .map_err(|e| provider_error("Invalid token response", e))?;
+
+        self.validate_id_token(&tokens.id_token, nonce).await
+    }
+
+    // Signature, issuer, audience, expiry and the nonce of this login
+    async fn validate_id_token(
+        &self,
+        id_token: &str,
+        nonce: &str,
+    ) -> Result<IdTokenClaims, AppError> {
+        let invalid = |reason: &str| AppError::AuthError(format!("Invalid ID token: {}", reason));
+
+        let header = decode_header(id_token).map_err(|e| invalid(&e.to_string()))?;
+        if !ALLOWED_ALGORITHMS.contains(&header.alg) {
+            return Err(invalid("unsupported signing algorithm"));
+        }
+
+        // An unknown key may mean the provider rotated its keys since they were fetched
+        let mut metadata = self.metadata(false).await?;
+        let mut key = find_key(&metadata.jwks, header.kid.as_deref());
+        if key.is_none() {
+            metadata = self.metadata(true).await?;
+            key = find_key(&metadata.jwks, header.kid.as_deref());
+        }
+        let key = key.ok_or_else(|| invalid("unknown signing key"))??;
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&metadata.discovery.issuer]);
+        validation.set_audience(&[&self.client_id]);
+        validation.set_required_spec_claims(&["exp", "sub", "iss", "aud"]);
+
+        let claims = decode::<IdTokenClaims>(id_token, &key, &validation)
+            .map_err(|e| invalid(&e.to_string()))?
+            .claims;
+
+        if claims.nonce.as_deref() != Some(nonce) {
+            return Err(invalid("nonce does not match"));
+        }
+
+        Ok(claims)
+    }
+
+    async fn metadata(&self, refresh: bool) -> Result<ProviderMetadata, AppError> {
+        if !refresh {
+            if let Some(metadata) = self.metadata.read().await.as_ref() {
+                if metadata.fetched_at.elapsed() < METADATA_TTL {
+                    return Ok(metadata.clone());
+                }
+            }
+        }
+
+        let discovery_url = format!(
+            "{}/.well-known/openid-configuration",
+            self.issuer_url.trim_end_matches('/')
+        );
+        let discovery: Discovery = self.get_json(&discovery_url).await?;
+
+        // The document has to be about the provider it was fetched from
+        if discovery.issuer.trim_end_matches('/') != self.issuer_url.trim_end_matches('/') {
+            error!(
+                "OIDC discovery issuer {} does not match {}",
+                discovery.issuer, self.issuer_url
+            );
+            return Err(AppError::InternalServerError(
+                "The identity provider is misconfigured".to_string(),
+            ));
+        }
+        let jwks: JwkSet = self.get_json(&discovery.jwks_uri).await?;
+
+        let metadata = ProviderMetadata {
+            discovery,
+            jwks,
+            fetched_at: Instant::now(),
+        };
+        *self.metadata.write().await = Some(metadata.clone());
+
+        Ok(metadata)
+    }
+
+    async fn get_json<T: serde::de::DeserializeOwned>(&self, url: &str) -> Result<T, AppError> {
+        self.http
+            .get(url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| provider_error("Request to the identity provider failed", e))?
+            .json()
+            .await
+            .map_err(|e| provider_error("Invalid answer from the identity provider", e))
+    }
+}
+
+// S256 challenge sent to the provider in place of the verifier
+fn code_challenge(code_verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()))
+}
+
+// The key named by `kid`, or the only signing key when the token names none
+fn find_key(jwks: &JwkSet, kid: Option<&str>) -> Option<Result<DecodingKey, AppError>> {
+    let jwk = match kid {
+        Some(kid) => jwks.find(kid),
+        None if jwks.keys.len() == 1 => jwks.keys.first(),
+        None => None,
+    }?;
+
+    Some(
+        DecodingKey::from_jwk(jwk).map_err(|e| {
+            AppError::AuthError(format!("Invalid ID token: unusable signing key: {}", e))
+        }),
+    )
+}
+
+// Client credentials are form encoded before they go into the basic auth header
+fn form_encode(value: &str) -> String {
+    url::form_urlencoded::byte_serialize(value.as_bytes()).collect()
+}
+
+fn provider_error(message: &str, e: impl std::fmt::Display) -> AppError {
+    error!("{}: {}", message, e);
+    AppError::InternalServerError(format!("{}: {}", message, e))
+}
@@ -0,0 +1,566 @@
+use hyper::client::connect::dns::Name;
+use ipnet::IpNet;
+use reqwest::dns::{Addrs, Resolve, Resolving};
+use std::error::Error;
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use url::{Host, Url};
+
+use crate::config::OutboundConfig;
+use crate::error::AppError;
+use crate::models::ProxyServer;
+
+// Loopback, private, shared, link-local, multicast and reserved ranges, none of them meant for the internet
+const INTERNAL_RANGES: [&str; 17] = [
+    "0.0.0.0/8",
+    "10.0.0.0/8",
+    "100.64.0.0/10",
+    "127.0.0.0/8",
+    "169.254.0.0/16",
+    "172.16.0.0/12",
+    "192.0.0.0/24",
+    "192.168.0.0/16",
+    "198.18.0.0/15",
+    "224.0.0.0/4",
+    "240.0.0.0/4",
+    "::/128",
+    "::1/128",
+    "64:ff9b::/96",
+    "fc00::/7",
+    "fe80::/10",
+    "ff00::/8",
+];
+
+// Where requests sent on behalf of users may go
+pub struct OutboundPolicy {
+    allow_private_networks: bool,
+    internal_ranges: Vec<IpNet>,
+    allowed_hosts: Vec<String>,
+    allowed_cidrs: Vec<IpNet>,
+    denied_hosts: Vec<String>,
+    denied_cidrs: Vec<IpNet>,
+    allowed_ports: Vec<u16>,
+    denied_ports: Vec<u16>,
+    server_proxy: Option<ProxyServer>,
+}
+
+// Why a request was refused, carried through reqwest when the resolver or a redirect refuses it
+#[derive(Debug)]
+pub struct PolicyViolation(String);
+
+impl fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Error for PolicyViolation {}
+
+impl From<PolicyViolation> for AppError {
+    fn from(violation: PolicyViolation) -> Self {
+        AppError::OutboundPolicyError(violation.0)
+    }
+}
+
+impl OutboundPolicy {
+    pub fn from_config(config: &OutboundConfig) -> Result<Self, String> {
+        Ok(OutboundPolicy {
+            allow_private_networks: config.allow_private_networks,
+            internal_ranges: INTERNAL_RANGES
+                .iter()
+                .map(|range| parse_cidr(range))
+                .collect::<Result<_, _>>()?,
+            allowed_hosts: parse_host_patterns(&config.allowed_hosts)?,
+            allowed_cidrs: config
+                .allowed_cidrs
+                .iter()
+                .map(|cidr| parse_cidr(cidr))
+                .collect::<Result<_, _>>()?,
+            denied_hosts: parse_host_patterns(&config.denied_hosts)?,
+            denied_cidrs: config
+                .denied_cidrs
+                .iter()
+                .map(|cidr| parse_cidr(cidr))
+                .collect::<Result<_, _>>()?,
+            allowed_ports: config.allowed_ports.clone(),
+            denied_ports: config.denied_ports.clone(),
+            server_proxy: config.proxy.url.as_ref().map(|url| ProxyServer {
+                url: url.clone(),
+                username: config.proxy.username.clone(),
+                password: config.proxy.password.clone(),
+                no_proxy: config.proxy.no_proxy.clone(),
+            }),
+        })
+    }
+
+    pub fn server_proxy(&self) -> Option<&ProxyServer> {
+        self.server_proxy.as_ref()
+    }
+
+    // Check what the URL alone tells, addresses of host names are checked once they are resolved
+    pub fn check_url(&self, url: &Url) -> Result<(), PolicyViolation> {
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(violation(format!(
+                "The {} scheme is not allowed",
+                url.scheme()
+            )));
+        }
+        self.check_destination(url)
+    }
+
+    // Proxies picked by users are hosts like any other, SOCKS proxies are resolved and checked here
+    // and then connected to by address, as reqwest looks them up on its own
+    pub async fn check_proxy(&self, url: &Url) -> Result<Url, AppError> {
+        self.check_destination(url)?;
+
+        let Some(Host::Domain(name)) = url.host().filter(|_| url.scheme().starts_with("socks"))
+        else {
+            return Ok(url.clone());
+        };
+        let addr = tokio::net::lookup_host((name, 0))
+            .await
+            .ok()
+            .and_then(|mut addrs| addrs.next())
+            .ok_or_else(|| {
+                AppError::BadRequestError(format!("Failed to resolve the proxy {}", name))
+            })?;
+        self.check_address(name, addr.ip())?;
+
+        let mut url = url.clone();
+        url.set_ip_host(addr.ip())
+            .map_err(|_| AppError::BadRequestError("Invalid proxy URL".to_string()))?;
+        Ok(url)
+    }
+
+    fn check_destination(&self, url: &Url) -> Result<(), PolicyViolation> {
+        // SOCKS proxies listen on 1080 unless told otherwise
+        let port = url
+            .port_or_known_default()
+            .or_else(|| url.scheme().starts_with("socks").then_some(1080))
+            .ok_or_else(|| violation("The URL has no port".to_string()))?;
+        if self.denied_ports.contains(&port)
+            || (!self.allowed_ports.is_empty() && !self.allowed_ports.contains(&port))
+        {
+            return Err(violation(format!("The port {} is not allowed", port)));
+        }
+
+        // Addresses written in the URL are never looked up, so they are checked here
+        match url.host() {
+            Some(Host::Domain(name)) => self.check_host(name),
+            Some(Host::Ipv4(ip)) => self.check_address(&ip.to_string(), ip.into()),
+            Some(Host::Ipv6(ip)) => self.check_address(&ip.to_string(), ip.into()),
+            None => Err(violation("The URL has no host".to_string())),
+        }
+    }
+
+    fn check_host(&self, host: &str) -> Result<(), PolicyViolation> {
+        if matches_any(&self.denied_hosts, host) {
+            return Err(violation(format!("The host {} is denied", host)));
+        }
+        Ok(())
+    }
+
+    fn check_address(&self, host: &str, ip: IpAddr) -> Result<(), PolicyViolation> {
+        self.check_host(host)?;
+
+        // IPv4 addresses mapped into IPv6 are checked as the IPv4 address they are
+        let ip = ip.to_canonical();
+        if self.denied_cidrs.iter().any(|cidr| cidr.contains(&ip)) {
+            return Err(violation(format!("The address {} is denied", ip)));
+        }
+
+        let internal = self.internal_ranges.iter().any(|range| range.contains(&ip));
+        let allowed = self.allow_private_networks
+            || self.allowed_cidrs.iter().any(|cidr| cidr.contains(&ip))
+            || matches_any(&self.allowed_hosts, host);
+        if internal && !allowed {
+            return Err(violation(if host == ip.to_string() {
+                format!("The internal address {} is not allowed", ip)
+            } else {
+                format!("{} points to the internal address {}", host, ip)
+            }));
+        }
+        Ok(())
+    }
+
+    // Proxies resolve the target on their own, so it is resolved and checked here first.
+    // The proxy may see other addresses, but a name pointing inside is refused up front
+    pub async fn check_target(&self, url: &Url) -> Result<(), AppError> {
+        self.check_url(url)?;
+
+        let Some(Host::Domain(name)) = url.host() else {
+            return Ok(());
+        };
+        let addrs: Vec<IpAddr> = tokio::net::lookup_host((name, 0))
+            .await
+            .map_err(|_| AppError::BadRequestError(format!("Failed to resolve {}", name)))?
+            .map(|addr| addr.ip())
+            .collect();
+        Ok(self.check_addresses(name, &addrs)?)
+    }
+
+    // Every address has to pass, a name with one internal address among public ones is refused
+    fn check_addresses(&self, host: &str, addrs: &[IpAddr]) -> Result<(), PolicyViolation> {
+        addrs
+            .iter()
+            .try_for_each(|ip| self.check_address(host, *ip))
+    }
+
+    // Resolves host names and refuses them when any of their addresses breaks the policy,
+    // the connection is then made to exactly the checked addresses so a second lookup cannot swap them
+    pub fn resolver(self: &Arc<Self>) -> Arc<PolicyResolver> {
+        Arc::new(PolicyResolver {
+            policy: self.clone(),
+        })
+    }
+}
+
+pub struct PolicyResolver {
+    policy: Arc<OutboundPolicy>,
+}
+
+impl Resolve for PolicyResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let policy = self.policy.clone();
+        Box::pin(async move {
+            let host = name.as_str().to_string();
+            policy.check_host(&host)?;
+
+            let addrs: Vec<SocketAddr> =
+                tokio::net::lookup_host((host.as_str(), 0)).await?.collect();
+            let ips: Vec<IpAddr> = addrs.iter().map(|addr| addr.ip()).collect();
+            policy.check_addresses(&host, &ips)?;
+
+            let addrs: Addrs = Box::new(addrs.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+// The violation behind a failed request, when the resolver or a redirect refused it
+pub fn find_violation(error: &reqwest::Error) -> Option<&PolicyViolation> {
+    let mut source = error.source();
+    while let Some(error) = source {
+        if let Some(violation) = error.downcast_ref::<PolicyViolation>() {
+            return Some(violation);
+        }
+        source = error.source();
+    }
+    None
+}
+
+fn violation(message: String) -> PolicyViolation {
+    PolicyViolation(message)
+}
+
+// A single address counts as a range of one
+fn parse_cidr(cidr: &str) -> Result<IpNet, String> {
+    cidr.parse::<IpNet>()
+        .or_else(|_| cidr.parse::<IpAddr>().map(IpNet::from))
+        .map_err(|_| format!("Invalid CIDR: {}", cidr))
+}
+
+pub fn parse_host_patterns(patterns: &[String]) -> Result<Vec<String>, String> {
+    patterns
+        .iter()
+        .map(|pattern| {
+            let pattern = pattern.trim().trim_end_matches('.').to_lowercase();
+            let name = pattern.strip_prefix("*.").unwrap_or(&pattern);
+            if name.is_empty() || name.contains(['*', '/', ':']) {
+                return Err(format!("Invalid host pattern: {}", pattern));
+            }
+            Ok(pattern)
+        })
+        .collect()
+}
+
+fn matches_any(patterns: &[String], host: &str) -> bool {
+    patterns.iter().any(|pattern| matches_host(pattern, host))
+}
+
+// `*.example.com` matches the subdomains of example.com, anything else only itself
+pub fn matches_host(pattern: &str, host: &str) -> bool {
+    let host = host.trim_end_matches('.').to_lowercase();
+    match pattern.strip_prefix("*.") {
+        Some(domain) => host
+            .strip_suffix(domain)
+            .is_some_and(|subdomain| subdomain.ends_with('.')),
+        None => host == pattern,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::http::execute_request;
+    use crate::utils::http::tests::{fake_server, limits, outbound_config, request};
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    fn policy(configure: impl FnOnce(&mut OutboundConfig)) -> OutboundPolicy {
+        let mut config = outbound_config();
+        configure(&mut config);
+        OutboundPolicy::from_config(&config).unwrap()
+    }
+
+    fn url(url: &str) -> Url {
+        Url::parse(url).unwrap()
+    }
+
+    fn ip(ip: &str) -> IpAddr {
+        ip.parse().unwrap()
+    }
+
+    #[test]
+    fn every_internal_range_is_refused() {
+        let policy = policy(|_| {});
+        for range in INTERNAL_RANGES {
+            let net = parse_cidr(range).unwrap();
+            for ip in [net.network(), net.broadcast()] {
+                assert!(
+                    policy.check_address(&ip.to_string(), ip).is_err(),
+                    "{} in {} was allowed",
+                    ip,
+                    range
+                );
+
+                // The same IPv4 address mapped into IPv6
+                if let IpAddr::V4(v4) = ip {
+                    let mapped = IpAddr::V6(v4.to_ipv6_mapped());
+                    assert!(
+                        policy.check_address(&mapped.to_string(), mapped).is_err(),
+                        "{} was allowed",
+                        mapped
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn public_addresses_are_allowed() {
+        let policy = policy(|_| {});
+        for address in ["93.184.216.34", "8.8.8.8", "2606:4700:4700::1111"] {
+            assert!(policy.check_address(address, ip(address)).is_ok());
+        }
+        let mapped = IpAddr::V6(Ipv4Addr::new(8, 8, 8, 8).to_ipv6_mapped());
+        assert!(policy.check_address("dns", mapped).is_ok());
+    }
+
+    #[test]
+    fn literal_internal_addresses_in_urls_are_refused() {
+        let policy = policy(|_| {});
+        for address in [
+            "http://127.0.0.1/",
+            "http://0x7f.1/",
+            "http://2130706433/",
+            "http://[::1]/",
+            "http://[::ffff:127.0.0.1]/",
+            "http://[::ffff:a9fe:a9fe]/",
+            "http://169.254.169.254/latest/meta-data",
+            "http://10.0.0.1:8080/",
+        ] {
+            assert!(policy.check_url(&url(address)).is_err(), "{}", address);
+        }
+        assert!(policy.check_url(&url("http://93.184.216.34/")).is_ok());
+    }
+
+    #[test]
+    fn only_http_and_https_are_allowed() {
+        let policy = policy(|_| {});
+        assert!(policy.check_url(&url("https://example.com/")).is_ok());
+        for address in [
+            "file:///etc/passwd",
+            "ftp://example.com/",
+            "gopher://example.com/",
+        ] {
+            assert!(policy.check_url(&url(address)).is_err(), "{}", address);
+        }
+    }
+
+    #[test]
+    fn host_patterns_match_the_host_or_its_subdomains() {
+        assert!(matches_host("example.com", "example.com"));
+        assert!(matches_host("example.com", "EXAMPLE.com."));
+        assert!(!matches_host("example.com", "api.example.com"));
+        assert!(matches_host("*.example.com", "api.example.com"));
+        assert!(matches_host("*.example.com", "a.b.example.com"));
+        assert!(!matches_host("*.example.com", "example.com"));
+        assert!(!matches_host("*.example.com", "badexample.com"));
+    }
+
+    #[test]
+    fn host_patterns_are_normalized_and_validated() {
+        let patterns = [
+            "API.Example.com.".to_string(),
+            " *.example.org ".to_string(),
+        ];
+        assert_eq!(
+            parse_host_patterns(&patterns).unwrap(),
+            ["api.example.com", "*.example.org"]
+        );
+        for pattern in [
+            "",
+            "*",
+            "*.",
+            "a.*.com",
+            "example.com/path",
+            "example.com:80",
+        ] {
+            assert!(
+                parse_host_patterns(&[pattern.to_string()]).is_err(),
+                "{:?} was accepted",
+                pattern
+            );
+        }
+    }
+
+    #[test]
+    fn denied_hosts_are_refused_whatever_they_resolve_to() {
+        let policy = policy(|config| {
+            config.denied_hosts = vec!["*.evil.test".to_string(), "tracker.test".to_string()]
+        });
+        assert!(policy.check_url(&url("http://api.evil.test/")).is_err());
+        assert!(policy.check_url(&url("http://TRACKER.test/")).is_err());
+        assert!(policy.check_url(&url("http://evil.test/")).is_ok());
+        assert!(policy
+            .check_address("api.evil.test", ip("93.184.216.34"))
+            .is_err());
+    }
+
+    #[test]
+    fn allowed_hosts_and_cidrs_open_internal_addresses() {
+        let policy = policy(|config| {
+            config.allowed_hosts = vec!["*.corp.test".to_string()];
+            config.allowed_cidrs = vec!["10.1.0.0/16".to_string(), "192.168.1.5".to_string()];
+        });
+        assert!(policy
+            .check_address("wiki.corp.test", ip("10.9.9.9"))
+            .is_ok());
+        assert!(policy.check_address("other.test", ip("10.9.9.9")).is_err());
+        assert!(policy.check_address("10.1.2.3", ip("10.1.2.3")).is_ok());
+        assert!(policy
+            .check_address("192.168.1.5", ip("192.168.1.5"))
+            .is_ok());
+        assert!(policy
+            .check_address("192.168.1.6", ip("192.168.1.6"))
+            .is_err());
+    }
+
+    #[test]
+    fn denied_cidrs_win_over_allowed_private_networks() {
+        let policy = policy(|config| {
+            config.allow_private_networks = true;
+            config.denied_cidrs = vec!["169.254.169.254/32".to_string()];
+        });
+        assert!(policy.check_address("10.0.0.1", ip("10.0.0.1")).is_ok());
+        assert!(policy
+            .check_address("metadata", ip("169.254.169.254"))
+            .is_err());
+        let mapped = IpAddr::V6(Ipv4Addr::new(169, 254, 169, 254).to_ipv6_mapped());
+        assert!(policy.check_address("metadata", mapped).is_err());
+    }
+
+    #[test]
+    fn port_rules_apply_to_explicit_and_default_ports() {
+        let denied = policy(|config| config.denied_ports = vec![22, 6379]);
+        assert!(denied.check_url(&url("http://example.com:6379/")).is_err());
+        assert!(denied.check_url(&url("http://example.com:8080/")).is_ok());
+
+        let allowed = policy(|config| config.allowed_ports = vec![443]);
+        assert!(allowed.check_url(&url("https://example.com/")).is_ok());
+        assert!(allowed.check_url(&url("http://example.com/")).is_err());
+        assert!(allowed
+            .check_url(&url("https://example.com:8443/"))
+            .is_err());
+
+        // SOCKS proxies without a port are checked on 1080
+        let socks = policy(|config| config.denied_ports = vec![1080]);
+        assert!(socks
+            .check_destination(&url("socks5://proxy.example.com"))
+            .is_err());
+    }
+
+    #[test]
+    fn a_name_with_one_internal_address_is_refused() {
+        let policy = policy(|_| {});
+        let public = [ip("93.184.216.34"), ip("2606:2800:220:1::1")];
+        assert!(policy.check_addresses("rebind.test", &public).is_ok());
+
+        // A rebinding name that answers with public and internal addresses at once
+        for internal in [
+            IpAddr::V4(Ipv4Addr::LOCALHOST),
+            IpAddr::V6(Ipv6Addr::LOCALHOST),
+            ip("169.254.169.254"),
+            IpAddr::V6(Ipv4Addr::new(10, 0, 0, 1).to_ipv6_mapped()),
+        ] {
+            let mixed = [public[0], internal, public[1]];
+            assert!(
+                policy.check_addresses("rebind.test", &mixed).is_err(),
+                "{} was allowed",
+                internal
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn targets_reached_through_a_proxy_are_resolved_and_checked() {
+        let policy = policy(|_| {});
+        assert!(matches!(
+            policy.check_target(&url("http://localhost:8080/")).await,
+            Err(AppError::OutboundPolicyError(_))
+        ));
+        assert!(policy
+            .check_target(&url("http://93.184.216.34/"))
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn redirects_to_internal_addresses_are_refused() {
+        let (port, seen) = fake_server(
+            "HTTP/1.1 302 Found\r\nlocation: http://[::ffff:169.254.169.254]/latest/meta-data\r\ncontent-length: 0\r\n\r\n",
+        )
+        .await;
+        // The first host is allowed by name, the redirect target is not
+        let policy = Arc::new(policy(|config| {
+            config.allowed_hosts = vec!["localhost".to_string()]
+        }));
+
+        let result = execute_request(
+            &request(&format!("http://localhost:{}/", port)),
+            &limits(),
+            &policy,
+            None,
+            None,
+        )
+        .await;
+
+        assert!(matches!(result, Err(AppError::OutboundPolicyError(_))));
+        assert_eq!(seen.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn redirects_to_names_resolving_inside_are_refused() {
+        let (port, seen) = fake_server(
+            "HTTP/1.1 301 Moved Permanently\r\nlocation: http://localhost:1/\r\ncontent-length: 0\r\n\r\n",
+        )
+        .await;
+        // Only the literal address is allowed, not a name that resolves to it
+        let policy = Arc::new(policy(|config| {
+            config.allowed_hosts = vec!["127.0.0.1".to_string()]
+        }));
+
+        let result = execute_request(
+            &request(&format!("http://127.0.0.1:{}/", port)),
+            &limits(),
+            &policy,
+            None,
+            None,
+        )
+        .await;
+
+        assert!(matches!(result, Err(AppError::OutboundPolicyError(_))));
+        assert_eq!(seen.lock().unwrap().len(), 1);
+    }
+}
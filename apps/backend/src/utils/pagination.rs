@@ -0,0 +1,265 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sqlx::{Postgres, QueryBuilder};
+use uuid::Uuid;
+
+use crate::error::AppError;
+
+pub const DEFAULT_PAGE_SIZE: i64 = 50;
+pub const MAX_PAGE_SIZE: i64 = 200;
+
+// Keeps the full microsecond precision of Postgres timestamps
+const CURSOR_TIMESTAMP_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%.f";
+
+// Envelope returned by every paginated list endpoint
+#[derive(Debug, Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total: i64,
+    pub next_cursor: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortField {
+    #[default]
+    CreatedAt,
+    UpdatedAt,
+    Name,
+}
+
+impl SortField {
+    pub fn column(&self) -> &'static str {
+        match self {
+            SortField::CreatedAt => "created_at",
+            SortField::UpdatedAt => "updated_at",
+            SortField::Name => "name",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+impl SortOrder {
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            SortOrder::Asc => "ASC",
+            SortOrder::Desc => "DESC",
+        }
+    }
+}
+
+// Position after the last item of a page, ordered by the sort column and then the ID
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Cursor {
+    pub value: String,
+    pub id: Uuid,
+}
+
+impl Cursor {
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+
+    pub fn decode(cursor: &str) -> Result<Self, AppError> {
+        URL_SAFE_NO_PAD
+            .decode(cursor)
+            .ok()
+            .and_then(|bytes| serde_json::from_slice(&bytes).ok())
+            .ok_or_else(|| AppError::BadRequestError("Invalid cursor".to_string()))
+    }
+}
+
+pub fn page_size(limit: Option<i64>) -> Result<i64, AppError> {
+    match limit {
+        None => Ok(DEFAULT_PAGE_SIZE),
+        Some(limit) if (1..=MAX_PAGE_SIZE).contains(&limit) => Ok(limit),
+        Some(_) => Err(AppError::ValidationError(format!(
+            "limit must be between 1 and {}",
+            MAX_PAGE_SIZE
+        ))),
+    }
+}
+
+// Restrict a query to the rows after the cursor, `alias` is the table alias used in the query
+pub fn push_cursor_condition(
+    builder: &mut QueryBuilder<'_, Postgres>,
+    alias: &str,
+    sort: SortField,
+    order: SortOrder,
+    cursor: &Cursor,
+) -> Result<(), AppError> {
+    let comparison = match order {
+        SortOrder::Asc => ">",
+        SortOrder::Desc => "<",
+    };
+
+    builder.push(format!(
+        " AND ({alias}.{}, {alias}.id) {comparison} (",
+        sort.column()
+    ));
+    match sort {
+        SortField::Name => {
+            builder.push_bind(cursor.value.clone());
+        }
+        SortField::CreatedAt | SortField::UpdatedAt => {
+            let value = NaiveDateTime::parse_from_str(&cursor.value, CURSOR_TIMESTAMP_FORMAT)
+                .map_err(|_| AppError::BadRequestError("Invalid cursor".to_string()))?;
+            builder.push_bind(value);
+        }
+    }
+    builder.push(", ").push_bind(cursor.id).push(")");
+
+    Ok(())
+}
+
+pub fn push_order_by(
+    builder: &mut QueryBuilder<'_, Postgres>,
+    alias: &str,
+    sort: SortField,
+    order: SortOrder,
+) {
+    builder.push(format!(
+        " ORDER BY {alias}.{column} {order}, {alias}.id {order}",
+        column = sort.column(),
+        order = order.keyword()
+    ));
+}
+
+// The sort value of the last item, stored in the cursor for the next page
+pub fn sort_value(
+    sort: SortField,
+    name: &str,
+    created_at: NaiveDateTime,
+    updated_at: NaiveDateTime,
+) -> String {
+    match sort {
+        SortField::CreatedAt => created_at.format(CURSOR_TIMESTAMP_FORMAT).to_string(),
+        SortField::UpdatedAt => updated_at.format(CURSOR_TIMESTAMP_FORMAT).to_string(),
+        SortField::Name => name.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn sort_field(value: &str) -> Option<SortField> {
+        serde_json::from_value(json!(value)).ok()
+    }
+
+    #[test]
+    fn page_size_defaults_and_stays_in_bounds() {
+        assert_eq!(page_size(None).unwrap(), DEFAULT_PAGE_SIZE);
+        assert_eq!(page_size(Some(1)).unwrap(), 1);
+        assert_eq!(page_size(Some(MAX_PAGE_SIZE)).unwrap(), MAX_PAGE_SIZE);
+        for limit in [0, -1, MAX_PAGE_SIZE + 1, i64::MAX, i64::MIN] {
+            assert!(page_size(Some(limit)).is_err(), "{} was accepted", limit);
+        }
+    }
+
+    #[test]
+    fn only_known_columns_can_be_sorted_on() {
+        assert_eq!(sort_field("created_at"), Some(SortField::CreatedAt));
+        assert_eq!(sort_field("updated_at"), Some(SortField::UpdatedAt));
+        assert_eq!(sort_field("name"), Some(SortField::Name));
+        for value in [
+            "id",
+            "user_id",
+            "Name",
+            "name DESC",
+            "name; DROP TABLE requests",
+            "",
+        ] {
+            assert_eq!(sort_field(value), None, "{:?} was accepted", value);
+        }
+    }
+
+    #[test]
+    fn only_known_orders_are_accepted() {
+        assert_eq!(
+            serde_json::from_value::<SortOrder>(json!("asc")).unwrap(),
+            SortOrder::Asc
+        );
+        assert!(serde_json::from_value::<SortOrder>(json!("asc, id")).is_err());
+    }
+
+    #[test]
+    fn cursors_round_trip() {
+        let cursor = Cursor {
+            value: "2025-06-01T12:30:45.123456".to_string(),
+            id: Uuid::new_v4(),
+        };
+
+        let decoded = Cursor::decode(&cursor.encode()).unwrap();
+
+        assert_eq!(decoded.value, cursor.value);
+        assert_eq!(decoded.id, cursor.id);
+    }
+
+    #[test]
+    fn malformed_cursors_are_refused() {
+        let not_json = URL_SAFE_NO_PAD.encode("not json");
+        for cursor in ["", "!!!", not_json.as_str()] {
+            assert!(Cursor::decode(cursor).is_err(), "{:?} was accepted", cursor);
+        }
+    }
+
+    #[test]
+    fn timestamps_keep_microseconds_in_the_cursor() {
+        let at =
+            NaiveDateTime::parse_from_str("2025-06-01T12:30:45.123456", CURSOR_TIMESTAMP_FORMAT)
+                .unwrap();
+        let value = sort_value(SortField::UpdatedAt, "name", at, at);
+
+        assert_eq!(
+            NaiveDateTime::parse_from_str(&value, CURSOR_TIMESTAMP_FORMAT).unwrap(),
+            at
+        );
+        assert_eq!(sort_value(SortField::Name, "name", at, at), "name");
+    }
+
+    #[test]
+    fn cursor_condition_follows_the_order() {
+        let cursor = Cursor {
+            value: "b".to_string(),
+            id: Uuid::new_v4(),
+        };
+        let mut builder = QueryBuilder::<Postgres>::new("SELECT * FROM requests r WHERE TRUE");
+
+        push_cursor_condition(&mut builder, "r", SortField::Name, SortOrder::Asc, &cursor).unwrap();
+        push_order_by(&mut builder, "r", SortField::Name, SortOrder::Asc);
+
+        assert_eq!(
+            builder.sql(),
+            "SELECT * FROM requests r WHERE TRUE AND (r.name, r.id) > ($1, $2) \
+             ORDER BY r.name ASC, r.id ASC"
+        );
+    }
+
+    #[test]
+    fn timestamp_cursor_needs_a_timestamp() {
+        let cursor = Cursor {
+            value: "b".to_string(),
+            id: Uuid::new_v4(),
+        };
+        let mut builder = QueryBuilder::<Postgres>::new("");
+
+        assert!(push_cursor_condition(
+            &mut builder,
+            "r",
+            SortField::CreatedAt,
+            SortOrder::Desc,
+            &cursor
+        )
+        .is_err());
+    }
+}
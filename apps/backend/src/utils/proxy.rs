@@ -0,0 +1,124 @@
+use ipnet::IpNet;
+use reqwest::Proxy;
+use std::net::IpAddr;
+use url::{Host, Url};
+
+use crate::error::AppError;
+use crate::models::{ProxyMode, ProxyServer, ProxySource, UsedProxy};
+use crate::utils::outbound::OutboundPolicy;
+
+const PROXY_SCHEMES: [&str; 4] = ["http", "https", "socks5", "socks5h"];
+
+// Credentials have their own fields, so they never end up in a URL that gets shown
+pub fn is_valid_proxy_url(url: &str) -> bool {
+    Url::parse(url).is_ok_and(|url| {
+        PROXY_SCHEMES.contains(&url.scheme())
+            && url.has_host()
+            && url.username().is_empty()
+            && url.password().is_none()
+    })
+}
+
+// The proxy a request goes through and who picked it
+pub struct SelectedProxy {
+    pub server: ProxyServer,
+    pub source: ProxySource,
+}
+
+impl SelectedProxy {
+    // The request settings win over the workspace, which wins over the server
+    pub fn select(
+        request: Option<&ProxyMode>,
+        workspace: Option<&ProxyMode>,
+        server: Option<&ProxyServer>,
+    ) -> Option<SelectedProxy> {
+        let (mode, source) = match (request, workspace) {
+            (Some(mode), _) => (mode, ProxySource::Request),
+            (None, Some(mode)) => (mode, ProxySource::Workspace),
+            (None, None) => {
+                return server.map(|server| SelectedProxy {
+                    server: server.clone(),
+                    source: ProxySource::Server,
+                })
+            }
+        };
+
+        match mode {
+            ProxyMode::Direct => None,
+            ProxyMode::Proxy(server) => Some(SelectedProxy {
+                server: server.clone(),
+                source,
+            }),
+        }
+    }
+
+    // Proxies picked by users have to pass the outbound policy like any other host
+    pub async fn build(&self, policy: &OutboundPolicy) -> Result<Proxy, AppError> {
+        let invalid_proxy =
+            || AppError::BadRequestError(format!("Invalid proxy URL: {}", self.server.url));
+        if !is_valid_proxy_url(&self.server.url) {
+            return Err(invalid_proxy());
+        }
+
+        let mut url = Url::parse(&self.server.url).map_err(|_| invalid_proxy())?;
+        if self.source != ProxySource::Server {
+            url = policy.check_proxy(&url).await?;
+        }
+
+        let mut proxy = Proxy::all(url.as_str()).map_err(|_| invalid_proxy())?;
+        if let Some(username) = &self.server.username {
+            proxy = proxy.basic_auth(
+                username,
+                self.server.password.as_deref().unwrap_or_default(),
+            );
+        }
+
+        Ok(proxy)
+    }
+
+    // Only the proxy of the server may sit on an internal address
+    pub fn is_trusted(&self) -> bool {
+        self.source == ProxySource::Server
+    }
+
+    // The TLS connection to an HTTPS proxy is made with the same client as the request
+    pub fn uses_tls(&self) -> bool {
+        Url::parse(&self.server.url).is_ok_and(|url| url.scheme() == "https")
+    }
+
+    // Nothing when the URL is on the no-proxy list and goes out directly
+    pub fn used_for(&self, url: &Url) -> Option<UsedProxy> {
+        if bypasses(&self.server.no_proxy, url) {
+            return None;
+        }
+
+        Some(UsedProxy {
+            url: self.server.url.clone(),
+            source: self.source,
+        })
+    }
+}
+
+// The rules reqwest applies to the no-proxy list, the same as curl
+fn bypasses(no_proxy: &[String], url: &Url) -> bool {
+    let matches_ip = |entry: &str, ip: IpAddr| match entry.parse::<IpNet>() {
+        Ok(net) => net.contains(&ip),
+        Err(_) => entry.parse::<IpAddr>().is_ok_and(|entry| entry == ip),
+    };
+
+    no_proxy
+        .iter()
+        .map(|entry| entry.trim())
+        .any(|entry| match url.host() {
+            Some(Host::Domain(domain)) => {
+                entry == "*"
+                    || domain == entry.trim_start_matches('.')
+                    || domain
+                        .strip_suffix(entry)
+                        .is_some_and(|rest| entry.starts_with('.') || rest.ends_with('.'))
+            }
+            Some(Host::Ipv4(ip)) => matches_ip(entry, ip.into()),
+            Some(Host::Ipv6(ip)) => matches_ip(entry, ip.into()),
+            None => false,
+        })
+}
@@ -0,0 +1,278 @@
+use actix_web::HttpRequest;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use crate::config::{BucketConfig, RateLimitConfig};
+
+// How often buckets that filled up again are dropped from memory
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+// Routes that share a budget
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RateLimitScope {
+    Auth,
+    Execute,
+    Api,
+}
+
+impl RateLimitScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RateLimitScope::Auth => "auth",
+            RateLimitScope::Execute => "execute",
+            RateLimitScope::Api => "api",
+        }
+    }
+}
+
+struct Bucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+struct Buckets {
+    entries: HashMap<(RateLimitScope, String), Bucket>,
+    pruned_at: Instant,
+}
+
+// Token buckets kept in memory, shared by the workers of this process
+pub struct RateLimiter {
+    trust_forwarded_for: bool,
+    auth: BucketConfig,
+    execute: BucketConfig,
+    api: BucketConfig,
+    buckets: Mutex<Buckets>,
+}
+
+impl RateLimiter {
+    // Nothing to build when rate limiting is turned off
+    pub fn from_config(config: &RateLimitConfig) -> Option<Self> {
+        if !config.enabled {
+            return None;
+        }
+
+        Some(RateLimiter {
+            trust_forwarded_for: config.trust_forwarded_for,
+            auth: config.auth,
+            execute: config.execute,
+            api: config.api,
+            buckets: Mutex::new(Buckets {
+                entries: HashMap::new(),
+                pruned_at: Instant::now(),
+            }),
+        })
+    }
+
+    pub fn trust_forwarded_for(&self) -> bool {
+        self.trust_forwarded_for
+    }
+
+    // Take a token for the key, or the seconds until one is available
+    pub fn check(&self, scope: RateLimitScope, key: &str) -> Result<(), u64> {
+        self.check_at(scope, key, Instant::now())
+    }
+
+    fn check_at(&self, scope: RateLimitScope, key: &str, now: Instant) -> Result<(), u64> {
+        let config = self.bucket_config(scope);
+        let capacity = config.capacity as f64;
+        let per_second = config.per_minute as f64 / 60.0;
+
+        let mut buckets = self.buckets.lock().unwrap_or_else(|e| e.into_inner());
+        if now.duration_since(buckets.pruned_at) >= PRUNE_INTERVAL {
+            self.prune(&mut buckets, now);
+        }
+
+        let bucket = buckets
+            .entries
+            .entry((scope, key.to_string()))
+            .or_insert(Bucket {
+                tokens: capacity,
+                updated_at: now,
+            });
+
+        let elapsed = now.duration_since(bucket.updated_at).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * per_second).min(capacity);
+        bucket.updated_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+
+        Err(((1.0 - bucket.tokens) / per_second).ceil().max(1.0) as u64)
+    }
+
+    fn bucket_config(&self, scope: RateLimitScope) -> BucketConfig {
+        match scope {
+            RateLimitScope::Auth => self.auth,
+            RateLimitScope::Execute => self.execute,
+            RateLimitScope::Api => self.api,
+        }
+    }
+
+    // A bucket that would be full again is the same as no bucket
+    fn prune(&self, buckets: &mut Buckets, now: Instant) {
+        buckets.entries.retain(|(scope, _), bucket| {
+            let config = self.bucket_config(*scope);
+            let refill = now.duration_since(bucket.updated_at).as_secs_f64()
+                * config.per_minute as f64
+                / 60.0;
+            bucket.tokens + refill < config.capacity as f64
+        });
+        buckets.pruned_at = now;
+    }
+}
+
+// Signed in requests share the budget of their user, whatever address they come from
+pub fn user_key(user_id: &Uuid) -> String {
+    format!("user:{}", user_id)
+}
+
+// The forwarded address can be set by anyone, so it only counts behind a trusted proxy
+pub fn client_ip(req: &HttpRequest, trust_forwarded_for: bool) -> Option<String> {
+    let connection_info = req.connection_info();
+    let addr = if trust_forwarded_for {
+        connection_info.realip_remote_addr()
+    } else {
+        connection_info.peer_addr()
+    }?;
+
+    // Drop the port, only the address identifies the client
+    Some(match addr.parse::<SocketAddr>() {
+        Ok(socket) => socket.ip().to_string(),
+        Err(_) => addr.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Three at once and one more every two seconds for logins, the rest hardly limited
+    fn limiter() -> RateLimiter {
+        RateLimiter::from_config(&RateLimitConfig {
+            enabled: true,
+            trust_forwarded_for: false,
+            auth: BucketConfig {
+                capacity: 3,
+                per_minute: 30,
+            },
+            execute: BucketConfig {
+                capacity: 1,
+                per_minute: 1,
+            },
+            api: BucketConfig {
+                capacity: 100,
+                per_minute: 100,
+            },
+        })
+        .unwrap()
+    }
+
+    fn seconds(seconds: f64) -> Duration {
+        Duration::from_secs_f64(seconds)
+    }
+
+    #[test]
+    fn bucket_allows_its_capacity_at_once() {
+        let limiter = limiter();
+        let now = Instant::now();
+
+        for _ in 0..3 {
+            assert_eq!(limiter.check_at(RateLimitScope::Auth, "a", now), Ok(()));
+        }
+        assert_eq!(limiter.check_at(RateLimitScope::Auth, "a", now), Err(2));
+    }
+
+    #[test]
+    fn tokens_come_back_over_time() {
+        let limiter = limiter();
+        let now = Instant::now();
+        for _ in 0..3 {
+            limiter.check_at(RateLimitScope::Auth, "a", now).unwrap();
+        }
+
+        // Half a token is not enough, the wait left is rounded up
+        assert_eq!(
+            limiter.check_at(RateLimitScope::Auth, "a", now + seconds(1.0)),
+            Err(1)
+        );
+        assert_eq!(
+            limiter.check_at(RateLimitScope::Auth, "a", now + seconds(2.0)),
+            Ok(())
+        );
+        assert!(limiter
+            .check_at(RateLimitScope::Auth, "a", now + seconds(2.0))
+            .is_err());
+    }
+
+    #[test]
+    fn refill_stops_at_capacity() {
+        let limiter = limiter();
+        let now = Instant::now();
+        limiter.check_at(RateLimitScope::Auth, "a", now).unwrap();
+
+        let later = now + seconds(3600.0);
+        for _ in 0..3 {
+            assert_eq!(limiter.check_at(RateLimitScope::Auth, "a", later), Ok(()));
+        }
+        assert!(limiter.check_at(RateLimitScope::Auth, "a", later).is_err());
+    }
+
+    #[test]
+    fn keys_and_scopes_have_buckets_of_their_own() {
+        let limiter = limiter();
+        let now = Instant::now();
+        limiter.check_at(RateLimitScope::Execute, "a", now).unwrap();
+
+        assert_eq!(limiter.check_at(RateLimitScope::Execute, "a", now), Err(60));
+        assert_eq!(limiter.check_at(RateLimitScope::Execute, "b", now), Ok(()));
+        assert_eq!(limiter.check_at(RateLimitScope::Auth, "a", now), Ok(()));
+    }
+
+    #[test]
+    fn full_buckets_are_pruned() {
+        let limiter = limiter();
+        let now = Instant::now();
+        limiter.check_at(RateLimitScope::Auth, "a", now).unwrap();
+        limiter.check_at(RateLimitScope::Execute, "b", now).unwrap();
+
+        // The login bucket is full again after two seconds, the execute one takes a minute
+        let mut buckets = limiter.buckets.lock().unwrap();
+        limiter.prune(&mut buckets, now + seconds(30.0));
+
+        assert!(!buckets
+            .entries
+            .contains_key(&(RateLimitScope::Auth, "a".to_string())));
+        assert!(buckets
+            .entries
+            .contains_key(&(RateLimitScope::Execute, "b".to_string())));
+    }
+
+    #[test]
+    fn nothing_is_built_when_turned_off() {
+        let mut config = RateLimitConfig {
+            enabled: false,
+            trust_forwarded_for: false,
+            auth: BucketConfig {
+                capacity: 1,
+                per_minute: 1,
+            },
+            execute: BucketConfig {
+                capacity: 1,
+                per_minute: 1,
+            },
+            api: BucketConfig {
+                capacity: 1,
+                per_minute: 1,
+            },
+        };
+        assert!(RateLimiter::from_config(&config).is_none());
+
+        config.enabled = true;
+        assert!(RateLimiter::from_config(&config).is_some());
+    }
+}
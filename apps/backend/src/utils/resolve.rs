@@ -0,0 +1,162 @@
+use reqwest::Method;
+use serde_json::{Map, Value};
+use url::Url;
+
+use crate::error::AppError;
+use crate::models::{AuthConfig, Collection, ExecutionSettings, Request, ResolvedRequest};
+
+// Merge the collection defaults into a request, values set on the request win
+pub fn resolve_request(
+    request: &Request,
+    collection: Option<&Collection>,
+) -> Result<ResolvedRequest, AppError> {
+    let method = request.method.to_uppercase();
+    if method.parse::<Method>().is_err() {
+        return Err(AppError::ValidationError(format!(
+            "Invalid HTTP method: {}",
+            request.method
+        )));
+    }
+
+    let request_headers = as_object(Some(&request.headers));
+    let request_params = as_object(request.params.as_ref());
+    let request_auth = parse_auth(request.auth.as_ref())?;
+    let request_settings = parse_settings(&request.settings)?;
+
+    let (url, headers, params, auth, settings) = match collection {
+        Some(collection) => {
+            // Header names are case-insensitive, so a request header replaces any casing of it
+            let mut headers = Map::new();
+            for (key, value) in as_object(Some(&collection.default_headers)) {
+                if !request_headers
+                    .keys()
+                    .any(|name| name.eq_ignore_ascii_case(&key))
+                {
+                    headers.insert(key, value);
+                }
+            }
+            headers.extend(request_headers);
+
+            let mut params = as_object(Some(&collection.default_params));
+            params.extend(request_params);
+
+            let auth = match request_auth {
+                Some(auth) => Some(auth),
+                None => parse_auth(collection.auth.as_ref())?,
+            };
+
+            let settings = request_settings.merge(&parse_settings(&collection.settings)?);
+
+            (
+                join_url(collection.base_url.as_deref(), &request.url)?,
+                headers,
+                params,
+                auth,
+                settings,
+            )
+        }
+        None => (
+            join_url(None, &request.url)?,
+            request_headers,
+            request_params,
+            request_auth,
+            request_settings,
+        ),
+    };
+
+    Ok(ResolvedRequest {
+        request_id: request.id,
+        collection_id: request.collection_id,
+        workspace_id: request.workspace_id,
+        method,
+        url,
+        headers,
+        params,
+        body: request.body.clone(),
+        // An explicit `none` on the request disables the inherited auth
+        auth: auth.filter(|auth| !matches!(auth, AuthConfig::None)),
+        settings,
+    })
+}
+
+fn as_object(value: Option<&Value>) -> Map<String, Value> {
+    match value {
+        Some(Value::Object(map)) => map.clone(),
+        _ => Map::new(),
+    }
+}
+
+fn parse_auth(value: Option<&Value>) -> Result<Option<AuthConfig>, AppError> {
+    match value {
+        None | Some(Value::Null) => Ok(None),
+        Some(value) => serde_json::from_value(value.clone())
+            .map(Some)
+            .map_err(|e| AppError::InternalServerError(format!("Invalid stored auth: {}", e))),
+    }
+}
+
+fn parse_settings(value: &Value) -> Result<ExecutionSettings, AppError> {
+    match value {
+        Value::Null => Ok(ExecutionSettings::default()),
+        value => serde_json::from_value(value.clone())
+            .map_err(|e| AppError::InternalServerError(format!("Invalid stored settings: {}", e))),
+    }
+}
+
+fn join_url(base_url: Option<&str>, url: &str) -> Result<String, AppError> {
+    if Url::parse(url).is_ok() {
+        return Ok(url.to_string());
+    }
+
+    match base_url {
+        Some(base_url) => Ok(format!(
+            "{}/{}",
+            base_url.trim_end_matches('/'),
+            url.trim_start_matches('/')
+        )),
+        None => Err(AppError::ValidationError(
+            "Request URL is relative but no base URL is configured".to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relative_urls_are_joined_with_one_slash() {
+        for (base_url, url) in [
+            ("https://api.example.com/v1", "users"),
+            ("https://api.example.com/v1", "/users"),
+            ("https://api.example.com/v1/", "users"),
+            ("https://api.example.com/v1/", "/users"),
+            ("https://api.example.com/v1//", "//users"),
+        ] {
+            assert_eq!(
+                join_url(Some(base_url), url).unwrap(),
+                "https://api.example.com/v1/users",
+                "{} + {}",
+                base_url,
+                url
+            );
+        }
+    }
+
+    #[test]
+    fn absolute_urls_ignore_the_base_url() {
+        assert_eq!(
+            join_url(Some("https://api.example.com"), "http://other.test/x").unwrap(),
+            "http://other.test/x"
+        );
+        assert_eq!(
+            join_url(None, "http://other.test/x").unwrap(),
+            "http://other.test/x"
+        );
+    }
+
+    #[test]
+    fn relative_url_needs_a_base_url() {
+        assert!(join_url(None, "/users").is_err());
+    }
+}
@@ -0,0 +1,88 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::models::{EntityType, FieldChange};
+
+// Bookkeeping fields that change on every write and would only add noise to diffs
+const IGNORED_FIELDS: [&str; 1] = ["updated_at"];
+
+// Store the new state of an entity as its next revision
+pub async fn record_revision<T: Serialize>(
+    conn: &mut PgConnection,
+    entity_type: EntityType,
+    entity_id: Uuid,
+    snapshot: &T,
+    user_id: Uuid,
+) -> Result<(), AppError> {
+    let snapshot =
+        serde_json::to_value(snapshot).map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+    // Writers of the same entity take turns until their transaction ends, so two of them
+    // never read the same latest version and collide on the next one
+    match entity_type {
+        EntityType::Request => {
+            sqlx::query!(
+                "SELECT id FROM requests WHERE id = $1 FOR UPDATE",
+                entity_id
+            )
+            .fetch_optional(&mut *conn)
+            .await?;
+        }
+        EntityType::Collection => {
+            sqlx::query!(
+                "SELECT id FROM collections WHERE id = $1 FOR UPDATE",
+                entity_id
+            )
+            .fetch_optional(&mut *conn)
+            .await?;
+        }
+    }
+
+    sqlx::query!(
+        r#"
+        INSERT INTO revisions (id, entity_type, entity_id, version, snapshot, user_id, created_at)
+        SELECT $1, $2::VARCHAR, $3, COALESCE(MAX(version), 0) + 1, $4, $5, $6
+        FROM revisions
+        WHERE entity_type = $2::VARCHAR AND entity_id = $3
+        "#,
+        Uuid::new_v4(),
+        entity_type.as_str(),
+        entity_id,
+        snapshot,
+        user_id,
+        chrono::Utc::now().naive_utc()
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+// Compare the top-level fields of two snapshots
+pub fn diff_snapshots(before: &Value, after: &Value) -> Vec<FieldChange> {
+    let empty = Map::new();
+    let before = before.as_object().unwrap_or(&empty);
+    let after = after.as_object().unwrap_or(&empty);
+
+    let mut fields: Vec<&String> = before.keys().chain(after.keys()).collect();
+    fields.sort();
+    fields.dedup();
+
+    fields
+        .into_iter()
+        .filter(|field| !IGNORED_FIELDS.contains(&field.as_str()))
+        .filter_map(|field| {
+            let old = before.get(field).cloned().unwrap_or(Value::Null);
+            let new = after.get(field).cloned().unwrap_or(Value::Null);
+
+            (old != new).then(|| FieldChange {
+                field: field.clone(),
+                before: old,
+                after: new,
+            })
+        })
+        .collect()
+}
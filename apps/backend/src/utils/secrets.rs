@@ -0,0 +1,858 @@
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+use aes_gcm::{Aes256Gcm, Nonce};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::Utc;
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use sqlx::{PgConnection, PgPool};
+use std::collections::{BTreeSet, HashMap};
+use uuid::Uuid;
+
+use crate::config::SecretsConfig;
+use crate::error::AppError;
+use crate::models::{AuthConfig, ResolvedRequest, SecretKeyRotationResponse};
+use crate::utils::redact::REDACTED;
+
+const NONCE_LEN: usize = 12;
+const PLACEHOLDER_START: &str = "{{secrets.";
+const PLACEHOLDER_END: &str = "}}";
+
+// Shorter values are left alone in responses, masking them would garble unrelated text
+const MIN_MASKED_LENGTH: usize = 4;
+
+struct MasterKey {
+    id: String, // Start of the SHA-256 of the key, stored next to what it wrapped
+    cipher: Aes256Gcm,
+}
+
+// Encrypts secrets with the data key of their workspace, data keys are wrapped with the master key
+pub struct SecretVault {
+    master_key: MasterKey,
+    previous_master_keys: Vec<MasterKey>,
+}
+
+// The unwrapped data key of a workspace
+struct DataKey {
+    version: i32,
+    cipher: Aes256Gcm,
+}
+
+impl SecretVault {
+    // Nothing to build without a master key, secrets are turned off then
+    pub fn from_config(config: &SecretsConfig) -> Result<Option<Self>, String> {
+        let Some(master_key) = config.master_key.as_deref().filter(|key| !key.is_empty()) else {
+            return Ok(None);
+        };
+
+        Ok(Some(SecretVault {
+            master_key: parse_master_key(master_key)?,
+            previous_master_keys: config
+                .previous_master_keys
+                .iter()
+                .map(|key| parse_master_key(key))
+                .collect::<Result<_, _>>()?,
+        }))
+    }
+
+    // Wrap the data keys left over from previous master keys with the current one
+    pub async fn rewrap_data_keys(&self, pool: &PgPool) -> Result<u64, AppError> {
+        let keys = sqlx::query!(
+            r#"
+            SELECT workspace_id, master_key_id, wrapped_key FROM workspace_keys
+            WHERE master_key_id <> $1
+            "#,
+            self.master_key.id
+        )
+        .fetch_all(pool)
+        .await?;
+
+        let mut rewrapped = 0;
+        for key in keys {
+            let master_key = self
+                .previous_master_keys
+                .iter()
+                .find(|master_key| master_key.id == key.master_key_id)
+                .ok_or_else(|| unknown_master_key(&key.master_key_id))?;
+            let data_key = open(
+                &master_key.cipher,
+                &key.wrapped_key,
+                key.workspace_id.as_bytes(),
+            )?;
+
+            sqlx::query!(
+                r#"
+                UPDATE workspace_keys SET master_key_id = $2, wrapped_key = $3
+                WHERE workspace_id = $1 AND master_key_id = $4
+                "#,
+                key.workspace_id,
+                self.master_key.id,
+                seal(
+                    &self.master_key.cipher,
+                    &data_key,
+                    key.workspace_id.as_bytes()
+                )?,
+                key.master_key_id
+            )
+            .execute(pool)
+            .await?;
+            rewrapped += 1;
+        }
+
+        Ok(rewrapped)
+    }
+
+    // Encrypt a secret value, returns the ciphertext and the data key version it used
+    pub async fn encrypt(
+        &self,
+        conn: &mut PgConnection,
+        workspace_id: Uuid,
+        secret_id: Uuid,
+        value: &str,
+    ) -> Result<(Vec<u8>, i32), AppError> {
+        let data_key = match self.data_key(conn, workspace_id).await? {
+            Some(data_key) => data_key,
+            None => self.create_data_key(conn, workspace_id).await?,
+        };
+        let ciphertext = seal(&data_key.cipher, value.as_bytes(), secret_id.as_bytes())?;
+
+        Ok((ciphertext, data_key.version))
+    }
+
+    // Decrypt something encrypted for the given row, only meant for sending a request
+    pub async fn decrypt(
+        &self,
+        conn: &mut PgConnection,
+        workspace_id: Uuid,
+        id: Uuid,
+        ciphertext: &[u8],
+    ) -> Result<String, AppError> {
+        let data_key = self
+            .data_key(conn, workspace_id)
+            .await?
+            .ok_or_else(|| AppError::InternalServerError("Missing workspace key".to_string()))?;
+        let value = open(&data_key.cipher, ciphertext, id.as_bytes())?;
+
+        String::from_utf8(value).map_err(|e| AppError::InternalServerError(e.to_string()))
+    }
+
+    // Decrypt the named secrets of the workspace, only meant for sending a request
+    pub async fn reveal(
+        &self,
+        conn: &mut PgConnection,
+        workspace_id: Uuid,
+        names: &BTreeSet<String>,
+    ) -> Result<HashMap<String, String>, AppError> {
+        let names: Vec<String> = names.iter().cloned().collect();
+        let secrets = sqlx::query!(
+            r#"
+            SELECT id, name, ciphertext FROM secrets
+            WHERE workspace_id = $1 AND name = ANY($2)
+            "#,
+            workspace_id,
+            &names
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        if let Some(missing) = names
+            .iter()
+            .find(|name| !secrets.iter().any(|secret| &secret.name == *name))
+        {
+            return Err(AppError::BadRequestError(format!(
+                "The secret {} does not exist in this workspace",
+                missing
+            )));
+        }
+
+        let data_key = self
+            .data_key(conn, workspace_id)
+            .await?
+            .ok_or_else(|| AppError::InternalServerError("Missing workspace key".to_string()))?;
+
+        secrets
+            .into_iter()
+            .map(|secret| {
+                let value = open(&data_key.cipher, &secret.ciphertext, secret.id.as_bytes())?;
+                let value = String::from_utf8(value)
+                    .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+                Ok((secret.name, value))
+            })
+            .collect()
+    }
+
+    // Replace the data key of the workspace and encrypt every secret and client certificate
+    // again with the new one
+    pub async fn rotate_data_key(
+        &self,
+        conn: &mut PgConnection,
+        workspace_id: Uuid,
+    ) -> Result<SecretKeyRotationResponse, AppError> {
+        let Some(current) = self.data_key(conn, workspace_id).await? else {
+            let created = self.create_data_key(conn, workspace_id).await?;
+            return Ok(SecretKeyRotationResponse {
+                key_version: created.version,
+                secrets: 0,
+                client_certificates: 0,
+            });
+        };
+
+        let key_bytes = Aes256Gcm::generate_key(OsRng);
+        let next = DataKey {
+            version: current.version + 1,
+            cipher: Aes256Gcm::new(&key_bytes),
+        };
+
+        let secrets = sqlx::query!(
+            "SELECT id, ciphertext FROM secrets WHERE workspace_id = $1 FOR UPDATE",
+            workspace_id
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        for secret in &secrets {
+            let value = open(&current.cipher, &secret.ciphertext, secret.id.as_bytes())?;
+            sqlx::query!(
+                "UPDATE secrets SET ciphertext = $2, key_version = $3 WHERE id = $1",
+                secret.id,
+                seal(&next.cipher, &value, secret.id.as_bytes())?,
+                next.version
+            )
+            .execute(&mut *conn)
+            .await?;
+        }
+
+        let certificates = sqlx::query!(
+            "SELECT id, ciphertext FROM client_certificates WHERE workspace_id = $1 FOR UPDATE",
+            workspace_id
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        for certificate in &certificates {
+            let value = open(
+                &current.cipher,
+                &certificate.ciphertext,
+                certificate.id.as_bytes(),
+            )?;
+            sqlx::query!(
+                "UPDATE client_certificates SET ciphertext = $2, key_version = $3 WHERE id = $1",
+                certificate.id,
+                seal(&next.cipher, &value, certificate.id.as_bytes())?,
+                next.version
+            )
+            .execute(&mut *conn)
+            .await?;
+        }
+
+        sqlx::query!(
+            r#"
+            UPDATE workspace_keys
+            SET version = $2, master_key_id = $3, wrapped_key = $4, rotated_at = $5
+            WHERE workspace_id = $1
+            "#,
+            workspace_id,
+            next.version,
+            self.master_key.id,
+            seal(
+                &self.master_key.cipher,
+                key_bytes.as_slice(),
+                workspace_id.as_bytes()
+            )?,
+            Utc::now().naive_utc()
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(SecretKeyRotationResponse {
+            key_version: next.version,
+            secrets: secrets.len(),
+            client_certificates: certificates.len(),
+        })
+    }
+
+    // The data key of the workspace, locked until the transaction ends
+    async fn data_key(
+        &self,
+        conn: &mut PgConnection,
+        workspace_id: Uuid,
+    ) -> Result<Option<DataKey>, AppError> {
+        let Some(key) = sqlx::query!(
+            r#"
+            SELECT version, master_key_id, wrapped_key FROM workspace_keys
+            WHERE workspace_id = $1
+            FOR UPDATE
+            "#,
+            workspace_id
+        )
+        .fetch_optional(&mut *conn)
+        .await?
+        else {
+            return Ok(None);
+        };
+
+        let master_key = std::iter::once(&self.master_key)
+            .chain(&self.previous_master_keys)
+            .find(|master_key| master_key.id == key.master_key_id)
+            .ok_or_else(|| unknown_master_key(&key.master_key_id))?;
+        let key_bytes = open(
+            &master_key.cipher,
+            &key.wrapped_key,
+            workspace_id.as_bytes(),
+        )?;
+
+        Ok(Some(DataKey {
+            version: key.version,
+            cipher: Aes256Gcm::new_from_slice(&key_bytes)
+                .map_err(|e| AppError::InternalServerError(e.to_string()))?,
+        }))
+    }
+
+    async fn create_data_key(
+        &self,
+        conn: &mut PgConnection,
+        workspace_id: Uuid,
+    ) -> Result<DataKey, AppError> {
+        let key_bytes = Aes256Gcm::generate_key(OsRng);
+
+        let created = sqlx::query!(
+            r#"
+            INSERT INTO workspace_keys (workspace_id, version, master_key_id, wrapped_key, created_at)
+            VALUES ($1, 1, $2, $3, $4)
+            ON CONFLICT (workspace_id) DO NOTHING
+            "#,
+            workspace_id,
+            self.master_key.id,
+            seal(
+                &self.master_key.cipher,
+                key_bytes.as_slice(),
+                workspace_id.as_bytes()
+            )?,
+            Utc::now().naive_utc()
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        // Another request created the key first, use that one so both encrypt with it
+        if created.rows_affected() == 0 {
+            return self
+                .data_key(conn, workspace_id)
+                .await?
+                .ok_or_else(|| AppError::InternalServerError("Missing workspace key".to_string()));
+        }
+
+        Ok(DataKey {
+            version: 1,
+            cipher: Aes256Gcm::new(&key_bytes),
+        })
+    }
+}
+
+fn parse_master_key(encoded: &str) -> Result<MasterKey, String> {
+    let bytes = STANDARD
+        .decode(encoded.trim())
+        .map_err(|e| format!("Master keys have to be base64 encoded: {}", e))?;
+    let cipher = Aes256Gcm::new_from_slice(&bytes)
+        .map_err(|_| "Master keys have to be 32 bytes long".to_string())?;
+    let id = format!("{:x}", Sha256::digest(&bytes))[..16].to_string();
+
+    Ok(MasterKey { id, cipher })
+}
+
+pub fn secrets_not_configured() -> AppError {
+    AppError::NotFoundError("Secret storage is not configured".to_string())
+}
+
+fn unknown_master_key(id: &str) -> AppError {
+    AppError::InternalServerError(format!(
+        "The master key {} is not configured, add it to secrets.previous_master_keys",
+        id
+    ))
+}
+
+// A fresh nonce followed by the ciphertext, `aad` ties it to the row it is stored in
+fn seal(cipher: &Aes256Gcm, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>, AppError> {
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(
+            &nonce,
+            Payload {
+                msg: plaintext,
+                aad,
+            },
+        )
+        .map_err(|_| AppError::InternalServerError("Encryption failed".to_string()))?;
+
+    Ok([nonce.as_slice(), &ciphertext].concat())
+}
+
+fn open(cipher: &Aes256Gcm, sealed: &[u8], aad: &[u8]) -> Result<Vec<u8>, AppError> {
+    if sealed.len() < NONCE_LEN {
+        return Err(AppError::InternalServerError(
+            "Stored ciphertext is too short".to_string(),
+        ));
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+
+    cipher
+        .decrypt(
+            Nonce::from_slice(nonce),
+            Payload {
+                msg: ciphertext,
+                aad,
+            },
+        )
+        .map_err(|_| AppError::InternalServerError("Decryption failed".to_string()))
+}
+
+// How a secret is written into a request
+pub fn placeholder(name: &str) -> String {
+    format!("{}{}{}", PLACEHOLDER_START, name, PLACEHOLDER_END)
+}
+
+// Names of the secrets the request refers to
+pub fn secret_names(request: &ResolvedRequest) -> BTreeSet<String> {
+    let mut names = BTreeSet::new();
+    let mut collect = |text: &str| {
+        let mut rest = text;
+        while let Some(start) = rest.find(PLACEHOLDER_START) {
+            rest = &rest[start + PLACEHOLDER_START.len()..];
+            if let Some(end) = rest.find(PLACEHOLDER_END) {
+                names.insert(rest[..end].to_string());
+                rest = &rest[end..];
+            }
+        }
+    };
+
+    let mut texts = vec![request.url.as_str()];
+    texts.extend(request.headers.values().filter_map(Value::as_str));
+    texts.extend(request.params.values().filter_map(Value::as_str));
+    texts.extend(auth_values(request.auth.as_ref()));
+    texts.iter().for_each(|text| collect(text));
+    if let Some(body) = &request.body {
+        walk_strings(body, &mut |text| collect(text));
+    }
+
+    names
+}
+
+// The request with its placeholders replaced by the secret values
+pub fn fill_secrets(
+    request: &ResolvedRequest,
+    secrets: &HashMap<String, String>,
+) -> ResolvedRequest {
+    let fill = |text: &str| {
+        secrets
+            .iter()
+            .fold(text.to_string(), |text, (name, value)| {
+                text.replace(&placeholder(name), value)
+            })
+    };
+    let fill_map = |map: &Map<String, Value>| {
+        map.iter()
+            .map(|(key, value)| (key.clone(), map_strings(value, &fill)))
+            .collect()
+    };
+
+    ResolvedRequest {
+        url: fill(&request.url),
+        headers: fill_map(&request.headers),
+        params: fill_map(&request.params),
+        body: request.body.as_ref().map(|body| map_strings(body, &fill)),
+        auth: request.auth.as_ref().map(|auth| match auth {
+            AuthConfig::None => AuthConfig::None,
+            AuthConfig::Bearer { token } => AuthConfig::Bearer { token: fill(token) },
+            AuthConfig::Basic { username, password } => AuthConfig::Basic {
+                username: fill(username),
+                password: password.as_deref().map(fill),
+            },
+            AuthConfig::ApiKey {
+                key,
+                value,
+                location,
+            } => AuthConfig::ApiKey {
+                key: fill(key),
+                value: fill(value),
+                location: *location,
+            },
+        }),
+        ..request.clone()
+    }
+}
+
+// Hide secret values that come back in a response, like from an echo endpoint
+pub fn mask_secrets(text: &str, secrets: &HashMap<String, String>) -> String {
+    secrets
+        .values()
+        .filter(|value| value.len() >= MIN_MASKED_LENGTH)
+        .fold(text.to_string(), |text, value| {
+            text.replace(value, REDACTED)
+        })
+}
+
+pub fn mask_secrets_in_value(value: &Value, secrets: &HashMap<String, String>) -> Value {
+    map_strings(value, &|text| mask_secrets(text, secrets))
+}
+
+fn auth_values(auth: Option<&AuthConfig>) -> Vec<&str> {
+    match auth {
+        Some(AuthConfig::Bearer { token }) => vec![token],
+        Some(AuthConfig::Basic { username, password }) => {
+            let mut values = vec![username.as_str()];
+            values.extend(password.as_deref());
+            values
+        }
+        Some(AuthConfig::ApiKey { key, value, .. }) => vec![key, value],
+        Some(AuthConfig::None) | None => Vec::new(),
+    }
+}
+
+fn walk_strings(value: &Value, visit: &mut impl FnMut(&str)) {
+    match value {
+        Value::String(text) => visit(text),
+        Value::Array(items) => items.iter().for_each(|item| walk_strings(item, visit)),
+        Value::Object(fields) => fields.values().for_each(|field| walk_strings(field, visit)),
+        _ => {}
+    }
+}
+
+fn map_strings(value: &Value, map: &impl Fn(&str) -> String) -> Value {
+    match value {
+        Value::String(text) => Value::String(map(text)),
+        Value::Array(items) => {
+            Value::Array(items.iter().map(|item| map_strings(item, map)).collect())
+        }
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(key, field)| (key.clone(), map_strings(field, map)))
+                .collect(),
+        ),
+        _ => value.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::tests::TestDatabase;
+
+    fn master_key(byte: u8) -> String {
+        STANDARD.encode([byte; 32])
+    }
+
+    fn vault(master_key: String, previous_master_keys: Vec<String>) -> SecretVault {
+        SecretVault::from_config(&SecretsConfig {
+            master_key: Some(master_key),
+            previous_master_keys,
+        })
+        .unwrap()
+        .unwrap()
+    }
+
+    fn cipher() -> Aes256Gcm {
+        Aes256Gcm::new(&Aes256Gcm::generate_key(OsRng))
+    }
+
+    async fn create_workspace(conn: &mut PgConnection) -> Uuid {
+        let workspace_id = Uuid::new_v4();
+        sqlx::query("INSERT INTO workspaces (id, name) VALUES ($1, 'Test')")
+            .bind(workspace_id)
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+        workspace_id
+    }
+
+    async fn insert_secret(
+        conn: &mut PgConnection,
+        vault: &SecretVault,
+        workspace_id: Uuid,
+        name: &str,
+        value: &str,
+    ) -> i32 {
+        let secret_id = Uuid::new_v4();
+        let (ciphertext, key_version) = vault
+            .encrypt(conn, workspace_id, secret_id, value)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO secrets (id, workspace_id, name, ciphertext, key_version) VALUES ($1, $2, $3, $4, $5)",
+        )
+        .bind(secret_id)
+        .bind(workspace_id)
+        .bind(name)
+        .bind(ciphertext)
+        .bind(key_version)
+        .execute(&mut *conn)
+        .await
+        .unwrap();
+        key_version
+    }
+
+    fn names(names: &[&str]) -> BTreeSet<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn sealed_values_open_with_the_same_key_and_aad() {
+        let cipher = cipher();
+        let sealed = seal(&cipher, b"hunter22", b"row").unwrap();
+
+        assert_ne!(&sealed[NONCE_LEN..], b"hunter22");
+        assert_eq!(open(&cipher, &sealed, b"row").unwrap(), b"hunter22");
+    }
+
+    #[test]
+    fn every_seal_uses_a_fresh_nonce() {
+        let cipher = cipher();
+        assert_ne!(
+            seal(&cipher, b"hunter22", b"row").unwrap(),
+            seal(&cipher, b"hunter22", b"row").unwrap()
+        );
+    }
+
+    #[test]
+    fn opening_with_another_aad_fails() {
+        let cipher = cipher();
+        let sealed = seal(&cipher, b"hunter22", b"row").unwrap();
+
+        assert!(open(&cipher, &sealed, b"other row").is_err());
+    }
+
+    #[test]
+    fn opening_with_another_key_fails() {
+        let sealed = seal(&cipher(), b"hunter22", b"row").unwrap();
+
+        assert!(open(&cipher(), &sealed, b"row").is_err());
+    }
+
+    #[test]
+    fn tampered_or_truncated_ciphertext_fails() {
+        let cipher = cipher();
+        let mut sealed = seal(&cipher, b"hunter22", b"row").unwrap();
+
+        assert!(open(&cipher, &sealed[..NONCE_LEN - 1], b"row").is_err());
+        *sealed.last_mut().unwrap() ^= 1;
+        assert!(open(&cipher, &sealed, b"row").is_err());
+    }
+
+    #[test]
+    fn master_keys_have_to_be_32_base64_bytes() {
+        assert!(parse_master_key("not base64!").is_err());
+        assert!(parse_master_key(&STANDARD.encode([1; 16])).is_err());
+
+        let key = parse_master_key(&master_key(1)).unwrap();
+        assert_eq!(key.id.len(), 16);
+        assert_eq!(key.id, parse_master_key(&master_key(1)).unwrap().id);
+        assert_ne!(key.id, parse_master_key(&master_key(2)).unwrap().id);
+    }
+
+    #[test]
+    fn no_master_key_turns_secrets_off() {
+        for master_key in [None, Some(String::new())] {
+            let config = SecretsConfig {
+                master_key,
+                previous_master_keys: Vec::new(),
+            };
+            assert!(SecretVault::from_config(&config).unwrap().is_none());
+        }
+    }
+
+    #[test]
+    fn secrets_in_a_response_are_masked() {
+        let secrets = HashMap::from([
+            ("token".to_string(), "s3cr3t-token".to_string()),
+            ("pin".to_string(), "1234".to_string()),
+        ]);
+
+        assert_eq!(
+            mask_secrets("token=s3cr3t-token&pin=1234", &secrets),
+            format!("token={}&pin={}", REDACTED, REDACTED)
+        );
+    }
+
+    #[test]
+    fn short_secrets_are_not_masked() {
+        let secrets = HashMap::from([("id".to_string(), "abc".to_string())]);
+
+        assert_eq!(mask_secrets("abcdef abc", &secrets), "abcdef abc");
+    }
+
+    #[test]
+    fn secrets_are_masked_inside_json() {
+        let secrets = HashMap::from([("token".to_string(), "s3cr3t-token".to_string())]);
+        let value = serde_json::json!({ "echo": ["Bearer s3cr3t-token"], "count": 1 });
+
+        assert_eq!(
+            mask_secrets_in_value(&value, &secrets),
+            serde_json::json!({ "echo": [format!("Bearer {}", REDACTED)], "count": 1 })
+        );
+    }
+
+    #[tokio::test]
+    async fn encrypted_secrets_are_revealed() {
+        let database = TestDatabase::create().await;
+        let pool = &database.pool;
+        {
+            let vault = vault(master_key(1), Vec::new());
+            let mut conn = pool.acquire().await.unwrap();
+            let workspace_id = create_workspace(&mut conn).await;
+
+            let key_version =
+                insert_secret(&mut conn, &vault, workspace_id, "token", "s3cr3t").await;
+            let revealed = vault
+                .reveal(&mut conn, workspace_id, &names(&["token"]))
+                .await
+                .unwrap();
+
+            assert_eq!(key_version, 1);
+            assert_eq!(revealed["token"], "s3cr3t");
+            assert!(vault
+                .reveal(&mut conn, workspace_id, &names(&["missing"]))
+                .await
+                .is_err());
+        }
+        database.drop().await;
+    }
+
+    #[tokio::test]
+    async fn concurrent_first_encryptions_share_one_data_key() {
+        let database = TestDatabase::create().await;
+        let pool = &database.pool;
+        {
+            let vault = vault(master_key(1), Vec::new());
+            let workspace_id = create_workspace(&mut pool.acquire().await.unwrap()).await;
+
+            // Both transactions see no key, the second insert waits for the first to commit
+            let mut first = pool.begin().await.unwrap();
+            let mut second = pool.begin().await.unwrap();
+            assert!(vault
+                .data_key(&mut first, workspace_id)
+                .await
+                .unwrap()
+                .is_none());
+            assert!(vault
+                .data_key(&mut second, workspace_id)
+                .await
+                .unwrap()
+                .is_none());
+            let first_key = vault
+                .create_data_key(&mut first, workspace_id)
+                .await
+                .unwrap();
+            let second_key = tokio::spawn(async move {
+                let key = vault.create_data_key(&mut second, workspace_id).await;
+                second.commit().await.unwrap();
+                key
+            });
+            let sealed = seal(&first_key.cipher, b"hunter22", b"row").unwrap();
+            first.commit().await.unwrap();
+
+            let second_key = second_key.await.unwrap().unwrap();
+            assert_eq!(second_key.version, 1);
+            assert_eq!(
+                open(&second_key.cipher, &sealed, b"row").unwrap(),
+                b"hunter22"
+            );
+        }
+        database.drop().await;
+    }
+
+    #[tokio::test]
+    async fn rotating_the_data_key_encrypts_secrets_again() {
+        let database = TestDatabase::create().await;
+        let pool = &database.pool;
+        {
+            let vault = vault(master_key(1), Vec::new());
+            let mut conn = pool.acquire().await.unwrap();
+            let workspace_id = create_workspace(&mut conn).await;
+            insert_secret(&mut conn, &vault, workspace_id, "token", "s3cr3t").await;
+            let old_key = vault
+                .data_key(&mut conn, workspace_id)
+                .await
+                .unwrap()
+                .unwrap();
+
+            let rotation = vault
+                .rotate_data_key(&mut conn, workspace_id)
+                .await
+                .unwrap();
+            assert_eq!(rotation.key_version, 2);
+            assert_eq!(rotation.secrets, 1);
+
+            let secret = sqlx::query!(
+                "SELECT id, ciphertext, key_version FROM secrets WHERE workspace_id = $1",
+                workspace_id
+            )
+            .fetch_one(&mut *conn)
+            .await
+            .unwrap();
+            assert_eq!(secret.key_version, 2);
+            // The old data key no longer opens what was encrypted again
+            assert!(open(&old_key.cipher, &secret.ciphertext, secret.id.as_bytes()).is_err());
+
+            let revealed = vault
+                .reveal(&mut conn, workspace_id, &names(&["token"]))
+                .await
+                .unwrap();
+            assert_eq!(revealed["token"], "s3cr3t");
+
+            // New secrets use the rotated key
+            let key_version =
+                insert_secret(&mut conn, &vault, workspace_id, "other", "v4lue").await;
+            assert_eq!(key_version, 2);
+        }
+        database.drop().await;
+    }
+
+    #[tokio::test]
+    async fn rotating_without_a_data_key_creates_one() {
+        let database = TestDatabase::create().await;
+        let pool = &database.pool;
+        {
+            let vault = vault(master_key(1), Vec::new());
+            let mut conn = pool.acquire().await.unwrap();
+            let workspace_id = create_workspace(&mut conn).await;
+
+            let rotation = vault
+                .rotate_data_key(&mut conn, workspace_id)
+                .await
+                .unwrap();
+            assert_eq!(rotation.key_version, 1);
+            assert_eq!(rotation.secrets, 0);
+        }
+        database.drop().await;
+    }
+
+    #[tokio::test]
+    async fn data_keys_are_wrapped_again_with_a_new_master_key() {
+        let database = TestDatabase::create().await;
+        let pool = &database.pool;
+        {
+            let old_vault = vault(master_key(1), Vec::new());
+            let mut conn = pool.acquire().await.unwrap();
+            let workspace_id = create_workspace(&mut conn).await;
+            insert_secret(&mut conn, &old_vault, workspace_id, "token", "s3cr3t").await;
+
+            // Without the previous master key the data key cannot be unwrapped
+            let unaware_vault = vault(master_key(2), Vec::new());
+            assert!(unaware_vault.rewrap_data_keys(pool).await.is_err());
+
+            let new_vault = vault(master_key(2), vec![master_key(1)]);
+            assert_eq!(new_vault.rewrap_data_keys(pool).await.unwrap(), 1);
+            assert_eq!(new_vault.rewrap_data_keys(pool).await.unwrap(), 0);
+
+            // Only the new master key is needed from now on
+            let revealed = unaware_vault
+                .reveal(&mut conn, workspace_id, &names(&["token"]))
+                .await
+                .unwrap();
+            assert_eq!(revealed["token"], "s3cr3t");
+            assert!(old_vault
+                .reveal(&mut conn, workspace_id, &names(&["token"]))
+                .await
+                .is_err());
+        }
+        database.drop().await;
+    }
+}
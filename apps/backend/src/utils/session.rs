@@ -0,0 +1,167 @@
+use chrono::{Duration, NaiveDateTime, Utc};
+use sqlx::{PgConnection, PgExecutor};
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::error::AppError;
+use crate::models::TokenResponse;
+use crate::utils::audit::RequestOrigin;
+use crate::utils::jwt::JwtKeys;
+use crate::utils::token::{generate_token, hash_token};
+
+// Start a session for the user and hand out its first pair of tokens
+pub async fn start_session(
+    conn: &mut PgConnection,
+    config: &Config,
+    keys: &JwtKeys,
+    user_id: Uuid,
+    origin: &RequestOrigin,
+) -> Result<TokenResponse, AppError> {
+    let now = Utc::now().naive_utc();
+
+    let session = sqlx::query!(
+        r#"
+        INSERT INTO sessions
+            (id, user_id, ip_address, user_agent, expires_at, last_used_at, created_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $6)
+        RETURNING id
+        "#,
+        Uuid::new_v4(),
+        user_id,
+        origin.ip_address,
+        origin.user_agent,
+        now + Duration::days(config.refresh_token_expires_days),
+        now
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    issue_tokens(conn, config, keys, user_id, session.id).await
+}
+
+// Hand out a new pair of tokens for a session and push its expiry back
+pub async fn issue_tokens(
+    conn: &mut PgConnection,
+    config: &Config,
+    keys: &JwtKeys,
+    user_id: Uuid,
+    session_id: Uuid,
+) -> Result<TokenResponse, AppError> {
+    let now = Utc::now().naive_utc();
+
+    sqlx::query!(
+        r#"
+        UPDATE sessions
+        SET expires_at = $2, last_used_at = $3
+        WHERE id = $1
+        "#,
+        session_id,
+        now + Duration::days(config.refresh_token_expires_days),
+        now
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    // Only the hash of the refresh token is kept
+    let refresh_token = generate_token();
+    sqlx::query!(
+        r#"
+        INSERT INTO refresh_tokens (id, session_id, token_hash, created_at)
+        VALUES ($1, $2, $3, $4)
+        "#,
+        Uuid::new_v4(),
+        session_id,
+        hash_token(&refresh_token),
+        now
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    let token = keys.encode_access_token(user_id, session_id)?;
+
+    Ok(TokenResponse {
+        token,
+        refresh_token,
+        expires_in: keys.expires_in().num_seconds(),
+    })
+}
+
+// Whether access tokens of the session are still accepted
+pub async fn is_session_active<'c>(
+    executor: impl PgExecutor<'c>,
+    session_id: Uuid,
+    user_id: Uuid,
+) -> Result<bool, AppError> {
+    let session = sqlx::query!(
+        r#"
+        SELECT id FROM sessions
+        WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL AND expires_at > $3
+        "#,
+        session_id,
+        user_id,
+        Utc::now().naive_utc()
+    )
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(session.is_some())
+}
+
+// Revoke the open sessions of the user, all of them when no session is given
+pub async fn revoke_sessions<'c>(
+    executor: impl PgExecutor<'c>,
+    user_id: Uuid,
+    session_id: Option<Uuid>,
+) -> Result<u64, AppError> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE sessions
+        SET revoked_at = $3
+        WHERE user_id = $1 AND ($2::uuid IS NULL OR id = $2) AND revoked_at IS NULL
+        "#,
+        user_id,
+        session_id,
+        Utc::now().naive_utc()
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+// Revoke every session of the user except the one making the request
+pub async fn revoke_other_sessions<'c>(
+    executor: impl PgExecutor<'c>,
+    user_id: Uuid,
+    session_id: Uuid,
+) -> Result<u64, AppError> {
+    let result = sqlx::query!(
+        r#"
+        UPDATE sessions
+        SET revoked_at = $3
+        WHERE user_id = $1 AND id <> $2 AND revoked_at IS NULL
+        "#,
+        user_id,
+        session_id,
+        Utc::now().naive_utc()
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected())
+}
+
+// Delete sessions that expired or were revoked, their tokens go with them
+pub async fn purge_sessions<'c>(
+    executor: impl PgExecutor<'c>,
+    now: NaiveDateTime,
+) -> Result<u64, AppError> {
+    let result = sqlx::query!(
+        "DELETE FROM sessions WHERE expires_at <= $1 OR revoked_at IS NOT NULL",
+        now
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(result.rows_affected())
+}
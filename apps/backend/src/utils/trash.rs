@@ -0,0 +1,112 @@
+use chrono::NaiveDateTime;
+use sqlx::{PgConnection, PgPool};
+use uuid::Uuid;
+
+use crate::error::AppError;
+
+// Permanently delete a trashed request and its history, returns how many requests went
+pub async fn purge_request(conn: &mut PgConnection, request_id: Uuid) -> Result<u64, AppError> {
+    sqlx::query!(
+        r#"
+        DELETE FROM revisions
+        WHERE entity_type = 'request' AND entity_id = $1
+        "#,
+        request_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    let deleted = sqlx::query!(
+        r#"
+        DELETE FROM requests
+        WHERE id = $1
+        "#,
+        request_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(deleted.rows_affected())
+}
+
+// Permanently delete a trashed collection, its requests and their history,
+// returns how many collections and requests went
+pub async fn purge_collection(
+    conn: &mut PgConnection,
+    collection_id: Uuid,
+) -> Result<u64, AppError> {
+    sqlx::query!(
+        r#"
+        DELETE FROM revisions
+        WHERE (entity_type = 'collection' AND entity_id = $1)
+           OR (entity_type = 'request' AND entity_id IN (
+                SELECT id FROM requests WHERE collection_id = $1
+           ))
+        "#,
+        collection_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    let requests = sqlx::query!(
+        r#"
+        DELETE FROM requests
+        WHERE collection_id = $1
+        "#,
+        collection_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    let collections = sqlx::query!(
+        r#"
+        DELETE FROM collections
+        WHERE id = $1
+        "#,
+        collection_id
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(requests.rows_affected() + collections.rows_affected())
+}
+
+// Purge everything that has been in the trash since before the cutoff, returns how many
+// collections and requests were deleted, the requests of purged collections included
+pub async fn purge_expired(pool: &PgPool, cutoff: NaiveDateTime) -> Result<u64, AppError> {
+    let mut tx = pool.begin().await?;
+    let mut purged = 0;
+
+    let collection_ids = sqlx::query!(
+        r#"
+        SELECT id FROM collections
+        WHERE deleted_at < $1
+        "#,
+        cutoff
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    for collection in &collection_ids {
+        purged += purge_collection(&mut tx, collection.id).await?;
+    }
+
+    // Requests of purged collections are gone by now, only the remaining ones are left
+    let request_ids = sqlx::query!(
+        r#"
+        SELECT id FROM requests
+        WHERE deleted_at < $1
+        "#,
+        cutoff
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    for request in &request_ids {
+        purged += purge_request(&mut tx, request.id).await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(purged)
+}
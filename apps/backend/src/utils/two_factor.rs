@@ -0,0 +1,440 @@
+use chrono::{Duration, Utc};
+use rand::Rng;
+use sqlx::{PgConnection, PgExecutor};
+use totp_rs::{Algorithm, Secret, TOTP};
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::models::User;
+use crate::utils::token::{generate_token, hash_token};
+
+// Shown next to the account in authenticator apps
+const ISSUER: &str = "Endpoint";
+const STEP_SECONDS: u64 = 30;
+
+// Wrong codes a login challenge takes before the password has to be entered again
+pub const MAX_CHALLENGE_ATTEMPTS: i32 = 5;
+
+const RECOVERY_CODE_COUNT: usize = 10;
+// Lowercase letters and digits that cannot be mistaken for one another
+const RECOVERY_CODE_ALPHABET: &[u8] = b"abcdefghjkmnpqrstuvwxyz23456789";
+
+// How the second step of a login was passed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecondFactor {
+    Totp,
+    RecoveryCode,
+}
+
+impl SecondFactor {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SecondFactor::Totp => "totp",
+            SecondFactor::RecoveryCode => "recovery_code",
+        }
+    }
+}
+
+// New random secret, base32 encoded like authenticator apps expect it
+pub fn generate_secret() -> String {
+    Secret::generate_secret().to_encoded().to_string()
+}
+
+// Link that authenticator apps read from a QR code
+pub fn otpauth_uri(secret: &str, email: &str) -> Result<String, AppError> {
+    Ok(totp(secret, email)?.get_url())
+}
+
+// Time step the code belongs to, the ones right before and after are accepted for clock drift
+pub fn matching_step(secret: &str, email: &str, code: &str) -> Result<Option<i64>, AppError> {
+    matching_step_at(secret, email, code, Utc::now().timestamp() as u64)
+}
+
+fn matching_step_at(
+    secret: &str,
+    email: &str,
+    code: &str,
+    now: u64,
+) -> Result<Option<i64>, AppError> {
+    let code: String = code.chars().filter(|c| !c.is_whitespace()).collect();
+    if code.len() != 6 || !code.chars().all(|c| c.is_ascii_digit()) {
+        return Ok(None);
+    }
+
+    let totp = totp(secret, email)?;
+    let current = now / STEP_SECONDS;
+
+    Ok((current - 1..=current + 1)
+        .find(|step| totp.check(&code, step * STEP_SECONDS))
+        .map(|step| step as i64))
+}
+
+fn totp(secret: &str, email: &str) -> Result<TOTP, AppError> {
+    let secret = Secret::Encoded(secret.to_string())
+        .to_bytes()
+        .map_err(|e| AppError::InternalServerError(format!("Invalid TOTP secret: {:?}", e)))?;
+
+    TOTP::new(
+        Algorithm::SHA1,
+        6,
+        0,
+        STEP_SECONDS,
+        secret,
+        Some(ISSUER.to_string()),
+        email.to_string(),
+    )
+    .map_err(|e| AppError::InternalServerError(format!("Invalid TOTP settings: {}", e)))
+}
+
+// Whether the user has finished setting up two-factor authentication
+pub async fn has_two_factor<'c>(
+    executor: impl PgExecutor<'c>,
+    user_id: Uuid,
+) -> Result<bool, AppError> {
+    let user = sqlx::query!(
+        r#"SELECT totp_enabled_at IS NOT NULL AS "enabled!" FROM users WHERE id = $1"#,
+        user_id
+    )
+    .fetch_optional(executor)
+    .await?;
+
+    Ok(user.is_some_and(|user| user.enabled))
+}
+
+// Replace the recovery codes of the user, only their hashes are kept
+pub async fn replace_recovery_codes(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+) -> Result<Vec<String>, AppError> {
+    sqlx::query!("DELETE FROM recovery_codes WHERE user_id = $1", user_id)
+        .execute(&mut *conn)
+        .await?;
+
+    let now = Utc::now().naive_utc();
+    let codes: Vec<String> = (0..RECOVERY_CODE_COUNT)
+        .map(|_| generate_recovery_code())
+        .collect();
+
+    for code in &codes {
+        sqlx::query!(
+            r#"
+            INSERT INTO recovery_codes (id, user_id, code_hash, created_at)
+            VALUES ($1, $2, $3, $4)
+            "#,
+            Uuid::new_v4(),
+            user_id,
+            hash_recovery_code(code),
+            now
+        )
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(codes)
+}
+
+// Check a code from the app or an unused recovery code, either one works only once
+pub async fn verify_second_factor(
+    conn: &mut PgConnection,
+    user: &User,
+    code: &str,
+) -> Result<Option<SecondFactor>, AppError> {
+    let Some(secret) = user
+        .totp_secret
+        .as_deref()
+        .filter(|_| user.totp_enabled_at.is_some())
+    else {
+        return Ok(None);
+    };
+
+    if let Some(step) = matching_step(secret, &user.email, code)? {
+        // Codes at or before the last accepted step were already used
+        let accepted = sqlx::query!(
+            r#"
+            UPDATE users SET totp_last_step = $2
+            WHERE id = $1 AND (totp_last_step IS NULL OR totp_last_step < $2)
+            "#,
+            user.id,
+            step
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        return Ok((accepted.rows_affected() > 0).then_some(SecondFactor::Totp));
+    }
+
+    let recovery_code = sqlx::query!(
+        r#"
+        UPDATE recovery_codes SET used_at = $3
+        WHERE user_id = $1 AND code_hash = $2 AND used_at IS NULL
+        RETURNING id
+        "#,
+        user.id,
+        hash_recovery_code(code),
+        Utc::now().naive_utc()
+    )
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    Ok(recovery_code.map(|_| SecondFactor::RecoveryCode))
+}
+
+// Issue the token that stands in for the password during the second step of a login
+pub async fn create_login_challenge(
+    conn: &mut PgConnection,
+    user_id: Uuid,
+    expires_in: Duration,
+) -> Result<String, AppError> {
+    let now = Utc::now().naive_utc();
+
+    // A new login replaces the open challenge of the user, expired ones are dropped on the way
+    sqlx::query!(
+        "DELETE FROM login_challenges WHERE user_id = $1 OR expires_at <= $2",
+        user_id,
+        now
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    let token = generate_token();
+    sqlx::query!(
+        r#"
+        INSERT INTO login_challenges (id, user_id, token_hash, expires_at, created_at)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+        Uuid::new_v4(),
+        user_id,
+        hash_token(&token),
+        now + expires_in,
+        now
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(token)
+}
+
+// Two groups of five characters, like `k7m2p-xq9ra`
+fn generate_recovery_code() -> String {
+    let mut rng = rand::thread_rng();
+    let mut code: String = (0..10)
+        .map(|_| RECOVERY_CODE_ALPHABET[rng.gen_range(0..RECOVERY_CODE_ALPHABET.len())] as char)
+        .collect();
+    code.insert(5, '-');
+    code
+}
+
+// Case, spaces and the dash do not matter when a recovery code is typed in
+fn hash_recovery_code(code: &str) -> String {
+    let normalized: String = code
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    hash_token(&normalized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::tests::TestDatabase;
+
+    const EMAIL: &str = "alice@example.com";
+    // Halfway through a time step, so a second either way stays in it
+    const NOW: u64 = 58_333_333 * STEP_SECONDS + 15;
+
+    fn code_at(secret: &str, step: u64) -> String {
+        totp(secret, EMAIL).unwrap().generate(step * STEP_SECONDS)
+    }
+
+    async fn create_user(conn: &mut PgConnection, secret: Option<&str>) -> User {
+        sqlx::query_as!(
+            User,
+            r#"
+            INSERT INTO users (id, email, password_hash, name, totp_secret, totp_enabled_at)
+            VALUES ($1, $2, '', 'Alice', $3, $4)
+            RETURNING *
+            "#,
+            Uuid::new_v4(),
+            EMAIL,
+            secret,
+            secret.map(|_| Utc::now().naive_utc())
+        )
+        .fetch_one(&mut *conn)
+        .await
+        .unwrap()
+    }
+
+    #[test]
+    fn codes_of_the_current_and_neighbouring_steps_are_accepted() {
+        let secret = generate_secret();
+        let current = NOW / STEP_SECONDS;
+
+        for step in [current - 1, current, current + 1] {
+            let code = code_at(&secret, step);
+            assert_eq!(
+                matching_step_at(&secret, EMAIL, &code, NOW).unwrap(),
+                Some(step as i64)
+            );
+        }
+    }
+
+    #[test]
+    fn codes_further_off_are_refused() {
+        let secret = generate_secret();
+        let current = NOW / STEP_SECONDS;
+
+        for step in [current - 10, current - 2, current + 2, current + 10] {
+            let code = code_at(&secret, step);
+            // Another step can produce the same six digits now and then
+            if [current - 1, current, current + 1]
+                .iter()
+                .any(|near| code_at(&secret, *near) == code)
+            {
+                continue;
+            }
+            assert_eq!(matching_step_at(&secret, EMAIL, &code, NOW).unwrap(), None);
+        }
+    }
+
+    #[test]
+    fn spaces_in_codes_are_ignored() {
+        let secret = generate_secret();
+        let code = code_at(&secret, NOW / STEP_SECONDS);
+        let spaced = format!(" {} {} ", &code[..3], &code[3..]);
+
+        assert!(matching_step_at(&secret, EMAIL, &spaced, NOW)
+            .unwrap()
+            .is_some());
+    }
+
+    #[test]
+    fn malformed_codes_are_refused() {
+        let secret = generate_secret();
+
+        for code in ["", "12345", "1234567", "12a456", "١٢٣٤٥٦"] {
+            assert_eq!(
+                matching_step_at(&secret, EMAIL, code, NOW).unwrap(),
+                None,
+                "{:?} was accepted",
+                code
+            );
+        }
+    }
+
+    #[test]
+    fn recovery_codes_are_two_groups_of_five() {
+        let code = generate_recovery_code();
+
+        assert_eq!(code.len(), 11);
+        assert_eq!(code.as_bytes()[5], b'-');
+        assert!(code
+            .bytes()
+            .filter(|c| *c != b'-')
+            .all(|c| RECOVERY_CODE_ALPHABET.contains(&c)));
+    }
+
+    #[test]
+    fn recovery_codes_ignore_case_spaces_and_dashes() {
+        assert_eq!(
+            hash_recovery_code("K7M2P XQ9RA"),
+            hash_recovery_code("k7m2p-xq9ra")
+        );
+        assert_ne!(
+            hash_recovery_code("k7m2p-xq9rb"),
+            hash_recovery_code("k7m2p-xq9ra")
+        );
+    }
+
+    #[tokio::test]
+    async fn totp_codes_work_once() {
+        let database = TestDatabase::create().await;
+        {
+            let mut conn = database.pool.acquire().await.unwrap();
+            let secret = generate_secret();
+            let user = create_user(&mut conn, Some(&secret)).await;
+            let current = Utc::now().timestamp() as u64 / STEP_SECONDS;
+
+            let code = code_at(&secret, current);
+            assert_eq!(
+                verify_second_factor(&mut conn, &user, &code).await.unwrap(),
+                Some(SecondFactor::Totp)
+            );
+            assert_eq!(
+                verify_second_factor(&mut conn, &user, &code).await.unwrap(),
+                None
+            );
+
+            // A code of an earlier step is used up as well once a later one was accepted
+            let earlier = code_at(&secret, current - 1);
+            if earlier != code {
+                assert_eq!(
+                    verify_second_factor(&mut conn, &user, &earlier)
+                        .await
+                        .unwrap(),
+                    None
+                );
+            }
+        }
+        database.drop().await;
+    }
+
+    #[tokio::test]
+    async fn recovery_codes_work_once() {
+        let database = TestDatabase::create().await;
+        {
+            let mut conn = database.pool.acquire().await.unwrap();
+            let user = create_user(&mut conn, Some(&generate_secret())).await;
+            let old_codes = replace_recovery_codes(&mut conn, user.id).await.unwrap();
+            let codes = replace_recovery_codes(&mut conn, user.id).await.unwrap();
+            assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+
+            let typed = codes[0].to_uppercase().replace('-', " ");
+            assert_eq!(
+                verify_second_factor(&mut conn, &user, &typed)
+                    .await
+                    .unwrap(),
+                Some(SecondFactor::RecoveryCode)
+            );
+            assert_eq!(
+                verify_second_factor(&mut conn, &user, &codes[0])
+                    .await
+                    .unwrap(),
+                None
+            );
+            assert_eq!(
+                verify_second_factor(&mut conn, &user, &codes[1])
+                    .await
+                    .unwrap(),
+                Some(SecondFactor::RecoveryCode)
+            );
+
+            // Replaced codes no longer work
+            assert_eq!(
+                verify_second_factor(&mut conn, &user, &old_codes[2])
+                    .await
+                    .unwrap(),
+                None
+            );
+        }
+        database.drop().await;
+    }
+
+    #[tokio::test]
+    async fn nothing_passes_without_two_factor() {
+        let database = TestDatabase::create().await;
+        {
+            let mut conn = database.pool.acquire().await.unwrap();
+            let user = create_user(&mut conn, None).await;
+            let codes = replace_recovery_codes(&mut conn, user.id).await.unwrap();
+
+            assert_eq!(
+                verify_second_factor(&mut conn, &user, &codes[0])
+                    .await
+                    .unwrap(),
+                None
+            );
+        }
+        database.drop().await;
+    }
+}
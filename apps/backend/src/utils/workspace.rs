@@ -0,0 +1,149 @@
+use sqlx::{PgConnection, PgExecutor};
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::models::{Permission, Workspace, WorkspaceRole};
+
+// Name of the workspace created with every account
+pub const PERSONAL_WORKSPACE_NAME: &str = "Personal";
+
+// Create a workspace with the user as its owner
+pub async fn insert_workspace(
+    conn: &mut PgConnection,
+    name: &str,
+    description: Option<&str>,
+    is_personal: bool,
+    user_id: Uuid,
+) -> Result<Workspace, AppError> {
+    let now = chrono::Utc::now().naive_utc();
+
+    let workspace = sqlx::query_as!(
+        Workspace,
+        r#"
+        INSERT INTO workspaces (id, name, description, is_personal, created_at, updated_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING id, name, description, is_personal, created_at, updated_at, require_2fa, proxy
+        "#,
+        Uuid::new_v4(),
+        name,
+        description,
+        is_personal,
+        now,
+        now
+    )
+    .fetch_one(&mut *conn)
+    .await?;
+
+    sqlx::query!(
+        r#"
+        INSERT INTO workspace_members (workspace_id, user_id, role, created_at)
+        VALUES ($1, $2, $3, $4)
+        "#,
+        workspace.id,
+        user_id,
+        WorkspaceRole::Owner.as_str(),
+        now
+    )
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(workspace)
+}
+
+// Role of the user in the workspace, outsiders get a not found error
+// Only tells about the membership, access_role also checks the 2FA requirement
+pub async fn member_role<'c>(
+    executor: impl PgExecutor<'c>,
+    workspace_id: Uuid,
+    user_id: Uuid,
+) -> Result<WorkspaceRole, AppError> {
+    let member = sqlx::query!(
+        r#"
+        SELECT role FROM workspace_members
+        WHERE workspace_id = $1 AND user_id = $2
+        "#,
+        workspace_id,
+        user_id
+    )
+    .fetch_optional(executor)
+    .await?
+    .ok_or_else(|| AppError::NotFoundError("Workspace not found".to_string()))?;
+
+    member.role.parse()
+}
+
+// Role of the user when acting in the workspace, members without the required 2FA are turned away
+pub async fn access_role<'c>(
+    executor: impl PgExecutor<'c>,
+    workspace_id: Uuid,
+    user_id: Uuid,
+) -> Result<WorkspaceRole, AppError> {
+    let member = sqlx::query!(
+        r#"
+        SELECT m.role, w.require_2fa, u.totp_enabled_at IS NOT NULL AS "has_two_factor!"
+        FROM workspace_members m
+        JOIN workspaces w ON w.id = m.workspace_id
+        JOIN users u ON u.id = m.user_id
+        WHERE m.workspace_id = $1 AND m.user_id = $2
+        "#,
+        workspace_id,
+        user_id
+    )
+    .fetch_optional(executor)
+    .await?
+    .ok_or_else(|| AppError::NotFoundError("Workspace not found".to_string()))?;
+
+    if member.require_2fa && !member.has_two_factor {
+        return Err(AppError::ForbiddenError(
+            "This workspace requires two-factor authentication, enable it on your account first"
+                .to_string(),
+        ));
+    }
+
+    member.role.parse()
+}
+
+// Fail unless the role of the user in the workspace grants the permission
+pub async fn authorize<'c>(
+    executor: impl PgExecutor<'c>,
+    workspace_id: Uuid,
+    user_id: Uuid,
+    permission: Permission,
+) -> Result<WorkspaceRole, AppError> {
+    let role = access_role(executor, workspace_id, user_id).await?;
+
+    if !role.can(permission) {
+        return Err(AppError::ForbiddenError(format!(
+            "The {} role is not allowed to do this",
+            role.as_str()
+        )));
+    }
+
+    Ok(role)
+}
+
+// The workspace new items go to, the personal workspace of the user unless one is given
+pub async fn target_workspace<'c>(
+    executor: impl PgExecutor<'c>,
+    workspace_id: Option<Uuid>,
+    user_id: Uuid,
+) -> Result<Uuid, AppError> {
+    let workspace = sqlx::query!(
+        r#"
+        SELECT w.id FROM workspaces w
+        JOIN workspace_members m ON m.workspace_id = w.id
+        WHERE m.user_id = $1
+          AND (w.id = $2 OR ($2::UUID IS NULL AND w.is_personal AND m.role = $3))
+        ORDER BY w.created_at ASC
+        LIMIT 1
+        "#,
+        user_id,
+        workspace_id,
+        WorkspaceRole::Owner.as_str()
+    )
+    .fetch_optional(executor)
+    .await?
+    .ok_or_else(|| AppError::NotFoundError("Workspace not found".to_string()))?;
+
+    Ok(workspace.id)
+}